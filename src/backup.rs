@@ -0,0 +1,219 @@
+use log::{debug, info, warn};
+use regex::Regex;
+use rusqlite::{
+    Connection, OpenFlags,
+    backup::{Backup, Progress, StepResult},
+};
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::migration::{check_version, migrate};
+
+// Number of pages copied at each backup step.
+const BACKUP_PAGES_PER_STEP: i32 = 256;
+// Pause when the database is busy or locked by another connection.
+const BACKUP_BUSY_PAUSE: Duration = Duration::from_millis(250);
+
+static SNAPSHOT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^chimitheque_(?P<timestamp>[0-9]+)\.db$").unwrap());
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BackupError {
+    IntegrityCheckFailed(Vec<String>),
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            BackupError::IntegrityCheckFailed(errors) => {
+                write!(f, "integrity check failed: {}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+// Copy the source database into the destination one, step by step,
+// so that other connections can keep reading and writing the source.
+fn run_backup<F>(
+    source: &Connection,
+    destination: &mut Connection,
+    mut progress: F,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(Progress),
+{
+    let backup = Backup::new(source, destination)?;
+
+    loop {
+        match backup.step(BACKUP_PAGES_PER_STEP)? {
+            StepResult::Done => {
+                progress(backup.progress());
+                break;
+            }
+            StepResult::More => progress(backup.progress()),
+            // Busy or locked.
+            _ => thread::sleep(BACKUP_BUSY_PAUSE),
+        }
+    }
+
+    Ok(())
+}
+
+// Backup the database into the given file with the SQLite online backup API.
+// The progress callback is called after each step.
+// The backup is written into a temporary file renamed at the end,
+// so an existing file is only replaced by a complete backup.
+// The temporary file is removed when the backup fails.
+pub fn backup_to<P, F>(
+    db_connection: &Connection,
+    backup_path: P,
+    progress: F,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    P: AsRef<Path>,
+    F: FnMut(Progress),
+{
+    let backup_path = backup_path.as_ref();
+    let mut tmp_path = backup_path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    info!("backing up database to {}", backup_path.display());
+
+    let mayerr_backup = Connection::open(&tmp_path)
+        .map_err(Into::into)
+        .and_then(|mut backup_connection| {
+            run_backup(db_connection, &mut backup_connection, progress)
+        })
+        .and_then(|()| fs::rename(&tmp_path, backup_path).map_err(Into::into));
+
+    if mayerr_backup.is_err() && tmp_path.exists() {
+        info!("removing temporary backup {}", tmp_path.display());
+        if let Err(err) = fs::remove_file(&tmp_path) {
+            warn!("can not remove {}: {err}", tmp_path.display());
+        }
+    }
+
+    mayerr_backup
+}
+
+// Restore the database from the given backup file.
+// The backup must pass the integrity check and must not be more recent
+// than the library. It is then migrated to the latest version.
+// Return the database version.
+pub fn restore_from<P>(
+    db_connection: &mut Connection,
+    backup_path: P,
+) -> Result<u32, Box<dyn std::error::Error + Send + Sync>>
+where
+    P: AsRef<Path>,
+{
+    let backup_path = backup_path.as_ref();
+
+    info!("restoring database from {}", backup_path.display());
+
+    let backup_connection = Connection::open_with_flags(
+        backup_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    let integrity_errors = backup_connection
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    debug!("integrity_errors: {integrity_errors:?}");
+
+    if integrity_errors != ["ok"] {
+        return Err(Box::new(BackupError::IntegrityCheckFailed(
+            integrity_errors,
+        )));
+    }
+
+    check_version(&backup_connection)?;
+
+    run_backup(&backup_connection, db_connection, |_| {})?;
+
+    migrate(db_connection)
+}
+
+// Return the snapshots of the directory, most recent first.
+pub fn list_snapshots<P>(
+    snapshot_dir: P,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>>
+where
+    P: AsRef<Path>,
+{
+    let mut snapshots: Vec<(u64, PathBuf)> = Vec::new();
+
+    for entry in fs::read_dir(snapshot_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+
+        let Some(captures) = file_name
+            .to_str()
+            .and_then(|name| SNAPSHOT_RE.captures(name))
+        else {
+            continue;
+        };
+
+        let timestamp: u64 = captures["timestamp"].parse()?;
+        snapshots.push((timestamp, entry.path()));
+    }
+
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+}
+
+// Backup the database into a new chimitheque_{timestamp}.db snapshot
+// of the directory, then delete the oldest snapshots to keep at most
+// `retention` of them (the new one included).
+// The timestamp is in milliseconds, and bumped when a snapshot with the
+// same name already exists.
+// Return the path of the new snapshot.
+pub fn create_snapshot<P>(
+    db_connection: &Connection,
+    snapshot_dir: P,
+    retention: usize,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>>
+where
+    P: AsRef<Path>,
+{
+    let snapshot_dir = snapshot_dir.as_ref();
+    fs::create_dir_all(snapshot_dir)?;
+
+    let mut timestamp: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)?
+        .as_millis()
+        .try_into()?;
+    let mut snapshot_path = snapshot_dir.join(format!("chimitheque_{timestamp}.db"));
+    while snapshot_path.exists() {
+        timestamp += 1;
+        snapshot_path = snapshot_dir.join(format!("chimitheque_{timestamp}.db"));
+    }
+
+    backup_to(db_connection, &snapshot_path, |_| {})?;
+
+    for old_snapshot in list_snapshots(snapshot_dir)?
+        .into_iter()
+        .skip(retention.max(1))
+    {
+        info!("removing snapshot {}", old_snapshot.display());
+        fs::remove_file(old_snapshot)?;
+    }
+
+    Ok(snapshot_path)
+}
+
+#[cfg(test)]
+#[path = "backup_tests.rs"]
+mod backup_tests;
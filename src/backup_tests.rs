@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::backup::*;
+    use crate::migration::{MigrationError, latest_version};
+    use rusqlite::Connection;
+
+    fn init_test_backup() -> Connection {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute(
+                "INSERT INTO person (person_id, person_email) VALUES (1, 'person1@example.com')",
+                [],
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    fn count_persons(db_connection: &Connection) -> u64 {
+        db_connection
+            .query_row("SELECT COUNT(*) FROM person", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let db_connection = init_test_backup();
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+
+        let mut nb_progress_calls = 0;
+        backup_to(&db_connection, &backup_path, |progress| {
+            nb_progress_calls += 1;
+            assert!(progress.remaining <= progress.pagecount);
+        })
+        .unwrap();
+
+        assert!(nb_progress_calls > 0);
+        assert!(!backup_dir.path().join("backup.db.tmp").exists());
+
+        let backup_connection = Connection::open(&backup_path).unwrap();
+        assert_eq!(count_persons(&backup_connection), 1);
+    }
+
+    #[test]
+    fn test_backup_to_error() {
+        let db_connection = init_test_backup();
+
+        // The backup can not replace a non empty directory.
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+        std::fs::create_dir(&backup_path).unwrap();
+        std::fs::write(backup_path.join("file"), "").unwrap();
+
+        assert!(backup_to(&db_connection, &backup_path, |_| {}).is_err());
+        assert!(!backup_dir.path().join("backup.db.tmp").exists());
+    }
+
+    #[test]
+    fn test_restore_from() {
+        let db_connection = init_test_backup();
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+        backup_to(&db_connection, &backup_path, |_| {}).unwrap();
+
+        let mut restored_connection = crate::connection::ConnectOptions::new()
+            .open_in_memory()
+            .unwrap();
+        let version = restore_from(&mut restored_connection, &backup_path).unwrap();
+
+        assert_eq!(version, latest_version());
+        assert_eq!(count_persons(&restored_connection), 1);
+    }
+
+    #[test]
+    fn test_restore_from_too_recent() {
+        let db_connection = init_test_backup();
+        db_connection
+            .pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+        backup_to(&db_connection, &backup_path, |_| {}).unwrap();
+
+        let mut restored_connection = crate::test_utils::init_test();
+        let err = restore_from(&mut restored_connection, &backup_path).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<MigrationError>(),
+            Some(&MigrationError::DatabaseTooRecent {
+                database_version: latest_version() + 1,
+                library_version: latest_version(),
+            })
+        );
+        // The database has not been modified.
+        assert_eq!(count_persons(&restored_connection), 0);
+    }
+
+    #[test]
+    fn test_restore_from_not_a_database() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+        std::fs::write(&backup_path, "not a database").unwrap();
+
+        let mut restored_connection = crate::test_utils::init_test();
+        assert!(restore_from(&mut restored_connection, &backup_path).is_err());
+    }
+
+    #[test]
+    fn test_create_snapshot() {
+        let db_connection = init_test_backup();
+
+        let snapshot_dir = tempfile::tempdir().unwrap();
+
+        // Older snapshots.
+        for timestamp in [1, 2, 3] {
+            std::fs::write(
+                snapshot_dir
+                    .path()
+                    .join(format!("chimitheque_{timestamp}.db")),
+                "",
+            )
+            .unwrap();
+        }
+        // Not a snapshot.
+        std::fs::write(snapshot_dir.path().join("other.db"), "").unwrap();
+
+        let snapshot_path = create_snapshot(&db_connection, snapshot_dir.path(), 2).unwrap();
+
+        let snapshots = list_snapshots(snapshot_dir.path()).unwrap();
+        assert_eq!(
+            snapshots,
+            vec![
+                snapshot_path.clone(),
+                snapshot_dir.path().join("chimitheque_3.db")
+            ]
+        );
+        assert!(snapshot_dir.path().join("other.db").exists());
+
+        let snapshot_connection = Connection::open(&snapshot_path).unwrap();
+        assert_eq!(count_persons(&snapshot_connection), 1);
+    }
+
+    #[test]
+    fn test_create_snapshot_same_time() {
+        let db_connection = init_test_backup();
+
+        let snapshot_dir = tempfile::tempdir().unwrap();
+
+        let snapshot_paths: Vec<_> = (0..3)
+            .map(|_| create_snapshot(&db_connection, snapshot_dir.path(), 3).unwrap())
+            .collect();
+
+        // Each snapshot has its own file, even when taken in the same millisecond.
+        let mut snapshots = list_snapshots(snapshot_dir.path()).unwrap();
+        snapshots.reverse();
+        assert_eq!(snapshots, snapshot_paths);
+    }
+}
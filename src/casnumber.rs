@@ -1,6 +1,14 @@
 use chimitheque_types::casnumber::CasNumber as CasNumberStruct;
+use chimitheque_utils::casnumber::is_cas_number;
+use csv::ReaderBuilder;
+use log::{debug, info, warn};
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
 use sea_query::Iden;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Iden)]
@@ -9,7 +17,311 @@ pub enum CasNumber {
     CasNumberId,
     CasNumberLabel,
     CasNumberCmr,
+    CasNumberCmrSource,
 }
 
 #[derive(Debug, Serialize, Default)]
 pub struct CasNumberWrapper(pub CasNumberStruct);
+
+//
+// CMR import.
+//
+
+static CAS_NUMBER_RE: std::sync::LazyLock<Regex> =
+    std::sync::LazyLock::new(|| Regex::new(r"\b[0-9]{2,7}-[0-9]{2}-[0-9]\b").unwrap());
+
+// Carc. 1A, Muta. 2, Repr. 1B... and Lact. (effects on or via lactation) without category.
+static CMR_CLASS_RE: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
+    Regex::new(r"(?P<class>Carc|Muta|Repr)\.\s*(?P<category>1A|1B|2)\b|(?P<lactation>Lact)\.")
+        .unwrap()
+});
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CmrImportError {
+    MissingColumns,
+}
+
+impl Display for CmrImportError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            CmrImportError::MissingColumns => {
+                write!(f, "CAS number or hazard class column not found")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CmrImportError {}
+
+// The most severe carcinogenic, mutagenic and reprotoxic categories of a substance.
+// Categories are "1A", "1B" or "2", the lowest is the most severe.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct CmrCategories {
+    carcinogenic: Option<String>,
+    mutagenic: Option<String>,
+    reprotoxic: Option<String>,
+    // Effects on or via lactation.
+    lactation: bool,
+}
+
+impl CmrCategories {
+    fn add(&mut self, class: &str, category: &str) {
+        let current = match class {
+            "Carc" => &mut self.carcinogenic,
+            "Muta" => &mut self.mutagenic,
+            _ => &mut self.reprotoxic,
+        };
+
+        if current.as_deref().is_none_or(|current| category < current) {
+            *current = Some(category.to_string());
+        }
+    }
+
+    fn merge(&mut self, other: &CmrCategories) {
+        for (class, category) in [
+            ("Carc", &other.carcinogenic),
+            ("Muta", &other.mutagenic),
+            ("Repr", &other.reprotoxic),
+        ] {
+            if let Some(category) = category {
+                self.add(class, category);
+            }
+        }
+
+        self.lactation |= other.lactation;
+    }
+
+    // Format as "C1B M2 R1B LACT", None if not CMR.
+    fn to_cmr(&self) -> Option<String> {
+        let mut cmr = [
+            ("C", &self.carcinogenic),
+            ("M", &self.mutagenic),
+            ("R", &self.reprotoxic),
+        ]
+        .iter()
+        .filter_map(|(prefix, category)| {
+            category
+                .as_ref()
+                .map(|category| format!("{prefix}{category}"))
+        })
+        .collect::<Vec<_>>();
+
+        if self.lactation {
+            cmr.push("LACT".to_string());
+        }
+
+        let cmr = cmr.join(" ");
+
+        if cmr.is_empty() { None } else { Some(cmr) }
+    }
+}
+
+// An existing product whose CAS number CMR category changed.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct CmrProductChange {
+    pub product_id: u64,
+    pub cas_number_label: String,
+    pub old_cmr: Option<String>,
+    pub new_cmr: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CmrImportReport {
+    // CAS numbers created.
+    pub added: Vec<String>,
+    // Existing CAS numbers whose CMR category changed.
+    pub updated: usize,
+    // CAS numbers of the file failing the checksum.
+    pub invalid_cas_numbers: Vec<String>,
+    pub changed_products: Vec<CmrProductChange>,
+}
+
+// Parse an ECHA CLP Annex VI table export (CSV, comma or semicolon separated).
+// The header line is the first one with a "CAS" and a "Hazard Class" column,
+// title lines before it are skipped.
+// A cell may contain several CAS numbers and several hazard class codes.
+// Return the CMR categories by CAS number, None for the non CMR substances,
+// and the invalid CAS numbers.
+fn parse_echa_annex_vi(
+    content: &str,
+) -> Result<(BTreeMap<String, Option<String>>, Vec<String>), Box<dyn std::error::Error + Send + Sync>>
+{
+    let is_header = |line: &str| {
+        let line = line.to_lowercase();
+        line.contains("cas") && line.contains("hazard class")
+    };
+
+    let Some(header_line) = content.lines().find(|line| is_header(line)) else {
+        return Err(Box::new(CmrImportError::MissingColumns));
+    };
+
+    let delimiter = if header_line.matches(';').count() > header_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(content.as_bytes());
+
+    let mut columns: Option<(usize, usize)> = None;
+    let mut categories_by_cas: BTreeMap<String, CmrCategories> = BTreeMap::new();
+    let mut invalid_cas_numbers = vec![];
+
+    for mayerr_record in reader.records() {
+        let record = mayerr_record?;
+
+        let Some((cas_column, class_column)) = columns else {
+            let cas_column = record
+                .iter()
+                .position(|field| field.to_lowercase().contains("cas"));
+            let class_column = record
+                .iter()
+                .position(|field| field.to_lowercase().contains("hazard class"));
+
+            if let (Some(cas_column), Some(class_column)) = (cas_column, class_column) {
+                debug!("cas_column: {cas_column} class_column: {class_column}");
+                columns = Some((cas_column, class_column));
+            }
+
+            continue;
+        };
+
+        let (Some(cas_field), Some(class_field)) =
+            (record.get(cas_column), record.get(class_column))
+        else {
+            continue;
+        };
+
+        let mut categories = CmrCategories::default();
+        for captures in CMR_CLASS_RE.captures_iter(class_field) {
+            if captures.name("lactation").is_some() {
+                categories.lactation = true;
+            } else {
+                categories.add(&captures["class"], &captures["category"]);
+            }
+        }
+
+        for cas_match in CAS_NUMBER_RE.find_iter(cas_field) {
+            let cas = cas_match.as_str();
+
+            if is_cas_number(cas).is_err() {
+                warn!("invalid CAS number: {cas}");
+                invalid_cas_numbers.push(cas.to_string());
+                continue;
+            }
+
+            categories_by_cas
+                .entry(cas.to_string())
+                .or_default()
+                .merge(&categories);
+        }
+    }
+
+    if columns.is_none() {
+        return Err(Box::new(CmrImportError::MissingColumns));
+    }
+
+    Ok((
+        categories_by_cas
+            .into_iter()
+            .map(|(cas, categories)| (cas, categories.to_cmr()))
+            .collect(),
+        invalid_cas_numbers,
+    ))
+}
+
+// Update the CAS numbers CMR categories from an ECHA CLP Annex VI CSV export.
+// The source is the revision of the export, for example "ATP 21".
+// CMR CAS numbers missing from the database are created.
+// CAS numbers of the file that are not CMR any more are cleared,
+// CAS numbers missing from the file are left untouched.
+pub fn import_cmr_from_echa_csv(
+    db_connection: &mut Connection,
+    path: &Path,
+    source: &str,
+) -> Result<CmrImportReport, Box<dyn std::error::Error + Send + Sync>> {
+    info!("importing CMR categories from {}", path.display());
+
+    let content = std::fs::read_to_string(path)?;
+    let (cmr_by_cas, invalid_cas_numbers) = parse_echa_annex_vi(&content)?;
+
+    let mut report = CmrImportReport {
+        invalid_cas_numbers,
+        ..Default::default()
+    };
+
+    let tx = db_connection.transaction()?;
+
+    for (cas_number_label, new_cmr) in cmr_by_cas {
+        let maybe_cas_number: Option<(u64, Option<String>)> = tx
+            .query_row(
+                "SELECT cas_number_id, cas_number_cmr FROM cas_number WHERE cas_number_label = ?1",
+                [&cas_number_label],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let Some((cas_number_id, old_cmr)) = maybe_cas_number else {
+            if new_cmr.is_some() {
+                tx.execute(
+                    "INSERT INTO cas_number (cas_number_label, cas_number_cmr, cas_number_cmr_source) VALUES (?1, ?2, ?3)",
+                    (&cas_number_label, &new_cmr, source),
+                )?;
+                report.added.push(cas_number_label);
+            }
+
+            continue;
+        };
+
+        if old_cmr == new_cmr {
+            if new_cmr.is_some() {
+                tx.execute(
+                    "UPDATE cas_number SET cas_number_cmr_source = ?1 WHERE cas_number_id = ?2",
+                    (source, cas_number_id),
+                )?;
+            }
+
+            continue;
+        }
+
+        debug!("{cas_number_label}: {old_cmr:?} -> {new_cmr:?}");
+
+        tx.execute(
+            "UPDATE cas_number SET cas_number_cmr = ?1, cas_number_cmr_source = ?2 WHERE cas_number_id = ?3",
+            (&new_cmr, source, cas_number_id),
+        )?;
+        report.updated += 1;
+
+        let product_ids = tx
+            .prepare("SELECT product_id FROM product WHERE cas_number = ?1 ORDER BY product_id")?
+            .query_map([cas_number_id], |row| row.get::<_, u64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for product_id in product_ids {
+            report.changed_products.push(CmrProductChange {
+                product_id,
+                cas_number_label: cas_number_label.clone(),
+                old_cmr: old_cmr.clone(),
+                new_cmr: new_cmr.clone(),
+            });
+        }
+    }
+
+    tx.commit()?;
+
+    report
+        .changed_products
+        .sort_by_key(|change| change.product_id);
+
+    debug!("report: {report:#?}");
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "casnumber_tests.rs"]
+mod casnumber_tests;
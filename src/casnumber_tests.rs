@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::casnumber::*;
+    use rusqlite::Connection;
+
+    fn cmr(db_connection: &Connection, cas_number_label: &str) -> (Option<String>, Option<String>) {
+        db_connection
+            .query_row(
+                "SELECT cas_number_cmr, cas_number_cmr_source FROM cas_number WHERE cas_number_label = ?1",
+                [cas_number_label],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_import_cmr_from_echa_csv() {
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO cas_number (cas_number_id, cas_number_label, cas_number_cmr) VALUES
+                    (1, '71-43-2', 'C1A'), (2, '64-17-5', 'R2'), (3, '7732-18-5', NULL);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'BENZENE'), (2, 'ETHANOL');
+                INSERT INTO product (product_id, product_type, name, cas_number) VALUES
+                    (1, 'chem', 1, 1), (2, 'chem', 2, 2), (3, 'chem', 1, 1);",
+            )
+            .unwrap();
+
+        let csv_dir = tempfile::tempdir().unwrap();
+        let csv_path = csv_dir.path().join("annex_vi.csv");
+        std::fs::write(
+            &csv_path,
+            "Annex VI to CLP,,,,\n\
+            Index No;International Chemical Identification;EC No;CAS No;Hazard Class and Category Code(s)\n\
+            601-020-00-8;benzene;200-753-7;71-43-2;\"Flam. Liq. 2\nCarc. 1A\nMuta. 1B\nSTOT RE 1\"\n\
+            603-002-00-5;ethanol;200-578-6;64-17-5;Flam. Liq. 2\n\
+            605-001-00-5;formaldehyde;200-001-8;50-00-0;\"Carc. 1B\nMuta. 2\"\n\
+            605-001-01-2;formaldehyde ...%;200-001-8;\"50-00-0 [1]\n64-17-6 [2]\";Carc. 2\n",
+        )
+        .unwrap();
+
+        let report = import_cmr_from_echa_csv(&mut db_connection, &csv_path, "ATP 21").unwrap();
+
+        assert_eq!(report.added, vec!["50-00-0".to_string()]);
+        assert_eq!(report.updated, 2);
+        assert_eq!(report.invalid_cas_numbers, vec!["64-17-6".to_string()]);
+        assert_eq!(
+            report.changed_products,
+            vec![
+                CmrProductChange {
+                    product_id: 1,
+                    cas_number_label: "71-43-2".to_string(),
+                    old_cmr: Some("C1A".to_string()),
+                    new_cmr: Some("C1A M1B".to_string()),
+                },
+                CmrProductChange {
+                    product_id: 2,
+                    cas_number_label: "64-17-5".to_string(),
+                    old_cmr: Some("R2".to_string()),
+                    new_cmr: None,
+                },
+                CmrProductChange {
+                    product_id: 3,
+                    cas_number_label: "71-43-2".to_string(),
+                    old_cmr: Some("C1A".to_string()),
+                    new_cmr: Some("C1A M1B".to_string()),
+                },
+            ]
+        );
+
+        assert_eq!(
+            cmr(&db_connection, "50-00-0"),
+            (Some("C1B M2".to_string()), Some("ATP 21".to_string()))
+        );
+        assert_eq!(
+            cmr(&db_connection, "64-17-5"),
+            (None, Some("ATP 21".to_string()))
+        );
+        // Not in the file.
+        assert_eq!(cmr(&db_connection, "7732-18-5"), (None, None));
+
+        // Importing the same file again changes nothing.
+        let report = import_cmr_from_echa_csv(&mut db_connection, &csv_path, "ATP 21").unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(report.updated, 0);
+        assert!(report.changed_products.is_empty());
+    }
+
+    #[test]
+    fn test_parse_echa_annex_vi_lactation() {
+        let (cmr_by_cas, _) = parse_echa_annex_vi(
+            "Index No;International Chemical Identification;EC No;CAS No;Hazard Class and Category Code(s)\n\
+            607-624-00-8;perfluorooctane sulfonic acid;217-179-8;1763-23-1;\"Carc. 2\nRepr. 1B\nLact.\nSTOT RE 1\"\n\
+            082-001-00-6;lead compounds;;7439-92-1;\"Repr. 1A\nLact.\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            cmr_by_cas.get("1763-23-1"),
+            Some(&Some("C2 R1B LACT".to_string()))
+        );
+        assert_eq!(
+            cmr_by_cas.get("7439-92-1"),
+            Some(&Some("R1A LACT".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_import_cmr_from_echa_csv_missing_columns() {
+        let mut db_connection = crate::test_utils::init_test();
+
+        let csv_dir = tempfile::tempdir().unwrap();
+        let csv_path = csv_dir.path().join("annex_vi.csv");
+        std::fs::write(&csv_path, "Index No,Chemical name\n601-020-00-8,benzene\n").unwrap();
+
+        let err = import_cmr_from_echa_csv(&mut db_connection, &csv_path, "ATP 21").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CmrImportError>(),
+            Some(&CmrImportError::MissingColumns)
+        );
+    }
+}
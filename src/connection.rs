@@ -0,0 +1,266 @@
+use log::{debug, info};
+use rusqlite::{Connection, OpenFlags};
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    migration::{MigrationError, current_version, latest_version},
+    sqlfunctions::register_functions,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ConnectError {
+    Sqlite(rusqlite::Error),
+    JournalMode { expected: JournalMode, got: String },
+    Migration(MigrationError),
+}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ConnectError::Sqlite(err) => write!(f, "sqlite error: {err}"),
+            ConnectError::JournalMode { expected, got } => {
+                write!(f, "failed to set journal mode {expected}, got: {got}")
+            }
+            ConnectError::Migration(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectError::Sqlite(err) => Some(err),
+            ConnectError::Migration(err) => Some(err),
+            ConnectError::JournalMode { .. } => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ConnectError {
+    fn from(err: rusqlite::Error) -> Self {
+        ConnectError::Sqlite(err)
+    }
+}
+
+// https://sqlite.org/pragma.html#pragma_journal_mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+impl Display for JournalMode {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            JournalMode::Delete => write!(f, "delete"),
+            JournalMode::Truncate => write!(f, "truncate"),
+            JournalMode::Persist => write!(f, "persist"),
+            JournalMode::Memory => write!(f, "memory"),
+            JournalMode::Wal => write!(f, "wal"),
+            JournalMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+// https://sqlite.org/pragma.html#pragma_synchronous
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Display for Synchronous {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Synchronous::Off => write!(f, "OFF"),
+            Synchronous::Normal => write!(f, "NORMAL"),
+            Synchronous::Full => write!(f, "FULL"),
+            Synchronous::Extra => write!(f, "EXTRA"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    journal_mode: JournalMode,
+    synchronous: Synchronous,
+    // Negative values are in KiB, positive values in pages.
+    cache_size: i64,
+    busy_timeout: Duration,
+    read_only: bool,
+    // Run VACUUM and ANALYZE after opening the database.
+    run_maintenance: bool,
+    // Directory of the optional sqlite-regex extension (regex0.so).
+    sql_extension_dir: Option<PathBuf>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            journal_mode: JournalMode::Wal,
+            synchronous: Synchronous::Normal,
+            cache_size: -65536,
+            busy_timeout: Duration::from_secs(5),
+            read_only: false,
+            run_maintenance: false,
+            sql_extension_dir: env::var_os("SQLITE_EXTENSION_DIR").map(PathBuf::from),
+        }
+    }
+}
+
+impl ConnectOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        ConnectOptions::default()
+    }
+
+    #[must_use]
+    pub fn journal_mode(mut self, journal_mode: JournalMode) -> Self {
+        self.journal_mode = journal_mode;
+        self
+    }
+
+    #[must_use]
+    pub fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.synchronous = synchronous;
+        self
+    }
+
+    #[must_use]
+    pub fn cache_size(mut self, cache_size: i64) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    #[must_use]
+    pub fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    #[must_use]
+    pub fn run_maintenance(mut self, run_maintenance: bool) -> Self {
+        self.run_maintenance = run_maintenance;
+        self
+    }
+
+    #[must_use]
+    pub fn sql_extension_dir(mut self, sql_extension_dir: Option<PathBuf>) -> Self {
+        self.sql_extension_dir = sql_extension_dir;
+        self
+    }
+
+    pub fn open<P: AsRef<Path>>(&self, db_path: P) -> Result<Connection, ConnectError> {
+        let flags = if self.read_only {
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_FULL_MUTEX
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_FULL_MUTEX
+        };
+
+        let db_connection = Connection::open_with_flags(db_path, flags)?;
+
+        self.setup(&db_connection, false)?;
+
+        Ok(db_connection)
+    }
+
+    // In memory databases ignore the journal mode, read only and maintenance options.
+    pub fn open_in_memory(&self) -> Result<Connection, ConnectError> {
+        let db_connection = Connection::open_in_memory()?;
+
+        self.setup(&db_connection, true)?;
+
+        Ok(db_connection)
+    }
+
+    fn setup(&self, db_connection: &Connection, in_memory: bool) -> Result<(), ConnectError> {
+        debug!("connect options: {self:?}");
+
+        self.register_sql_functions(db_connection)?;
+
+        // The journal mode of a read only database can not be changed.
+        if !in_memory && !self.read_only {
+            let mode: String = db_connection.pragma_update_and_check(
+                None,
+                "journal_mode",
+                self.journal_mode.to_string(),
+                |row| row.get(0),
+            )?;
+
+            if !mode.eq_ignore_ascii_case(&self.journal_mode.to_string()) {
+                return Err(ConnectError::JournalMode {
+                    expected: self.journal_mode,
+                    got: mode,
+                });
+            }
+        }
+
+        db_connection.pragma_update(None, "foreign_keys", true)?;
+        db_connection.pragma_update(None, "synchronous", self.synchronous.to_string())?;
+        db_connection.pragma_update(None, "cache_size", self.cache_size)?;
+        // Avoid disk I/O for temporary tables and indices.
+        db_connection.pragma_update(None, "temp_store", "MEMORY")?;
+        db_connection.busy_timeout(self.busy_timeout)?;
+
+        // Refuse databases created by a more recent version of the library.
+        let database_version = current_version(db_connection)?;
+        let library_version = latest_version();
+        if database_version > library_version {
+            return Err(ConnectError::Migration(MigrationError::DatabaseTooRecent {
+                database_version,
+                library_version,
+            }));
+        }
+
+        if self.run_maintenance && !in_memory && !self.read_only {
+            info!("running database maintenance");
+
+            db_connection.execute("VACUUM", [])?;
+            db_connection.execute("ANALYZE", [])?;
+        }
+
+        Ok(())
+    }
+
+    // Register the SQL functions.
+    // If an extension directory is set, the sqlite-regex extension is loaded
+    // and its regexp and regex_capture functions replace the Rust ones.
+    fn register_sql_functions(&self, db_connection: &Connection) -> Result<(), ConnectError> {
+        register_functions(db_connection)?;
+
+        if let Some(sql_extension_dir) = &self.sql_extension_dir {
+            let sql_extension_regex = sql_extension_dir.join("regex0.so");
+
+            info!("loading regexp extension {}", sql_extension_regex.display());
+
+            unsafe {
+                db_connection.load_extension(sql_extension_regex, None::<&str>)?;
+            };
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "connection_tests.rs"]
+mod connection_tests;
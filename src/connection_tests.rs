@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::connection::*;
+    use crate::migration::{MigrationError, latest_version};
+
+    fn init_test_connection() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_open_default() {
+        init_test_connection();
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("storage.db");
+
+        let db_connection = ConnectOptions::new().open(&db_path).unwrap();
+
+        let journal_mode: String = db_connection
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        let foreign_keys: bool = db_connection
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert!(foreign_keys);
+
+        let synchronous: i64 = db_connection
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1);
+    }
+
+    #[test]
+    fn test_open_options() {
+        init_test_connection();
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("storage.db");
+
+        let db_connection = ConnectOptions::new()
+            .journal_mode(JournalMode::Delete)
+            .synchronous(Synchronous::Full)
+            .cache_size(-1024)
+            .run_maintenance(true)
+            .open(&db_path)
+            .unwrap();
+
+        let journal_mode: String = db_connection
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "delete");
+
+        let synchronous: i64 = db_connection
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 2);
+
+        let cache_size: i64 = db_connection
+            .query_row("PRAGMA cache_size", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cache_size, -1024);
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        init_test_connection();
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("storage.db");
+
+        // The database must exist.
+        assert!(matches!(
+            ConnectOptions::new().read_only(true).open(&db_path),
+            Err(ConnectError::Sqlite(_))
+        ));
+
+        let db_connection = ConnectOptions::new().open(&db_path).unwrap();
+        db_connection
+            .execute("CREATE TABLE t (v TEXT)", [])
+            .unwrap();
+        drop(db_connection);
+
+        let db_connection = ConnectOptions::new()
+            .read_only(true)
+            .open(&db_path)
+            .unwrap();
+        assert!(
+            db_connection
+                .execute("INSERT INTO t VALUES ('a')", [])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_open_in_memory() {
+        init_test_connection();
+
+        let db_connection = ConnectOptions::new().open_in_memory().unwrap();
+
+        // Rust SQL functions are registered.
+        let is_match: bool = db_connection
+            .query_row("SELECT regexp('^a', 'abc')", [], |row| row.get(0))
+            .unwrap();
+        assert!(is_match);
+    }
+
+    #[test]
+    fn test_open_database_too_recent() {
+        init_test_connection();
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("storage.db");
+
+        let db_connection = ConnectOptions::new().open(&db_path).unwrap();
+        db_connection
+            .pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        drop(db_connection);
+
+        assert_eq!(
+            ConnectOptions::new().open(&db_path).unwrap_err(),
+            ConnectError::Migration(MigrationError::DatabaseTooRecent {
+                database_version: latest_version() + 1,
+                library_version: latest_version(),
+            })
+        );
+    }
+}
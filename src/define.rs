@@ -7,6 +7,14 @@ pub static PRECAUTIONARY_STATEMENT_RE: std::sync::LazyLock<Regex> =
 pub static HAZARD_STATEMENT_RE: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
     Regex::new(r"(?P<reference>(EU){0,1}H[0-9]+)(\t)(?P<label>[^\t]+)(\t)").unwrap()
 });
+// "H225 (100%): Highly Flammable liquid and vapor [Danger Flammable liquids]"
+// strings of the PubChem PUG View records.
+pub static PUBCHEM_GHS_STATEMENT_RE: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
+    Regex::new(
+            r"^(?P<reference>(EU)?H[0-9]+[A-Za-z]*(\+H[0-9]+[A-Za-z]*)*|P[0-9]+(\+P[0-9]+)*)(\s*\([^)]*\))?\s*:\s*(?P<label>.+?)(\s*\[[^\]]*\])?$",
+        )
+        .unwrap()
+});
 pub static STORAGE_BARECODE_RE: std::sync::LazyLock<Regex> =
     std::sync::LazyLock::new(|| Regex::new(r"([_a-zA-Z]+[0-9]+)\.[0-9]+").unwrap());
 
@@ -1,6 +1,14 @@
 use chimitheque_types::empiricalformula::EmpiricalFormula as EmpiricalFormulaStruct;
+use log::{debug, warn};
+use rusqlite::Connection;
 use sea_query::Iden;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::molecularweight::atomic_weight;
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Iden)]
@@ -12,3 +20,304 @@ pub enum EmpiricalFormula {
 
 #[derive(Debug, Serialize, Default)]
 pub struct EmpiricalFormulaWrapper(pub EmpiricalFormulaStruct);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormulaError {
+    Empty,
+    InvalidCharacter { character: char, position: usize },
+    UnbalancedParenthesis(usize),
+    CountOverflow,
+    // Not an element symbol, like the Ph or Me abbreviations.
+    UnknownElement { element: String, position: usize },
+}
+
+impl Display for FormulaError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            FormulaError::Empty => write!(f, "empty formula"),
+            FormulaError::InvalidCharacter {
+                character,
+                position,
+            } => write!(f, "invalid character {character} at position {position}"),
+            FormulaError::UnbalancedParenthesis(position) => {
+                write!(f, "unbalanced parenthesis at position {position}")
+            }
+            FormulaError::CountOverflow => write!(f, "element count too large"),
+            FormulaError::UnknownElement { element, position } => {
+                write!(f, "unknown element {element} at position {position}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+// Hydrate and adduct separators: CuSO4.5H2O, CuSO4·5H2O, CuSO4*5H2O.
+fn is_separator(c: char) -> bool {
+    matches!(c, '.' | '·' | '•' | '*')
+}
+
+fn parse_number(chars: &mut Peekable<CharIndices>) -> Result<Option<u32>, FormulaError> {
+    let mut maybe_number: Option<u32> = None;
+
+    while let Some(&(_, c)) = chars.peek() {
+        let Some(digit) = c.to_digit(10) else {
+            break;
+        };
+        chars.next();
+
+        maybe_number = Some(
+            maybe_number
+                .unwrap_or(0)
+                .checked_mul(10)
+                .and_then(|number| number.checked_add(digit))
+                .ok_or(FormulaError::CountOverflow)?,
+        );
+    }
+
+    Ok(maybe_number)
+}
+
+fn add_count(
+    counts: &mut BTreeMap<String, u32>,
+    element: &str,
+    count: u32,
+) -> Result<(), FormulaError> {
+    let current = counts.entry(element.to_string()).or_insert(0);
+    *current = current
+        .checked_add(count)
+        .ok_or(FormulaError::CountOverflow)?;
+
+    Ok(())
+}
+
+fn multiply_counts(
+    counts: &mut BTreeMap<String, u32>,
+    multiplier: u32,
+) -> Result<(), FormulaError> {
+    for count in counts.values_mut() {
+        *count = count
+            .checked_mul(multiplier)
+            .ok_or(FormulaError::CountOverflow)?;
+    }
+
+    Ok(())
+}
+
+// Parse a sequence of elements and groups until the end of the formula,
+// a separator or the closing parenthesis of the current group.
+fn parse_groups(
+    chars: &mut Peekable<CharIndices>,
+    closing: Option<(char, usize)>,
+) -> Result<BTreeMap<String, u32>, FormulaError> {
+    let mut counts = BTreeMap::new();
+
+    while let Some(&(position, c)) = chars.peek() {
+        match c {
+            'A'..='Z' => {
+                chars.next();
+
+                let mut element = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !c.is_ascii_lowercase() {
+                        break;
+                    }
+                    element.push(c);
+                    chars.next();
+                }
+
+                if atomic_weight(&element).is_none() {
+                    return Err(FormulaError::UnknownElement { element, position });
+                }
+
+                let count = parse_number(chars)?.unwrap_or(1);
+                add_count(&mut counts, &element, count)?;
+            }
+            '(' | '[' => {
+                chars.next();
+
+                let expected = if c == '(' { ')' } else { ']' };
+                let mut group_counts = parse_groups(chars, Some((expected, position)))?;
+
+                let multiplier = parse_number(chars)?.unwrap_or(1);
+                multiply_counts(&mut group_counts, multiplier)?;
+
+                for (element, count) in group_counts {
+                    add_count(&mut counts, &element, count)?;
+                }
+            }
+            ')' | ']' => {
+                return match closing {
+                    Some((expected, _)) if expected == c => {
+                        chars.next();
+                        Ok(counts)
+                    }
+                    _ => Err(FormulaError::UnbalancedParenthesis(position)),
+                };
+            }
+            c if is_separator(c) => break,
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                return Err(FormulaError::InvalidCharacter {
+                    character: c,
+                    position,
+                });
+            }
+        }
+    }
+
+    if let Some((_, position)) = closing {
+        return Err(FormulaError::UnbalancedParenthesis(position));
+    }
+
+    Ok(counts)
+}
+
+// Return the number of atoms of each element of the formula.
+// Groups can be nested with parentheses or brackets, and hydrates or adducts
+// written with a dot with an optional leading multiplier: CuSO4.5H2O.
+pub fn parse_element_counts(formula: &str) -> Result<BTreeMap<String, u32>, FormulaError> {
+    let mut counts = BTreeMap::new();
+    let mut chars = formula.char_indices().peekable();
+
+    loop {
+        // Leading multiplier of the part, 5 in 5H2O.
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        let multiplier = parse_number(&mut chars)?.unwrap_or(1);
+
+        let mut part_counts = parse_groups(&mut chars, None)?;
+        multiply_counts(&mut part_counts, multiplier)?;
+
+        for (element, count) in part_counts {
+            add_count(&mut counts, &element, count)?;
+        }
+
+        // Separator or end of the formula.
+        if chars.next().is_none() {
+            break;
+        }
+    }
+
+    counts.retain(|_, count| *count > 0);
+
+    if counts.is_empty() {
+        return Err(FormulaError::Empty);
+    }
+
+    Ok(counts)
+}
+
+// Write element counts in Hill order: carbon first, then hydrogen, then
+// the other elements alphabetically. Without carbon all the elements,
+// hydrogen included, are sorted alphabetically. Counts of 1 are omitted.
+#[must_use]
+pub fn hill_formula(counts: &BTreeMap<String, u32>) -> String {
+    let has_carbon = counts.contains_key("C");
+
+    // The counts are sorted alphabetically, the stable sort keeps this order.
+    let mut elements: Vec<(&str, u32)> = counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(element, count)| (element.as_str(), *count))
+        .collect();
+    elements.sort_by_key(|(element, _)| match *element {
+        "C" => 0,
+        "H" if has_carbon => 1,
+        _ => 2,
+    });
+
+    elements
+        .into_iter()
+        .map(|(element, count)| {
+            if count == 1 {
+                element.to_string()
+            } else {
+                format!("{element}{count}")
+            }
+        })
+        .collect()
+}
+
+// Expand a linear or empirical formula into a Hill ordered empirical formula:
+// CH3CH2OH gives C2H6O, CuSO4.5H2O gives CuH10O9S.
+pub fn to_hill_formula(formula: &str) -> Result<String, FormulaError> {
+    Ok(hill_formula(&parse_element_counts(formula)?))
+}
+
+// Replace the empirical_formula_element rows of the formula.
+// An invalid formula is logged and left without rows.
+// Return false if the formula is invalid.
+pub fn index_empirical_formula(
+    db_connection: &Connection,
+    empirical_formula_id: u64,
+    empirical_formula_label: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    db_connection.execute(
+        "DELETE FROM empirical_formula_element WHERE empirical_formula = ?1",
+        [empirical_formula_id],
+    )?;
+
+    let counts = match parse_element_counts(empirical_formula_label) {
+        Ok(counts) => counts,
+        Err(err) => {
+            warn!("skipping empirical formula {empirical_formula_label}: {err}");
+            return Ok(false);
+        }
+    };
+
+    for (element, count) in counts {
+        db_connection.execute(
+            "INSERT INTO empirical_formula_element (empirical_formula, element, element_count) VALUES (?1, ?2, ?3)",
+            (empirical_formula_id, element, count),
+        )?;
+    }
+
+    Ok(true)
+}
+
+// Fill the empirical_formula_element table for the formulas not indexed yet.
+// Called after the writes that create or rename formulas without going
+// through searchable::create_update (migrations, imports, sanitizing),
+// the rows being removed by a trigger when a formula label changes.
+// Invalid formulas are skipped and logged.
+// Return the number of indexed formulas.
+pub fn update_empirical_formula_elements(
+    db_connection: &Connection,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let sql = "SELECT empirical_formula_id, empirical_formula_label FROM empirical_formula
+        WHERE NOT EXISTS (SELECT 1 FROM empirical_formula_element
+            WHERE empirical_formula_element.empirical_formula = empirical_formula.empirical_formula_id)";
+
+    debug!("sql: {sql}");
+
+    let formulas = db_connection
+        .prepare(sql)?
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut nb_indexed = 0;
+
+    for (empirical_formula_id, empirical_formula_label) in formulas {
+        if index_empirical_formula(
+            db_connection,
+            empirical_formula_id,
+            &empirical_formula_label,
+        )? {
+            nb_indexed += 1;
+        }
+    }
+
+    debug!("nb_indexed: {nb_indexed}");
+
+    Ok(nb_indexed)
+}
+
+#[cfg(test)]
+#[path = "empiricalformula_tests.rs"]
+mod empiricalformula_tests;
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::empiricalformula::*;
+    use std::collections::BTreeMap;
+
+    fn counts(expected: &[(&str, u32)]) -> BTreeMap<String, u32> {
+        expected
+            .iter()
+            .map(|(element, count)| ((*element).to_string(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_element_counts() {
+        assert_eq!(
+            parse_element_counts("C2H6O").unwrap(),
+            counts(&[("C", 2), ("H", 6), ("O", 1)])
+        );
+        assert_eq!(
+            parse_element_counts("CH3COOH").unwrap(),
+            counts(&[("C", 2), ("H", 4), ("O", 2)])
+        );
+        assert_eq!(
+            parse_element_counts("Ca(OH)2").unwrap(),
+            counts(&[("Ca", 1), ("H", 2), ("O", 2)])
+        );
+        assert_eq!(
+            parse_element_counts("K4[Fe(CN)6]").unwrap(),
+            counts(&[("C", 6), ("Fe", 1), ("K", 4), ("N", 6)])
+        );
+        // Hydrates.
+        assert_eq!(
+            parse_element_counts("CuSO4.5H2O").unwrap(),
+            counts(&[("Cu", 1), ("H", 10), ("O", 9), ("S", 1)])
+        );
+        assert_eq!(
+            parse_element_counts("CuSO4·5H2O").unwrap(),
+            parse_element_counts("CuSO4 * 5 H2O").unwrap()
+        );
+
+        assert_eq!(parse_element_counts(""), Err(FormulaError::Empty));
+        assert_eq!(
+            parse_element_counts("C2H6O)"),
+            Err(FormulaError::UnbalancedParenthesis(5))
+        );
+        assert_eq!(
+            parse_element_counts("Ca(OH2"),
+            Err(FormulaError::UnbalancedParenthesis(2))
+        );
+        assert_eq!(
+            parse_element_counts("C2h6"),
+            Err(FormulaError::InvalidCharacter {
+                character: 'h',
+                position: 2
+            })
+        );
+        assert_eq!(
+            parse_element_counts("C99999999999"),
+            Err(FormulaError::CountOverflow)
+        );
+        // Abbreviations are not elements.
+        assert_eq!(
+            parse_element_counts("PhCH2OH"),
+            Err(FormulaError::UnknownElement {
+                element: "Ph".to_string(),
+                position: 0
+            })
+        );
+        assert_eq!(
+            parse_element_counts("CH3COOEt"),
+            Err(FormulaError::UnknownElement {
+                element: "Et".to_string(),
+                position: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_hill_formula() {
+        assert_eq!(to_hill_formula("CH3CH2OH").unwrap(), "C2H6O");
+        assert_eq!(to_hill_formula("OH6C2").unwrap(), "C2H6O");
+        assert_eq!(to_hill_formula("C6H5Br").unwrap(), "C6H5Br");
+        assert_eq!(to_hill_formula("CH3(CH2)4COOH").unwrap(), "C6H12O2");
+        // Without carbon, hydrogen is sorted alphabetically.
+        assert_eq!(to_hill_formula("H2SO4").unwrap(), "H2O4S");
+        assert_eq!(to_hill_formula("NaOH").unwrap(), "HNaO");
+        assert_eq!(to_hill_formula("CuSO4.5H2O").unwrap(), "CuH10O9S");
+        assert_eq!(
+            to_hill_formula("CH3("),
+            Err(FormulaError::UnbalancedParenthesis(3))
+        );
+        assert_eq!(
+            to_hill_formula("(CH3CO)2O.Bu"),
+            Err(FormulaError::UnknownElement {
+                element: "Bu".to_string(),
+                position: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_empirical_formula_elements() {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES
+                    (1, 'C2H6O'), (2, 'C6H5Br'), (3, 'not a formula');",
+            )
+            .unwrap();
+
+        assert_eq!(
+            update_empirical_formula_elements(&db_connection).unwrap(),
+            2
+        );
+        // Already indexed.
+        assert_eq!(
+            update_empirical_formula_elements(&db_connection).unwrap(),
+            0
+        );
+
+        // The rows of a formula are rebuilt when its label changes.
+        db_connection
+            .execute(
+                "UPDATE empirical_formula SET empirical_formula_label = 'C6H5Cl' WHERE empirical_formula_id = 2",
+                [],
+            )
+            .unwrap();
+        assert_eq!(
+            update_empirical_formula_elements(&db_connection).unwrap(),
+            1
+        );
+
+        let elements: Vec<(String, u32)> = db_connection
+            .prepare(
+                "SELECT element, element_count FROM empirical_formula_element WHERE empirical_formula = 2 ORDER BY element",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                ("C".to_string(), 6),
+                ("Cl".to_string(), 1),
+                ("H".to_string(), 5)
+            ]
+        );
+    }
+}
@@ -239,7 +239,7 @@ pub fn get_entities(
         )
         .group_by_col((Entity::Table, Entity::EntityId))
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(order_by)),
+            Expr::cust_with_expr("? COLLATE UNICODE_NOCASE", Expr::col(order_by)),
             order,
         )
         .conditions(
@@ -0,0 +1,765 @@
+use chimitheque_types::{
+    product::Product as ProductStruct, requestfilter::RequestFilter,
+    storage::Storage as StorageStruct,
+};
+use chrono::{DateTime, Utc};
+use csv::WriterBuilder;
+use log::debug;
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    io::Write,
+    str::FromStr,
+};
+
+use crate::{
+    product::{get_products, get_products_by_ids},
+    storage::get_storages,
+    xlsx::{XlsxCell, XlsxWriter},
+};
+
+// Number of products or storages fetched at once while exporting.
+const EXPORT_PAGE_SIZE: usize = 500;
+
+// Separator of the values of the list columns, as expected by the importers.
+const EXPORT_LIST_SEPARATOR: &str = ";";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExportError {
+    NoColumns,
+    UnknownColumn(String),
+    UnknownFormat(String),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ExportError::NoColumns => write!(f, "no columns to export"),
+            ExportError::UnknownColumn(s) => write!(f, "unknown column {s}"),
+            ExportError::UnknownFormat(s) => write!(f, "unknown export format {s}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    Xlsx,
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "jsonlines" => Ok(ExportFormat::JsonLines),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductExportColumn {
+    ProductId,
+    Name,
+    Synonyms,
+    Specificity,
+    ProductType,
+    CasNumber,
+    CeNumber,
+    EmpiricalFormula,
+    LinearFormula,
+    MolecularWeight,
+    PhysicalState,
+    SignalWord,
+    Symbols,
+    HazardStatements,
+    PrecautionaryStatements,
+    Cmr,
+    ClassesOfCompound,
+    Category,
+    Tags,
+    SupplierRefs,
+    ProducerRef,
+    Restricted,
+    Radioactive,
+    Inchi,
+    Inchikey,
+    CanonicalSmiles,
+    Temperature,
+    NumberPerCarton,
+    NumberPerBag,
+    Msds,
+    Sheet,
+    DisposalComment,
+    Remark,
+    Person,
+}
+
+impl ProductExportColumn {
+    pub const ALL: [ProductExportColumn; 34] = [
+        ProductExportColumn::ProductId,
+        ProductExportColumn::Name,
+        ProductExportColumn::Synonyms,
+        ProductExportColumn::Specificity,
+        ProductExportColumn::ProductType,
+        ProductExportColumn::CasNumber,
+        ProductExportColumn::CeNumber,
+        ProductExportColumn::EmpiricalFormula,
+        ProductExportColumn::LinearFormula,
+        ProductExportColumn::MolecularWeight,
+        ProductExportColumn::PhysicalState,
+        ProductExportColumn::SignalWord,
+        ProductExportColumn::Symbols,
+        ProductExportColumn::HazardStatements,
+        ProductExportColumn::PrecautionaryStatements,
+        ProductExportColumn::Cmr,
+        ProductExportColumn::ClassesOfCompound,
+        ProductExportColumn::Category,
+        ProductExportColumn::Tags,
+        ProductExportColumn::SupplierRefs,
+        ProductExportColumn::ProducerRef,
+        ProductExportColumn::Restricted,
+        ProductExportColumn::Radioactive,
+        ProductExportColumn::Inchi,
+        ProductExportColumn::Inchikey,
+        ProductExportColumn::CanonicalSmiles,
+        ProductExportColumn::Temperature,
+        ProductExportColumn::NumberPerCarton,
+        ProductExportColumn::NumberPerBag,
+        ProductExportColumn::Msds,
+        ProductExportColumn::Sheet,
+        ProductExportColumn::DisposalComment,
+        ProductExportColumn::Remark,
+        ProductExportColumn::Person,
+    ];
+
+    // Header of the column, the ones read by the product importer are the same.
+    #[must_use]
+    pub fn header(self) -> &'static str {
+        match self {
+            ProductExportColumn::ProductId => "PRODUCT_ID",
+            ProductExportColumn::Name => "NAME",
+            ProductExportColumn::Synonyms => "SYNONYMS",
+            ProductExportColumn::Specificity => "PRODUCT_SPECIFICITY",
+            ProductExportColumn::ProductType => "PRODUCT_TYPE",
+            ProductExportColumn::CasNumber => "CAS_NUMBER",
+            ProductExportColumn::CeNumber => "CE_NUMBER",
+            ProductExportColumn::EmpiricalFormula => "EMPIRICAL_FORMULA",
+            ProductExportColumn::LinearFormula => "LINEAR_FORMULA",
+            ProductExportColumn::MolecularWeight => "MOLECULAR_WEIGHT",
+            ProductExportColumn::PhysicalState => "PHYSICAL_STATE",
+            ProductExportColumn::SignalWord => "SIGNAL_WORD",
+            ProductExportColumn::Symbols => "SYMBOLS",
+            ProductExportColumn::HazardStatements => "HAZARD_STATEMENTS",
+            ProductExportColumn::PrecautionaryStatements => "PRECAUTIONARY_STATEMENTS",
+            ProductExportColumn::Cmr => "CMR",
+            ProductExportColumn::ClassesOfCompound => "CLASSES_OF_COMPOUND",
+            ProductExportColumn::Category => "CATEGORY",
+            ProductExportColumn::Tags => "TAGS",
+            ProductExportColumn::SupplierRefs => "SUPPLIER_REFS",
+            ProductExportColumn::ProducerRef => "PRODUCER_REF",
+            ProductExportColumn::Restricted => "RESTRICTED",
+            ProductExportColumn::Radioactive => "RADIOACTIVE",
+            ProductExportColumn::Inchi => "INCHI",
+            ProductExportColumn::Inchikey => "INCHIKEY",
+            ProductExportColumn::CanonicalSmiles => "CANONICAL_SMILES",
+            ProductExportColumn::Temperature => "TEMPERATURE",
+            ProductExportColumn::NumberPerCarton => "NUMBER_PER_CARTON",
+            ProductExportColumn::NumberPerBag => "NUMBER_PER_BAG",
+            ProductExportColumn::Msds => "MSDS",
+            ProductExportColumn::Sheet => "SHEET",
+            ProductExportColumn::DisposalComment => "DISPOSAL_COMMENT",
+            ProductExportColumn::Remark => "REMARK",
+            ProductExportColumn::Person => "PERSON",
+        }
+    }
+
+    // Header of the column in a storage export.
+    fn storage_header(self) -> &'static str {
+        match self {
+            ProductExportColumn::Name => "PRODUCT_NAME",
+            ProductExportColumn::Person => "PRODUCT_PERSON",
+            _ => self.header(),
+        }
+    }
+
+    #[must_use]
+    pub fn value(self, product: &ProductStruct) -> Value {
+        match self {
+            ProductExportColumn::ProductId => to_value(product.product_id),
+            ProductExportColumn::Name => Value::String(product.name.name_label.clone()),
+            ProductExportColumn::Synonyms => join(
+                product
+                    .synonyms
+                    .iter()
+                    .flatten()
+                    .map(|synonym| synonym.name_label.clone()),
+            ),
+            ProductExportColumn::Specificity => to_value(&product.product_specificity),
+            ProductExportColumn::ProductType => Value::String(product.product_type.to_string()),
+            ProductExportColumn::CasNumber => to_value(
+                product
+                    .cas_number
+                    .as_ref()
+                    .map(|cas_number| &cas_number.cas_number_label),
+            ),
+            ProductExportColumn::CeNumber => to_value(
+                product
+                    .ce_number
+                    .as_ref()
+                    .map(|ce_number| &ce_number.ce_number_label),
+            ),
+            ProductExportColumn::EmpiricalFormula => to_value(
+                product
+                    .empirical_formula
+                    .as_ref()
+                    .map(|empirical_formula| &empirical_formula.empirical_formula_label),
+            ),
+            ProductExportColumn::LinearFormula => to_value(
+                product
+                    .linear_formula
+                    .as_ref()
+                    .map(|linear_formula| &linear_formula.linear_formula_label),
+            ),
+            ProductExportColumn::MolecularWeight => to_value(product.product_molecular_weight),
+            ProductExportColumn::PhysicalState => to_value(
+                product
+                    .physical_state
+                    .as_ref()
+                    .map(|physical_state| &physical_state.physical_state_label),
+            ),
+            ProductExportColumn::SignalWord => to_value(
+                product
+                    .signal_word
+                    .as_ref()
+                    .map(|signal_word| &signal_word.signal_word_label),
+            ),
+            ProductExportColumn::Symbols => join(
+                product
+                    .symbols
+                    .iter()
+                    .flatten()
+                    .map(|symbol| symbol.symbol_label.clone()),
+            ),
+            ProductExportColumn::HazardStatements => join(
+                product
+                    .hazard_statements
+                    .iter()
+                    .flatten()
+                    .map(|hazard_statement| hazard_statement.hazard_statement_reference.clone()),
+            ),
+            ProductExportColumn::PrecautionaryStatements => {
+                join(product.precautionary_statements.iter().flatten().map(
+                    |precautionary_statement| {
+                        precautionary_statement
+                            .precautionary_statement_reference
+                            .clone()
+                    },
+                ))
+            }
+            ProductExportColumn::Cmr => Value::Bool(
+                product
+                    .cas_number
+                    .as_ref()
+                    .and_then(|cas_number| cas_number.cas_number_cmr.as_deref())
+                    .is_some_and(|cmr| !cmr.is_empty())
+                    || product
+                        .product_hs_cmr
+                        .as_deref()
+                        .is_some_and(|cmr| !cmr.is_empty()),
+            ),
+            ProductExportColumn::ClassesOfCompound => join(
+                product
+                    .classes_of_compound
+                    .iter()
+                    .flatten()
+                    .map(|class_of_compound| class_of_compound.class_of_compound_label.clone()),
+            ),
+            ProductExportColumn::Category => to_value(
+                product
+                    .category
+                    .as_ref()
+                    .map(|category| &category.category_label),
+            ),
+            ProductExportColumn::Tags => join(
+                product
+                    .tags
+                    .iter()
+                    .flatten()
+                    .map(|tag| tag.tag_label.clone()),
+            ),
+            ProductExportColumn::SupplierRefs => {
+                join(product.supplier_refs.iter().flatten().map(|supplier_ref| {
+                    format!(
+                        "{}:{}",
+                        supplier_ref.supplier.supplier_label, supplier_ref.supplier_ref_label
+                    )
+                }))
+            }
+            ProductExportColumn::ProducerRef => {
+                to_value(product.producer_ref.as_ref().map(|producer_ref| {
+                    format!(
+                        "{}:{}",
+                        producer_ref.producer.producer_label, producer_ref.producer_ref_label
+                    )
+                }))
+            }
+            ProductExportColumn::Restricted => to_value(product.product_restricted),
+            ProductExportColumn::Radioactive => to_value(product.product_radioactive),
+            ProductExportColumn::Inchi => to_value(&product.product_inchi),
+            ProductExportColumn::Inchikey => to_value(&product.product_inchikey),
+            ProductExportColumn::CanonicalSmiles => to_value(&product.product_canonical_smiles),
+            ProductExportColumn::Temperature => to_value(product.product_temperature),
+            ProductExportColumn::NumberPerCarton => to_value(product.product_number_per_carton),
+            ProductExportColumn::NumberPerBag => to_value(product.product_number_per_bag),
+            ProductExportColumn::Msds => to_value(&product.product_msds),
+            ProductExportColumn::Sheet => to_value(&product.product_sheet),
+            ProductExportColumn::DisposalComment => to_value(&product.product_disposal_comment),
+            ProductExportColumn::Remark => to_value(&product.product_remark),
+            ProductExportColumn::Person => to_value(&product.person.person_email),
+        }
+    }
+}
+
+impl FromStr for ProductExportColumn {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ProductExportColumn::ALL
+            .into_iter()
+            .find(|column| column.header().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ExportError::UnknownColumn(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageExportColumn {
+    StorageId,
+    Barecode,
+    Quantity,
+    Unit,
+    Concentration,
+    UnitConcentration,
+    Supplier,
+    // Store location full path, as read by the storage importer.
+    StoreLocation,
+    StoreLocationName,
+    Entity,
+    CreationDate,
+    ModificationDate,
+    EntryDate,
+    OpeningDate,
+    ExpirationDate,
+    ExitDate,
+    BatchNumber,
+    Reference,
+    Comment,
+    NumberOfBag,
+    NumberOfCarton,
+    ToDestroy,
+    Archive,
+    Person,
+    // Any column of the storage product.
+    Product(ProductExportColumn),
+}
+
+impl StorageExportColumn {
+    pub const ALL: [StorageExportColumn; 24] = [
+        StorageExportColumn::StorageId,
+        StorageExportColumn::Barecode,
+        StorageExportColumn::Quantity,
+        StorageExportColumn::Unit,
+        StorageExportColumn::Concentration,
+        StorageExportColumn::UnitConcentration,
+        StorageExportColumn::Supplier,
+        StorageExportColumn::StoreLocation,
+        StorageExportColumn::StoreLocationName,
+        StorageExportColumn::Entity,
+        StorageExportColumn::CreationDate,
+        StorageExportColumn::ModificationDate,
+        StorageExportColumn::EntryDate,
+        StorageExportColumn::OpeningDate,
+        StorageExportColumn::ExpirationDate,
+        StorageExportColumn::ExitDate,
+        StorageExportColumn::BatchNumber,
+        StorageExportColumn::Reference,
+        StorageExportColumn::Comment,
+        StorageExportColumn::NumberOfBag,
+        StorageExportColumn::NumberOfCarton,
+        StorageExportColumn::ToDestroy,
+        StorageExportColumn::Archive,
+        StorageExportColumn::Person,
+    ];
+
+    // Header of the column, the ones read by the storage importer are the same.
+    #[must_use]
+    pub fn header(self) -> &'static str {
+        match self {
+            StorageExportColumn::StorageId => "STORAGE_ID",
+            StorageExportColumn::Barecode => "BARECODE",
+            StorageExportColumn::Quantity => "QUANTITY",
+            StorageExportColumn::Unit => "UNIT",
+            StorageExportColumn::Concentration => "CONCENTRATION",
+            StorageExportColumn::UnitConcentration => "UNIT_CONCENTRATION",
+            StorageExportColumn::Supplier => "SUPPLIER",
+            StorageExportColumn::StoreLocation => "STORE_LOCATION",
+            StorageExportColumn::StoreLocationName => "STORE_LOCATION_NAME",
+            StorageExportColumn::Entity => "ENTITY",
+            StorageExportColumn::CreationDate => "CREATION_DATE",
+            StorageExportColumn::ModificationDate => "MODIFICATION_DATE",
+            StorageExportColumn::EntryDate => "ENTRY_DATE",
+            StorageExportColumn::OpeningDate => "OPENING_DATE",
+            StorageExportColumn::ExpirationDate => "EXPIRATION_DATE",
+            StorageExportColumn::ExitDate => "EXIT_DATE",
+            StorageExportColumn::BatchNumber => "BATCH_NUMBER",
+            StorageExportColumn::Reference => "REFERENCE",
+            StorageExportColumn::Comment => "COMMENT",
+            StorageExportColumn::NumberOfBag => "NUMBER_OF_BAG",
+            StorageExportColumn::NumberOfCarton => "NUMBER_OF_CARTON",
+            StorageExportColumn::ToDestroy => "TO_DESTROY",
+            StorageExportColumn::Archive => "ARCHIVE",
+            StorageExportColumn::Person => "PERSON",
+            StorageExportColumn::Product(product_column) => product_column.storage_header(),
+        }
+    }
+
+    // The storage product must be fully loaded for the product columns.
+    #[must_use]
+    pub fn value(self, storage: &StorageStruct) -> Value {
+        match self {
+            StorageExportColumn::StorageId => to_value(storage.storage_id),
+            StorageExportColumn::Barecode => to_value(&storage.storage_barecode),
+            StorageExportColumn::Quantity => to_value(storage.storage_quantity),
+            StorageExportColumn::Unit => {
+                to_value(storage.unit_quantity.as_ref().map(|unit| &unit.unit_label))
+            }
+            StorageExportColumn::Concentration => to_value(storage.storage_concentration),
+            StorageExportColumn::UnitConcentration => to_value(
+                storage
+                    .unit_concentration
+                    .as_ref()
+                    .map(|unit| &unit.unit_label),
+            ),
+            StorageExportColumn::Supplier => to_value(
+                storage
+                    .supplier
+                    .as_ref()
+                    .map(|supplier| &supplier.supplier_label),
+            ),
+            StorageExportColumn::StoreLocation => {
+                to_value(&storage.store_location.store_location_full_path)
+            }
+            StorageExportColumn::StoreLocationName => {
+                to_value(&storage.store_location.store_location_name)
+            }
+            StorageExportColumn::Entity => to_value(
+                storage
+                    .store_location
+                    .entity
+                    .as_ref()
+                    .map(|entity| &entity.entity_name),
+            ),
+            StorageExportColumn::CreationDate => date_value(Some(storage.storage_creation_date)),
+            StorageExportColumn::ModificationDate => {
+                date_value(Some(storage.storage_modification_date))
+            }
+            StorageExportColumn::EntryDate => date_value(storage.storage_entry_date),
+            StorageExportColumn::OpeningDate => date_value(storage.storage_opening_date),
+            StorageExportColumn::ExpirationDate => date_value(storage.storage_expiration_date),
+            StorageExportColumn::ExitDate => date_value(storage.storage_exit_date),
+            StorageExportColumn::BatchNumber => to_value(&storage.storage_batch_number),
+            StorageExportColumn::Reference => to_value(&storage.storage_reference),
+            StorageExportColumn::Comment => to_value(&storage.storage_comment),
+            StorageExportColumn::NumberOfBag => to_value(storage.storage_number_of_bag),
+            StorageExportColumn::NumberOfCarton => to_value(storage.storage_number_of_carton),
+            StorageExportColumn::ToDestroy => to_value(storage.storage_to_destroy),
+            StorageExportColumn::Archive => to_value(storage.storage_archive),
+            StorageExportColumn::Person => to_value(&storage.person.person_email),
+            StorageExportColumn::Product(product_column) => product_column.value(&storage.product),
+        }
+    }
+
+    fn is_product_column(self) -> bool {
+        matches!(self, StorageExportColumn::Product(_))
+    }
+}
+
+impl FromStr for StorageExportColumn {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StorageExportColumn::ALL
+            .into_iter()
+            .chain(
+                ProductExportColumn::ALL
+                    .into_iter()
+                    .map(StorageExportColumn::Product),
+            )
+            .find(|column| column.header().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ExportError::UnknownColumn(s.to_string()))
+    }
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn date_value(maybe_date: Option<DateTime<Utc>>) -> Value {
+    to_value(maybe_date.map(|date| date.format("%Y-%m-%d").to_string()))
+}
+
+// Join the values of a list column, Null if empty.
+fn join(values: impl Iterator<Item = String>) -> Value {
+    let values: Vec<String> = values.collect();
+
+    if values.is_empty() {
+        Value::Null
+    } else {
+        Value::String(values.join(EXPORT_LIST_SEPARATOR))
+    }
+}
+
+// Rows writer of the export formats.
+enum RowWriter<W: Write> {
+    Csv(csv::Writer<W>),
+    JsonLines { writer: W, keys: Vec<String> },
+    Xlsx(XlsxWriter<W>),
+}
+
+impl<W: Write> RowWriter<W> {
+    // Start the export, writing the headers for CSV and XLSX.
+    fn new(
+        format: ExportFormat,
+        writer: W,
+        headers: &[&str],
+        sheet_name: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(match format {
+            ExportFormat::Csv => {
+                let mut csv_writer = WriterBuilder::new().from_writer(writer);
+                csv_writer.write_record(headers)?;
+
+                RowWriter::Csv(csv_writer)
+            }
+            ExportFormat::JsonLines => RowWriter::JsonLines {
+                writer,
+                keys: headers.iter().map(|header| header.to_lowercase()).collect(),
+            },
+            ExportFormat::Xlsx => {
+                let mut xlsx_writer = XlsxWriter::new(writer, sheet_name)?;
+                xlsx_writer.write_row(
+                    &headers
+                        .iter()
+                        .map(|header| XlsxCell::Text(header))
+                        .collect::<Vec<_>>(),
+                )?;
+
+                RowWriter::Xlsx(xlsx_writer)
+            }
+        })
+    }
+
+    fn write_row(
+        &mut self,
+        values: Vec<Value>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            RowWriter::Csv(csv_writer) => {
+                csv_writer.write_record(values.iter().map(|value| match value {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                }))?;
+            }
+            RowWriter::JsonLines { writer, keys } => {
+                let object: Map<String, Value> = keys.iter().cloned().zip(values).collect();
+
+                serde_json::to_writer(&mut *writer, &object)?;
+                writer.write_all(b"\n")?;
+            }
+            RowWriter::Xlsx(xlsx_writer) => {
+                let serialized: Vec<String> = values.iter().map(Value::to_string).collect();
+
+                xlsx_writer.write_row(
+                    &values
+                        .iter()
+                        .zip(&serialized)
+                        .map(|(value, serialized)| match value {
+                            Value::Null => XlsxCell::Empty,
+                            Value::Bool(boolean) => XlsxCell::Bool(*boolean),
+                            Value::Number(number) => number
+                                .as_f64()
+                                .map_or(XlsxCell::Text(serialized), XlsxCell::Number),
+                            Value::String(s) => XlsxCell::Text(s),
+                            _ => XlsxCell::Text(serialized),
+                        })
+                        .collect::<Vec<_>>(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            RowWriter::Csv(mut csv_writer) => csv_writer.flush()?,
+            RowWriter::JsonLines { mut writer, .. } => writer.flush()?,
+            RowWriter::Xlsx(xlsx_writer) => {
+                xlsx_writer.finish()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Write the products matching the filter with the given columns.
+// Products are fetched and written by pages, the limit and offset of the filter are ignored.
+// Return the number of exported products.
+pub fn write_products(
+    db_connection: &Connection,
+    filter: &RequestFilter,
+    person_id: u64,
+    columns: &[ProductExportColumn],
+    format: ExportFormat,
+    writer: impl Write,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("filter:{filter:?}");
+    debug!("columns:{columns:?}");
+
+    if columns.is_empty() {
+        return Err(Box::new(ExportError::NoColumns));
+    }
+
+    let headers: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+    let mut row_writer = RowWriter::new(format, writer, &headers, "products")?;
+
+    let mut nb_products = 0;
+    loop {
+        let (products, _) = get_products(
+            db_connection,
+            RequestFilter {
+                limit: Some(EXPORT_PAGE_SIZE),
+                offset: Some(nb_products),
+                ..filter.clone()
+            },
+            person_id,
+        )?;
+
+        for product in &products {
+            row_writer.write_row(columns.iter().map(|column| column.value(product)).collect())?;
+        }
+
+        nb_products += products.len();
+
+        if products.len() < EXPORT_PAGE_SIZE {
+            break;
+        }
+    }
+
+    row_writer.finish()?;
+
+    debug!("nb_products:{nb_products}");
+
+    Ok(nb_products)
+}
+
+// Write the storages matching the filter with the given columns.
+// Storages are fetched and written by pages, the limit and offset of the filter are ignored.
+// Return the number of exported storages.
+pub fn write_storages(
+    db_connection: &Connection,
+    filter: &RequestFilter,
+    person_id: u64,
+    columns: &[StorageExportColumn],
+    format: ExportFormat,
+    writer: impl Write,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("filter:{filter:?}");
+    debug!("columns:{columns:?}");
+
+    if columns.is_empty() {
+        return Err(Box::new(ExportError::NoColumns));
+    }
+
+    let headers: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+    let mut row_writer = RowWriter::new(format, writer, &headers, "storages")?;
+
+    // get_storages only returns the product name, the products of each page
+    // are loaded when a product column is exported.
+    let with_products = columns.iter().any(|column| column.is_product_column());
+
+    let mut nb_storages = 0;
+    loop {
+        let (storages, _) = get_storages(
+            db_connection,
+            RequestFilter {
+                limit: Some(EXPORT_PAGE_SIZE),
+                offset: Some(nb_storages),
+                ..filter.clone()
+            },
+            person_id,
+        )?;
+
+        let nb_page_storages = storages.len();
+
+        let products: HashMap<u64, ProductStruct> = if with_products {
+            let mut product_ids: Vec<u64> = storages
+                .iter()
+                .filter_map(|storage| storage.product.product_id)
+                .collect();
+            product_ids.sort_unstable();
+            product_ids.dedup();
+
+            get_products_by_ids(db_connection, &product_ids, person_id)?
+                .into_iter()
+                .filter_map(|product| product.product_id.map(|product_id| (product_id, product)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        for mut storage in storages {
+            if let Some(product) = storage
+                .product
+                .product_id
+                .and_then(|product_id| products.get(&product_id))
+            {
+                storage.product = product.clone();
+            }
+
+            row_writer.write_row(
+                columns
+                    .iter()
+                    .map(|column| column.value(&storage))
+                    .collect(),
+            )?;
+        }
+
+        nb_storages += nb_page_storages;
+
+        if nb_page_storages < EXPORT_PAGE_SIZE {
+            break;
+        }
+    }
+
+    row_writer.finish()?;
+
+    debug!("nb_storages:{nb_storages}");
+
+    Ok(nb_storages)
+}
+
+#[cfg(test)]
+#[path = "export_tests.rs"]
+mod export_tests;
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::{export::*, init::populate_db_with_base_data, test_utils::xlsx_part};
+
+    fn init_test_export() -> Connection {
+        let mut db_connection = crate::test_utils::init_test();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO entity (entity_id, entity_name) VALUES (1, 'lab');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES
+                    (1, 'all', 'all', NULL);
+                INSERT INTO store_location (store_location_id, store_location_name, store_location_can_store, store_location_full_path, entity, store_location) VALUES
+                    (1, 'lab', 0, 'lab', 1, NULL),
+                    (2, 'cabinet [CAB]', 1, 'lab/cabinet [CAB]', 1, 1);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ALCOOL ETHYLIQUE'), (3, 'BENZENE');
+                INSERT INTO cas_number (cas_number_id, cas_number_label, cas_number_cmr) VALUES (1, '64-17-5', NULL), (2, '71-43-2', 'C1A');
+                INSERT INTO product (product_id, product_type, person, name, cas_number, product_specificity) VALUES
+                    (1, 'chem', 1, 1, 1, 'absolute'), (2, 'chem', 1, 3, 2, NULL);
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2);
+                INSERT INTO productsymbols (productsymbols_product_id, productsymbols_symbol_id)
+                    SELECT 2, symbol_id FROM symbol WHERE symbol_label IN ('GHS02', 'GHS08');
+                INSERT INTO storage (storage_id, storage_creation_date, storage_modification_date, storage_entry_date, storage_barecode, storage_quantity, unit_quantity, person, product, store_location, storage) VALUES
+                    (1, 1704067200, 1704067200, 1705276800, 'CAB1.1', 500.0, 2, 1, 1, 2, NULL),
+                    (2, 1704067200, 1704067200, NULL, 'CAB2.1', NULL, NULL, 1, 2, 2, NULL);",
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            "cas_number".parse::<ProductExportColumn>(),
+            Ok(ProductExportColumn::CasNumber)
+        );
+        assert_eq!(
+            "QUANTITY".parse::<StorageExportColumn>(),
+            Ok(StorageExportColumn::Quantity)
+        );
+        assert_eq!(
+            "product_name".parse::<StorageExportColumn>(),
+            Ok(StorageExportColumn::Product(ProductExportColumn::Name))
+        );
+        assert_eq!(
+            "hazard_statements".parse::<StorageExportColumn>(),
+            Ok(StorageExportColumn::Product(
+                ProductExportColumn::HazardStatements
+            ))
+        );
+        assert_eq!(
+            "foo".parse::<StorageExportColumn>(),
+            Err(ExportError::UnknownColumn("foo".to_string()))
+        );
+        assert_eq!("jsonl".parse::<ExportFormat>(), Ok(ExportFormat::JsonLines));
+        assert_eq!(
+            "pdf".parse::<ExportFormat>(),
+            Err(ExportError::UnknownFormat("pdf".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_write_products() {
+        let db_connection = init_test_export();
+        let columns = [
+            ProductExportColumn::Name,
+            ProductExportColumn::Synonyms,
+            ProductExportColumn::CasNumber,
+            ProductExportColumn::Symbols,
+            ProductExportColumn::Cmr,
+        ];
+
+        // CSV.
+        let mut output = Vec::new();
+        let nb_products = write_products(
+            &db_connection,
+            &RequestFilter {
+                order_by: Some("name".to_string()),
+                ..Default::default()
+            },
+            1,
+            &columns,
+            ExportFormat::Csv,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(nb_products, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "NAME,SYNONYMS,CAS_NUMBER,SYMBOLS,CMR
+BENZENE,,71-43-2,GHS02;GHS08,true
+ETHANOL,ALCOOL ETHYLIQUE,64-17-5,,false
+"
+        );
+
+        // JSON Lines.
+        let mut output = Vec::new();
+        write_products(
+            &db_connection,
+            &RequestFilter {
+                id: Some(1),
+                ..Default::default()
+            },
+            1,
+            &columns,
+            ExportFormat::JsonLines,
+            &mut output,
+        )
+        .unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![serde_json::json!({
+                "name": "ETHANOL",
+                "synonyms": "ALCOOL ETHYLIQUE",
+                "cas_number": "64-17-5",
+                "symbols": null,
+                "cmr": false,
+            })]
+        );
+
+        // No columns.
+        assert_eq!(
+            write_products(
+                &db_connection,
+                &RequestFilter::default(),
+                1,
+                &[],
+                ExportFormat::Csv,
+                Vec::new(),
+            )
+            .unwrap_err()
+            .downcast_ref::<ExportError>(),
+            Some(&ExportError::NoColumns)
+        );
+    }
+
+    #[test]
+    fn test_write_storages() {
+        let db_connection = init_test_export();
+        let columns = [
+            StorageExportColumn::Barecode,
+            StorageExportColumn::Product(ProductExportColumn::Name),
+            StorageExportColumn::Product(ProductExportColumn::CasNumber),
+            StorageExportColumn::Quantity,
+            StorageExportColumn::Unit,
+            StorageExportColumn::StoreLocation,
+            StorageExportColumn::EntryDate,
+        ];
+
+        // CSV.
+        let mut output = Vec::new();
+        let nb_storages = write_storages(
+            &db_connection,
+            &RequestFilter::default(),
+            1,
+            &columns,
+            ExportFormat::Csv,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(nb_storages, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "BARECODE,PRODUCT_NAME,CAS_NUMBER,QUANTITY,UNIT,STORE_LOCATION,ENTRY_DATE
+CAB1.1,ETHANOL,64-17-5,500.0,mL,lab/cabinet [CAB],2024-01-15
+CAB2.1,BENZENE,71-43-2,,,lab/cabinet [CAB],
+"
+        );
+
+        // XLSX.
+        let mut output = Vec::new();
+        write_storages(
+            &db_connection,
+            &RequestFilter::default(),
+            1,
+            &columns,
+            ExportFormat::Xlsx,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(&output[0..4], b"PK\x03\x04");
+        assert!(xlsx_part(&output, "xl/workbook.xml").contains(r#"<sheet name="storages""#));
+        assert!(xlsx_part(&output, "xl/sharedStrings.xml").contains(">lab/cabinet [CAB]</t>"));
+    }
+}
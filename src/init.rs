@@ -1,301 +1,418 @@
+use chimitheque_traits::searchable::Searchable;
 use chimitheque_types::{
-    casnumber::CasNumber, cenumber::CeNumber, empiricalformula::EmpiricalFormula,
-    linearformula::LinearFormula, name::Name, requestfilter::RequestFilter,
+    casnumber::CasNumber, category::Category, cenumber::CeNumber, classofcompound::ClassOfCompound,
+    empiricalformula::EmpiricalFormula, linearformula::LinearFormula, name::Name,
+    supplier::Supplier, symbol::Symbol, tag::Tag,
 };
-use log::{debug, error, info};
-use rusqlite::{Batch, Connection, OpenFlags, Transaction, fallible_iterator::FallibleIterator};
-use std::env;
+use chimitheque_utils::string::{Transform, clean};
+use log::{debug, error, info, warn};
+use rusqlite::{Batch, Connection, Transaction, fallible_iterator::FallibleIterator, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::path::Path;
 
 use crate::{
+    connection::{ConnectError, ConnectOptions},
     define::{
         CATEGORIES, CLASSES_OF_COMPOUNDS, CMR_CAS, HAZARD_STATEMENT_RE, PHYSICAL_STATES,
-        PRECAUTIONARY_STATEMENT_RE, PRODUCERS, SIGNAL_WORDS, SUPPLIERS, SYMBOLS, TAGS,
+        PRECAUTIONARY_STATEMENT_RE, PRODUCERS, PUBCHEM_GHS_STATEMENT_RE, SIGNAL_WORDS, SUPPLIERS,
+        SYMBOLS, TAGS,
+    },
+    empiricalformula::{to_hill_formula, update_empirical_formula_elements},
+    migration::migrate,
+    searchable::merge_rows,
+    storage::create_storage_qrcode,
+    storelocation::{
+        StaleFullPath, compute_store_location_full_paths, update_store_location_full_paths,
     },
-    searchable::{create_update, get_many},
 };
 
-#[must_use]
-pub fn connect_test() -> Connection {
-    let sql_extension_dir = env::var("SQLITE_EXTENSION_DIR")
-        .expect("Missing SQLITE_EXTENSION_DIR environment variable.");
-    let sql_extension_regex = Path::new(sql_extension_dir.as_str()).join("regex0.so");
+// Open the database with the default connection options.
+// Use ConnectOptions to configure the connection.
+pub fn connect(db_path: &str) -> Result<Connection, ConnectError> {
+    ConnectOptions::new().open(db_path)
+}
 
-    let db_connection = Connection::open_in_memory().unwrap();
-    unsafe {
-        db_connection
-            .load_extension(sql_extension_regex, None::<&str>)
-            .expect("Unable to load regexp extension.");
-    };
+//
+// Sanitize.
+//
+
+// A row whose label is changed, merged or invalid.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SanitizeChange {
+    pub table: String,
+    pub id: u64,
+    pub old_label: String,
+    pub new_label: String,
+    // Row keeping the sanitized label, when it already existed.
+    pub merged_into: Option<u64>,
+    pub error: Option<String>,
+}
 
-    db_connection
+#[derive(Debug, Default, Serialize)]
+pub struct SanitizeReport {
+    pub changes: Vec<SanitizeChange>,
+    // True if the changes have not been written.
+    pub dry_run: bool,
 }
 
-pub fn connect(db_path: &str) -> Result<Connection, rusqlite::Error> {
-    let sql_extension_dir = env::var("SQLITE_EXTENSION_DIR")
-        .expect("Missing SQLITE_EXTENSION_DIR environment variable.");
-    let sql_extension_regex = Path::new(sql_extension_dir.as_str()).join("regex0.so");
+impl SanitizeReport {
+    #[must_use]
+    pub fn errors(&self) -> Vec<&SanitizeChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.error.is_some())
+            .collect()
+    }
+}
 
-    let db_connection = Connection::open_with_flags(
-        db_path,
-        OpenFlags::SQLITE_OPEN_READ_WRITE
-            | OpenFlags::SQLITE_OPEN_CREATE
-            | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
-    )?;
-    unsafe {
-        db_connection
-            .load_extension(sql_extension_regex, None::<&str>)
-            .expect("Unable to load regexp extension.");
-    };
+type SanitizeResult = (String, Option<Box<dyn std::error::Error + Send + Sync>>);
 
-    // Set journal mode to WAL and verify the change.
-    db_connection
-        .pragma_update_and_check(None, "journal_mode", "WAL", |mode| {
-            if mode.get::<_, String>(0) == Ok("wal".to_string()) {
-                Ok(())
-            } else {
-                Err(rusqlite::Error::SqliteFailure(
-                    rusqlite::ffi::Error {
-                        code: rusqlite::ffi::ErrorCode::Unknown,
-                        extended_code: 0,
-                    },
-                    Some(format!("Failed to set WAL mode, got: {mode:?}")),
-                ))
-            }
-        })
-        .expect("Failed to set WAL mode");
+// A table with a label to sanitize.
+// Labels must be unique within the table, or within the parent for references.
+struct SanitizeTable {
+    table_name: String,
+    id_field_name: String,
+    text_field_name: String,
+    parent_field_name: Option<&'static str>,
+    sanitize: fn(&str) -> SanitizeResult,
+}
 
-    // Enable foreign keys.
-    db_connection
-        .execute("PRAGMA foreign_keys = ON", [])
-        .expect("Failed to enable foreign keys");
+// Sanitize a label with the sanitize_and_validate method of a Searchable.
+// The label is sanitized even if it is not valid.
+fn sanitize_searchable<T: Searchable + Default>(
+    label: &str,
+    sanitize_and_validate: fn(&mut T) -> Result<(), Box<dyn std::error::Error + Send + Sync>>,
+) -> SanitizeResult {
+    let mut item = T::default();
+    item.set_text_field(label);
 
-    // Set synchronous mode to NORMAL.
-    db_connection
-        .execute("PRAGMA synchronous = NORMAL", [])
-        .expect("Failed to set synchronous mode");
+    let maybe_err = sanitize_and_validate(&mut item).err();
 
-    // Set cache size to -65536 (64MB).
-    db_connection
-        .execute("PRAGMA cache_size = -65536", [])
-        .expect("Failed to set cache size");
+    (item.get_text(), maybe_err)
+}
 
-    // Set temp store to MEMORY to avoid disk I/O.
-    db_connection
-        .execute("PRAGMA temp_store = MEMORY", [])
-        .expect("Failed to set temp store to MEMORY");
+fn sanitize_label(label: &str) -> SanitizeResult {
+    (clean(label, Transform::None), None)
+}
 
-    // Vacuum and analyze.
-    db_connection
-        .execute("VACUUM", [])
-        .expect("Failed to vacuum database");
-    db_connection
-        .execute("ANALYZE", [])
-        .expect("Failed to analyze database");
+fn searchable_table<T: Searchable + Default>(
+    sanitize: fn(&str) -> SanitizeResult,
+) -> SanitizeTable {
+    let item = T::default();
+
+    SanitizeTable {
+        table_name: item.get_table_name().to_string(),
+        id_field_name: item.get_id_field_name().to_string(),
+        text_field_name: item.get_text_field_name().to_string(),
+        parent_field_name: None,
+        sanitize,
+    }
+}
 
-    Ok(db_connection)
+fn sanitize_tables() -> Vec<SanitizeTable> {
+    vec![
+        searchable_table::<CasNumber>(|label| {
+            sanitize_searchable(label, CasNumber::sanitize_and_validate)
+        }),
+        searchable_table::<CeNumber>(|label| {
+            sanitize_searchable(label, CeNumber::sanitize_and_validate)
+        }),
+        searchable_table::<Name>(|label| sanitize_searchable(label, Name::sanitize_and_validate)),
+        searchable_table::<EmpiricalFormula>(|label| {
+            sanitize_searchable(label, EmpiricalFormula::sanitize_and_validate)
+        }),
+        searchable_table::<LinearFormula>(|label| {
+            sanitize_searchable(label, LinearFormula::sanitize_and_validate)
+        }),
+        searchable_table::<Tag>(|label| sanitize_searchable(label, Tag::sanitize_and_validate)),
+        searchable_table::<Category>(|label| {
+            sanitize_searchable(label, Category::sanitize_and_validate)
+        }),
+        searchable_table::<ClassOfCompound>(|label| {
+            sanitize_searchable(label, ClassOfCompound::sanitize_and_validate)
+        }),
+        searchable_table::<Supplier>(|label| {
+            sanitize_searchable(label, Supplier::sanitize_and_validate)
+        }),
+        searchable_table::<Symbol>(sanitize_label),
+        SanitizeTable {
+            table_name: "producer".to_string(),
+            id_field_name: "producer_id".to_string(),
+            text_field_name: "producer_label".to_string(),
+            parent_field_name: None,
+            sanitize: sanitize_label,
+        },
+        SanitizeTable {
+            table_name: "supplier_ref".to_string(),
+            id_field_name: "supplier_ref_id".to_string(),
+            text_field_name: "supplier_ref_label".to_string(),
+            parent_field_name: Some("supplier"),
+            sanitize: sanitize_label,
+        },
+        SanitizeTable {
+            table_name: "producer_ref".to_string(),
+            id_field_name: "producer_ref_id".to_string(),
+            text_field_name: "producer_ref_label".to_string(),
+            parent_field_name: Some("producer"),
+            sanitize: sanitize_label,
+        },
+    ]
 }
 
-pub fn sanitize(
-    db_connection: &mut Connection,
+fn sanitize_table(
+    db_transaction: &Transaction,
+    table: &SanitizeTable,
     skip_errors: bool,
+    dry_run: bool,
+    report: &mut SanitizeReport,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // CAS numbers.
-    match get_many(
-        &CasNumber {
-            ..Default::default()
-        },
-        db_connection,
-        &RequestFilter::default(),
-    ) {
-        Ok((cas_numbers, _count)) => {
-            for mut cas_number in cas_numbers {
-                let mayerr = cas_number.sanitize_and_validate();
-
-                if let Err(err) = mayerr {
-                    if !skip_errors {
-                        return Err(err);
-                    }
+    let SanitizeTable {
+        table_name,
+        id_field_name,
+        text_field_name,
+        ..
+    } = table;
+    let parent_field_name = table.parent_field_name.unwrap_or("NULL");
+
+    let select_query = format!(
+        "SELECT {id_field_name}, {text_field_name}, {parent_field_name} FROM {table_name} ORDER BY {id_field_name}"
+    );
+    debug!("sql: {select_query}");
+
+    let rows = db_transaction
+        .prepare(&select_query)?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<u64>>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Rows with the same label, and the same parent for references.
+    let duplicate_query = format!(
+        "SELECT {id_field_name} FROM {table_name}
+        WHERE {text_field_name}=?1 AND {id_field_name}!=?2 AND {parent_field_name} IS ?3
+        ORDER BY {id_field_name} LIMIT 1"
+    );
+
+    for (id, old_label, parent_id) in rows {
+        let (new_label, maybe_err) = (table.sanitize)(&old_label);
+
+        let error = match maybe_err {
+            Some(err) if !skip_errors && !dry_run => return Err(err),
+            Some(err) => {
+                error!("skipping error: {err} for {table_name} {id} {old_label:?}");
+                Some(err.to_string())
+            }
+            None => None,
+        };
 
-                    error!("skipping error: {err} for {cas_number:?}");
-                    debug!("create_update {cas_number:?}");
+        if new_label == old_label && error.is_none() {
+            continue;
+        }
 
-                    // Even on error, we still want to update the database will sanitized entry.
-                    create_update(
-                        &CasNumber::default(),
-                        cas_number.cas_number_id,
-                        db_connection,
-                        &cas_number.cas_number_label,
-                    )?;
-                }
+        let mut merged_into = None;
+
+        if new_label != old_label {
+            debug!("sql: {duplicate_query}");
+
+            merged_into = match db_transaction.query_row(
+                &duplicate_query,
+                params![new_label, id, parent_id],
+                |row| row.get::<_, u64>(0),
+            ) {
+                Ok(duplicate_id) => Some(duplicate_id),
+                Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Err(err) => return Err(Box::new(err)),
+            };
+
+            // Changes are also written on dry run so that following rows
+            // are checked against the sanitized labels. They are rolled back.
+            if let Some(duplicate_id) = merged_into {
+                merge_rows(db_transaction, table_name, id_field_name, id, duplicate_id)?;
+            } else {
+                let update_query = format!(
+                    "UPDATE {table_name} SET {text_field_name}=?1 WHERE {id_field_name}=?2"
+                );
+                debug!("sql: {update_query}");
+
+                db_transaction.execute(&update_query, params![new_label, id])?;
             }
         }
-        Err(err) => {
-            if !skip_errors {
-                return Err(err);
-            }
 
-            error!("skipping error: {err}");
-        }
+        report.changes.push(SanitizeChange {
+            table: table_name.clone(),
+            id,
+            old_label,
+            new_label,
+            merged_into,
+            error,
+        });
     }
 
-    // CE numbers.
-    match get_many(
-        &CeNumber {
-            ..Default::default()
-        },
-        db_connection,
-        &RequestFilter::default(),
-    ) {
-        Ok((ce_numbers, _count)) => {
-            for mut ce_number in ce_numbers {
-                let mayerr = ce_number.sanitize_and_validate();
-
-                if let Err(err) = mayerr {
-                    if !skip_errors {
-                        return Err(err);
-                    }
+    Ok(())
+}
 
-                    error!("skipping error: {err} for {ce_number:?}");
-                    debug!("create_update {ce_number:?}");
+// Sanitize the labels of the searchable tables and of the supplier and
+// producer references.
+// A row whose sanitized label already exists is merged into the existing row.
+// Invalid labels are still sanitized when skip_errors is set, otherwise
+// the first error aborts the whole sanitization.
+// With dry_run nothing is written and every error is reported.
+pub fn sanitize(
+    db_connection: &mut Connection,
+    skip_errors: bool,
+    dry_run: bool,
+) -> Result<SanitizeReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut report = SanitizeReport {
+        dry_run,
+        ..Default::default()
+    };
 
-                    // Even on error, we still want to update the database will sanitized entry.
-                    create_update(
-                        &CeNumber::default(),
-                        ce_number.ce_number_id,
-                        db_connection,
-                        &ce_number.ce_number_label,
-                    )?;
-                }
-            }
-        }
-        Err(err) => {
-            if !skip_errors {
-                return Err(err);
-            }
+    let tx = db_connection.transaction()?;
 
-            error!("skipping error: {err}");
-        }
+    for table in sanitize_tables() {
+        info!("- sanitizing {}", table.table_name);
+
+        sanitize_table(&tx, &table, skip_errors, dry_run, &mut report)?;
     }
 
-    // Names.
-    match get_many(
-        &Name {
-            ..Default::default()
-        },
-        db_connection,
-        &RequestFilter::default(),
-    ) {
-        Ok((names, _count)) => {
-            for mut name in names {
-                let mayerr = name.sanitize_and_validate();
-
-                if let Err(err) = mayerr {
-                    if !skip_errors {
-                        return Err(err);
-                    }
+    // Renamed formulas lost their elements.
+    update_empirical_formula_elements(&tx)?;
 
-                    error!("skipping error: {err} for {name:?}");
-                    debug!("create_update {name:?}");
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
 
-                    // Even on error, we still want to update the database will sanitized entry.
-                    create_update(
-                        &Name::default(),
-                        name.name_id,
-                        db_connection,
-                        &name.name_label,
-                    )?;
-                }
-            }
-        }
-        Err(err) => {
-            if !skip_errors {
-                return Err(err);
-            }
+    debug!("report: {report:#?}");
 
-            error!("skipping error: {err}");
-        }
-    }
+    Ok(report)
+}
 
-    // Empirical formulas.
-    match get_many(
-        &EmpiricalFormula {
-            ..Default::default()
-        },
-        db_connection,
-        &RequestFilter::default(),
-    ) {
-        Ok((empirical_formulas, _count)) => {
-            for mut empirical_formula in empirical_formulas {
-                let mayerr = empirical_formula.sanitize_and_validate();
-
-                if let Err(err) = mayerr {
-                    if !skip_errors {
-                        return Err(err);
-                    }
+//
+// Formula normalization.
+//
 
-                    error!("skipping error: {err} for {empirical_formula:?}");
-                    debug!("create_update {empirical_formula:?}");
+// Empirical formula set from the linear formula of a product.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DerivedEmpiricalFormula {
+    pub product_id: u64,
+    pub linear_formula_label: String,
+    pub empirical_formula_label: String,
+}
 
-                    // Even on error, we still want to update the database will sanitized entry.
-                    create_update(
-                        &EmpiricalFormula::default(),
-                        empirical_formula.empirical_formula_id,
-                        db_connection,
-                        &empirical_formula.empirical_formula_label,
-                    )?;
-                }
-            }
-        }
-        Err(err) => {
-            if !skip_errors {
-                return Err(err);
-            }
+#[derive(Debug, Default, Serialize)]
+pub struct FormulaNormalizationReport {
+    // Empirical formulas rewritten in Hill order, merged or invalid.
+    pub changes: Vec<SanitizeChange>,
+    pub derived: Vec<DerivedEmpiricalFormula>,
+    // True if the changes have not been written.
+    pub dry_run: bool,
+}
 
-            error!("skipping error: {err}");
-        }
+fn normalize_empirical_formula(label: &str) -> SanitizeResult {
+    match to_hill_formula(label) {
+        Ok(hill_label) => (hill_label, None),
+        Err(err) => (label.to_string(), Some(Box::new(err))),
     }
+}
 
-    // Linear formulas.
-    match get_many(
-        &LinearFormula {
-            ..Default::default()
-        },
-        db_connection,
-        &RequestFilter::default(),
-    ) {
-        Ok((linear_formulas, _count)) => {
-            for mut linear_formula in linear_formulas {
-                let mayerr = linear_formula.sanitize_and_validate();
-
-                if let Err(err) = mayerr {
-                    if !skip_errors {
-                        return Err(err);
-                    }
+// Rewrite the empirical formulas in Hill order, merging the formulas
+// that only differ by their element order, then set the empirical formula
+// of the products having only a linear formula.
+// Errors are handled as in sanitize. Linear formulas that can not be
+// expanded are skipped.
+pub fn normalize_formulas(
+    db_connection: &mut Connection,
+    skip_errors: bool,
+    dry_run: bool,
+) -> Result<FormulaNormalizationReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut sanitize_report = SanitizeReport {
+        dry_run,
+        ..Default::default()
+    };
+    let mut derived = Vec::new();
 
-                    error!("skipping error: {err} for {linear_formula:?}");
-                    debug!("create_update {linear_formula:?}");
+    let tx = db_connection.transaction()?;
 
-                    // Even on error, we still want to update the database will sanitized entry.
-                    create_update(
-                        &LinearFormula::default(),
-                        linear_formula.linear_formula_id,
-                        db_connection,
-                        &linear_formula.linear_formula_label,
-                    )?;
-                }
-            }
-        }
-        Err(err) => {
-            if !skip_errors {
-                return Err(err);
+    info!("- normalizing empirical formulas");
+
+    sanitize_table(
+        &tx,
+        &searchable_table::<EmpiricalFormula>(normalize_empirical_formula),
+        skip_errors,
+        dry_run,
+        &mut sanitize_report,
+    )?;
+
+    info!("- deriving empirical formulas from linear formulas");
+
+    let select_query = "SELECT product.product_id, linear_formula.linear_formula_label FROM product
+        JOIN linear_formula ON product.linear_formula = linear_formula.linear_formula_id
+        WHERE product.empirical_formula IS NULL
+        ORDER BY product.product_id";
+    debug!("sql: {select_query}");
+
+    let products = tx
+        .prepare(select_query)?
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (product_id, linear_formula_label) in products {
+        let empirical_formula_label = match to_hill_formula(&linear_formula_label) {
+            Ok(empirical_formula_label) => empirical_formula_label,
+            Err(err) => {
+                warn!(
+                    "skipping product {product_id} linear formula {linear_formula_label:?}: {err}"
+                );
+                continue;
             }
+        };
 
-            error!("skipping error: {err}");
-        }
+        tx.execute(
+            "INSERT OR IGNORE INTO empirical_formula (empirical_formula_label) VALUES (?1)",
+            [&empirical_formula_label],
+        )?;
+        tx.execute(
+            "UPDATE product SET empirical_formula =
+                (SELECT empirical_formula_id FROM empirical_formula WHERE empirical_formula_label = ?1)
+            WHERE product_id = ?2",
+            params![empirical_formula_label, product_id],
+        )?;
+
+        derived.push(DerivedEmpiricalFormula {
+            product_id,
+            linear_formula_label,
+            empirical_formula_label,
+        });
     }
 
-    Ok(())
+    info!("- indexing empirical formula elements");
+    update_empirical_formula_elements(&tx)?;
+
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+
+    let report = FormulaNormalizationReport {
+        changes: sanitize_report.changes,
+        derived,
+        dry_run,
+    };
+
+    debug!("report: {report:#?}");
+
+    Ok(report)
 }
 
 pub fn create_tables(
@@ -308,14 +425,11 @@ pub fn create_tables(
     // BLOB
     // ANY
 
-    let sql = include_str!("resources/shema.sql");
-
     info!("creating database structure");
 
-    let mut batch = Batch::new(db_connection, sql);
-    while let Some(mut stmt) = batch.next()? {
-        stmt.execute([])?;
-    }
+    let version = migrate(db_connection)?;
+
+    info!("database structure at version {version}");
 
     Ok(())
 }
@@ -471,55 +585,749 @@ pub fn populate_db_with_base_data(
     Ok(())
 }
 
-// https://pubchem.ncbi.nlm.nih.gov/ghs/
+// Replace the products, categories, CAS and CE numbers, empirical formulas,
+// names, producers, entities and store locations with the demo dataset.
+// The database must have been populated with populate_db_with_base_data.
+pub fn populate_demo_data(
+    db_connection: &mut Connection,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let sql = include_str!("resources/demo.sql");
+
+    info!("adding demo data");
+
+    let tx = db_connection.transaction()?;
+
+    let mut batch = Batch::new(&tx, sql);
+    while let Some(mut stmt) = batch.next()? {
+        stmt.execute([])?;
+    }
+
+    update_empirical_formula_elements(&tx)?;
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+//
+// GHS statements.
+//
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GhsStatement {
+    #[serde(alias = "code")]
+    pub reference: String,
+    #[serde(alias = "statement")]
+    pub label: String,
+}
+
+impl GhsStatement {
+    fn is_precautionary(&self) -> bool {
+        self.reference.starts_with('P')
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct GhsStatementChange {
+    pub reference: String,
+    pub old_label: String,
+    pub new_label: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct GhsUpdateReport {
+    pub added: Vec<GhsStatement>,
+    pub changed: Vec<GhsStatementChange>,
+    // Statements of the database missing from the file. They are kept
+    // as products may still reference them.
+    pub removed: Vec<GhsStatement>,
+    // Products linked to a removed statement.
+    pub products_with_removed_statements: Vec<u64>,
+    // The statements come from a single compound record: they are only
+    // inserted or updated, and nothing is reported as removed.
+    pub partial: bool,
+}
+
+// Collect the statements of the StringWithMarkup strings of a PubChem PUG View JSON value,
+// walking the Record, Section and Information objects.
+fn collect_pubchem_ghs_statements(value: &serde_json::Value, statements: &mut Vec<GhsStatement>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, child) in object {
+                if key == "StringWithMarkup"
+                    && let serde_json::Value::Array(markups) = child
+                {
+                    for string in markups
+                        .iter()
+                        .filter_map(|markup| markup["String"].as_str())
+                    {
+                        if let Some(captures) = PUBCHEM_GHS_STATEMENT_RE.captures(string.trim()) {
+                            statements.push(GhsStatement {
+                                reference: captures["reference"].to_string(),
+                                label: captures["label"].to_string(),
+                            });
+                        }
+                    }
+                } else {
+                    collect_pubchem_ghs_statements(child, statements);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for child in values {
+                collect_pubchem_ghs_statements(child, statements);
+            }
+        }
+        _ => (),
+    }
+}
+
+// Parse GHS statements, in one of the formats:
+// - the PubChem ghscode TSV format (https://pubchem.ncbi.nlm.nih.gov/ghs/),
+// - a PubChem PUG View JSON record, whose StringWithMarkup strings are
+//   "H225: Highly flammable liquid and vapour", the strings with only references
+//   ("P210, P233...") are skipped,
+// - a JSON list of {"reference", "label"} objects.
+// Return the statements, and false for a PUG View record that only holds
+// the statements of one compound and not a full GHS revision.
+fn parse_ghs_statements(
+    content: &str,
+) -> Result<(Vec<GhsStatement>, bool), Box<dyn std::error::Error + Send + Sync>> {
+    if content.trim_start().starts_with('[') {
+        return Ok((serde_json::from_str(content)?, true));
+    }
+
+    if content.trim_start().starts_with('{') {
+        let record: serde_json::Value = serde_json::from_str(content)?;
+        let mut statements = vec![];
+        collect_pubchem_ghs_statements(&record, &mut statements);
+
+        return Ok((statements, false));
+    }
+
+    let mut statements = vec![];
+
+    for line in content.lines() {
+        let Some(captures) = HAZARD_STATEMENT_RE
+            .captures(line)
+            .or_else(|| PRECAUTIONARY_STATEMENT_RE.captures(line))
+        else {
+            continue;
+        };
+
+        statements.push(GhsStatement {
+            reference: captures["reference"].to_string(),
+            label: captures["label"].to_string(),
+        });
+    }
+
+    Ok((statements, true))
+}
+
+// Return the (reference, label) of the statements of the given table.
+fn select_ghs_statements(
+    db_transaction: &Transaction,
+    table_name: &str,
+) -> Result<Vec<GhsStatement>, Box<dyn std::error::Error + Send + Sync>> {
+    let sql = format!(
+        "SELECT {table_name}_reference, {table_name}_label FROM {table_name} ORDER BY {table_name}_reference"
+    );
+    debug!("sql: {sql}");
+
+    Ok(db_transaction
+        .prepare(&sql)?
+        .query_map([], |row| {
+            Ok(GhsStatement {
+                reference: row.get(0)?,
+                label: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+// Insert or update the statements and report the differences with the database.
+// The removed statements are only computed for a full GHS revision, and for
+// the kinds (hazard or precautionary) present in the statements.
+fn update_ghs_statements_with(
+    db_transaction: &Transaction,
+    mut statements: Vec<GhsStatement>,
+    full_revision: bool,
+) -> Result<GhsUpdateReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut report = GhsUpdateReport {
+        partial: !full_revision,
+        ..Default::default()
+    };
+
+    // A reference can appear several times (one line per hazard category),
+    // the last one wins as with the upsert.
+    let mut seen_references = HashSet::new();
+    statements.reverse();
+    statements.retain(|statement| seen_references.insert(statement.reference.clone()));
+    statements.reverse();
+    let mut products_with_removed_statements = HashSet::new();
+
+    let (precautionary_statements, hazard_statements): (Vec<_>, Vec<_>) = statements
+        .into_iter()
+        .partition(GhsStatement::is_precautionary);
+
+    for (table_name, join_table_name, statements) in [
+        (
+            "hazard_statement",
+            "producthazardstatements",
+            hazard_statements,
+        ),
+        (
+            "precautionary_statement",
+            "productprecautionarystatements",
+            precautionary_statements,
+        ),
+    ] {
+        if statements.is_empty() {
+            continue;
+        }
+
+        let existing_statements = select_ghs_statements(db_transaction, table_name)?;
+        let existing_references: HashSet<String> = existing_statements
+            .iter()
+            .map(|statement| statement.reference.clone())
+            .collect();
+
+        let references: HashSet<&str> = statements
+            .iter()
+            .map(|statement| statement.reference.as_str())
+            .collect();
+
+        let upsert_query = format!(
+            "INSERT INTO {table_name} ({table_name}_label, {table_name}_reference)
+            VALUES (?1, ?2)
+            ON CONFLICT({table_name}_reference) DO UPDATE
+            SET {table_name}_label = excluded.{table_name}_label"
+        );
+        debug!("sql: {upsert_query}");
+
+        for statement in &statements {
+            db_transaction.execute(&upsert_query, (&statement.label, &statement.reference))?;
+        }
+
+        let products_query = format!(
+            "SELECT {join_table_name}_product_id FROM {join_table_name}
+            JOIN {table_name} ON {join_table_name}_{table_name}_id = {table_name}_id
+            WHERE {table_name}_reference = ?1"
+        );
+        debug!("sql: {products_query}");
+
+        for existing_statement in existing_statements {
+            if !references.contains(existing_statement.reference.as_str()) {
+                if !full_revision {
+                    continue;
+                }
+
+                let product_ids = db_transaction
+                    .prepare(&products_query)?
+                    .query_map([&existing_statement.reference], |row| row.get::<_, u64>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                products_with_removed_statements.extend(product_ids);
+
+                report.removed.push(existing_statement);
+            } else if let Some(statement) = statements.iter().find(|statement| {
+                statement.reference == existing_statement.reference
+                    && statement.label != existing_statement.label
+            }) {
+                report.changed.push(GhsStatementChange {
+                    reference: existing_statement.reference,
+                    old_label: existing_statement.label,
+                    new_label: statement.label.clone(),
+                });
+            }
+        }
+
+        for statement in statements {
+            if !existing_references.contains(&statement.reference) {
+                report.added.push(statement);
+            }
+        }
+    }
+
+    report.products_with_removed_statements =
+        products_with_removed_statements.into_iter().collect();
+    report.products_with_removed_statements.sort_unstable();
+
+    Ok(report)
+}
+
+// Insert or update the statements of the embedded GHS revision.
 fn update_ghs_statements(
     db_transaction: &Transaction,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let file = include_str!("resources/ghscode_11.txt");
-    for line in file.lines() {
-        if let Some(captures) = HAZARD_STATEMENT_RE.captures(line) {
-            let reference = captures.name("reference").unwrap().as_str();
-            let label = captures.name("label").unwrap().as_str();
+    let (statements, full_revision) =
+        parse_ghs_statements(include_str!("resources/ghscode_11.txt"))?;
 
-            debug!("reference: {reference}");
-            debug!("label: {label}");
+    update_ghs_statements_with(db_transaction, statements, full_revision)?;
 
-            db_transaction.execute(
-                "INSERT INTO hazard_statement (hazard_statement_label, hazard_statement_reference)
-            VALUES (?1, ?2)
-            ON CONFLICT(hazard_statement_reference) DO UPDATE
-            SET hazard_statement_reference = ?2;",
-                (&label, &reference),
-            )?;
-        } else if let Some(captures) = PRECAUTIONARY_STATEMENT_RE.captures(line) {
-            let reference = captures.name("reference").unwrap().as_str();
-            let label = captures.name("label").unwrap().as_str();
-
-            debug!("reference: {reference}");
-            debug!("label: {label}");
-
-            db_transaction.execute(
-                "INSERT INTO precautionary_statement (precautionary_statement_label, precautionary_statement_reference)
-            VALUES (?1, ?2)
-            ON CONFLICT(precautionary_statement_reference) DO UPDATE
-            SET precautionary_statement_reference = ?2;",
-            (&label, &reference),
-            )?;
+    Ok(())
+}
+
+// Update the GHS statements from a file, in the PubChem ghscode TSV format,
+// a PubChem PUG View JSON record or a JSON list of {"reference", "label"} objects.
+// Statements missing from the file are reported but not deleted,
+// except for a PUG View record that only holds the statements of one compound.
+pub fn update_ghs_statements_from_file(
+    db_connection: &mut Connection,
+    path: &Path,
+) -> Result<GhsUpdateReport, Box<dyn std::error::Error + Send + Sync>> {
+    info!("updating GHS statements from {}", path.display());
+
+    let content = std::fs::read_to_string(path)?;
+    let (statements, full_revision) = parse_ghs_statements(&content)?;
+
+    let tx = db_connection.transaction()?;
+
+    let report = update_ghs_statements_with(&tx, statements, full_revision)?;
+
+    tx.commit()?;
+
+    debug!("report: {report:#?}");
+
+    Ok(report)
+}
+
+//
+// Legacy (Go) Chimithèque database import.
+//
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LegacyImportError {
+    LegacyDatabaseNotFound(String),
+    TargetDatabaseNotEmpty(String),
+}
+
+impl Display for LegacyImportError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            LegacyImportError::LegacyDatabaseNotFound(path) => {
+                write!(f, "legacy database not found: {path}")
+            }
+            LegacyImportError::TargetDatabaseNotEmpty(table) => {
+                write!(f, "target database is not empty, table {table} has rows")
+            }
         }
     }
+}
+
+impl std::error::Error for LegacyImportError {}
+
+// A legacy row imported with a different value to avoid a UNIQUE conflict.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LegacyImportRename {
+    pub table: String,
+    pub id: u64,
+    pub legacy_value: String,
+    pub new_value: String,
+}
+
+// A row referencing a missing parent, as returned by PRAGMA foreign_key_check.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LegacyImportForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LegacyImportReport {
+    pub renamed: Vec<LegacyImportRename>,
+    pub store_locations_updated: usize,
+    pub qrcodes_generated: usize,
+    pub foreign_key_violations: Vec<LegacyImportForeignKeyViolation>,
+}
+
+// Tables filled by the import, they must be empty before importing.
+const LEGACY_IMPORT_TABLES: [&str; 26] = [
+    "bookmark",
+    "borrowing",
+    "cas_number",
+    "ce_number",
+    "category",
+    "class_of_compound",
+    "empirical_formula",
+    "linear_formula",
+    "entity",
+    "name",
+    "hazard_statement",
+    "precautionary_statement",
+    "permission",
+    "person",
+    "physical_state",
+    "producer",
+    "producer_ref",
+    "product",
+    "signal_word",
+    "storage",
+    "store_location",
+    "supplier",
+    "supplier_ref",
+    "symbol",
+    "tag",
+    "unit",
+];
+
+// Import a legacy (Go) Chimithèque database into an empty database.
+// Persons, producers and suppliers conflicting on their unique column are
+// renamed and reported. Store location full paths and storage QR codes
+// are recomputed.
+pub fn import_legacy_database(
+    db_connection: &mut Connection,
+    legacy_db_path: &str,
+) -> Result<LegacyImportReport, Box<dyn std::error::Error + Send + Sync>> {
+    // ATTACH would silently create an empty database.
+    if !Path::new(legacy_db_path).is_file() {
+        return Err(Box::new(LegacyImportError::LegacyDatabaseNotFound(
+            legacy_db_path.to_string(),
+        )));
+    }
+
+    create_tables(db_connection)?;
+
+    for table in LEGACY_IMPORT_TABLES {
+        let count: u64 =
+            db_connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })?;
+
+        if count > 0 {
+            return Err(Box::new(LegacyImportError::TargetDatabaseNotEmpty(
+                table.to_string(),
+            )));
+        }
+    }
+
+    info!("attaching legacy database {legacy_db_path}");
+
+    // ATTACH and foreign_keys can not be changed inside a transaction.
+    let foreign_keys: bool =
+        db_connection.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+
+    db_connection.execute("ATTACH DATABASE ?1 AS legacy", [legacy_db_path])?;
+    db_connection.pragma_update(None, "foreign_keys", false)?;
+
+    let mayreport = import_legacy_tables(db_connection);
+
+    // Restore the connection whatever the import result.
+    db_connection.pragma_update(None, "foreign_keys", foreign_keys)?;
+    db_connection.execute("DETACH DATABASE legacy", [])?;
+
+    mayreport
+}
+
+fn import_legacy_tables(
+    db_connection: &mut Connection,
+) -> Result<LegacyImportReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut report = LegacyImportReport::default();
+
+    let tx = db_connection.transaction()?;
+
+    info!("- importing persons");
+    import_legacy_unique_column(&tx, "person", "person_email", true, &mut report.renamed)?;
+
+    info!("- importing producers");
+    import_legacy_unique_column(
+        &tx,
+        "producer",
+        "producer_label",
+        false,
+        &mut report.renamed,
+    )?;
+
+    info!("- importing suppliers");
+    import_legacy_unique_column(
+        &tx,
+        "supplier",
+        "supplier_label",
+        false,
+        &mut report.renamed,
+    )?;
+
+    info!("- importing other tables");
+    let mut batch = Batch::new(&tx, include_str!("resources/migration.sql"));
+    while let Some(mut stmt) = batch.next()? {
+        stmt.execute([])?;
+    }
+
+    info!("- indexing empirical formula elements");
+    update_empirical_formula_elements(&tx)?;
+
+    info!("- computing store location full paths");
+    report.store_locations_updated = update_store_location_full_paths(&tx)?;
+
+    info!("- generating storage qrcodes");
+    let storage_ids = tx
+        .prepare("SELECT storage_id FROM storage WHERE storage IS NULL")?
+        .query_map([], |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for storage_id in &storage_ids {
+        create_storage_qrcode(&tx, *storage_id)?;
+    }
+    report.qrcodes_generated = storage_ids.len();
+
+    info!("- checking foreign keys");
+    report.foreign_key_violations = tx
+        .prepare("PRAGMA main.foreign_key_check")?
+        .query_map([], |row| {
+            Ok(LegacyImportForeignKeyViolation {
+                table: row.get(0)?,
+                rowid: row.get(1)?,
+                parent: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for violation in &report.foreign_key_violations {
+        warn!("foreign key violation: {violation:?}");
+    }
+
+    tx.commit()?;
+
+    Ok(report)
+}
+
+// Copy the legacy table id and unique column, suffixing conflicting values
+// with the row id. Legacy rows are imported by id so the oldest row keeps its value.
+fn import_legacy_unique_column(
+    db_transaction: &Transaction,
+    table: &str,
+    column: &str,
+    lowercase: bool,
+    renamed: &mut Vec<LegacyImportRename>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let id_column = format!("{table}_id");
+
+    let rows = db_transaction
+        .prepare(&format!(
+            "SELECT {id_column}, {column} FROM legacy.{table} ORDER BY {id_column}"
+        ))?
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut values: HashSet<String> = HashSet::new();
+
+    for (id, legacy_value) in rows {
+        let mut value = if lowercase {
+            legacy_value.to_lowercase()
+        } else {
+            legacy_value.clone()
+        };
+
+        if values.contains(&value) {
+            while values.contains(&value) {
+                value = format!("{value}_{id}");
+            }
+
+            debug!("renaming {table} {id}: {legacy_value} -> {value}");
+
+            renamed.push(LegacyImportRename {
+                table: table.to_string(),
+                id,
+                legacy_value,
+                new_value: value.clone(),
+            });
+        }
+
+        db_transaction.execute(
+            &format!("INSERT INTO {table} ({id_column}, {column}) VALUES (?1, ?2)"),
+            (id, &value),
+        )?;
+
+        values.insert(value);
+    }
 
     Ok(())
 }
 
+//
+// Consistency check.
+//
+
+// A permission on an entity that does not exist.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct OrphanPermission {
+    pub person: u64,
+    pub permission_name: String,
+    pub permission_item: String,
+    pub permission_entity: i64,
+}
+
+// A barecode shared by storages of different products.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DuplicateBarecode {
+    pub storage_barecode: String,
+    pub storage_ids: Vec<u64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConsistencyReport {
+    // Repairable.
+    pub orphan_permissions: Vec<OrphanPermission>,
+    pub stale_full_paths: Vec<StaleFullPath>,
+    pub orphan_history_storages: Vec<u64>,
+    // Reported only.
+    pub store_location_cycles: Vec<u64>,
+    pub storages_in_non_storing_locations: Vec<u64>,
+    pub products_without_name: Vec<u64>,
+    pub duplicate_barecodes: Vec<DuplicateBarecode>,
+    // True if the repairable problems have been fixed.
+    pub repaired: bool,
+}
+
+impl ConsistencyReport {
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.orphan_permissions.is_empty()
+            && self.stale_full_paths.is_empty()
+            && self.orphan_history_storages.is_empty()
+            && self.store_location_cycles.is_empty()
+            && self.storages_in_non_storing_locations.is_empty()
+            && self.products_without_name.is_empty()
+            && self.duplicate_barecodes.is_empty()
+    }
+}
+
+fn select_ids(
+    db_transaction: &Transaction,
+    sql: &str,
+) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("sql: {sql}");
+
+    Ok(db_transaction
+        .prepare(sql)?
+        .query_map([], |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+// Detect the invariants not enforced by the schema.
+// With repair, orphan permissions and orphan history storages are deleted
+// and stale store location full paths are recomputed.
+// The report always lists the problems found before the repair.
+pub fn check_consistency(
+    db_connection: &mut Connection,
+    repair: bool,
+) -> Result<ConsistencyReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mut report = ConsistencyReport::default();
+
+    let tx = db_connection.transaction()?;
+
+    info!("- checking permissions");
+    report.orphan_permissions = tx
+        .prepare(
+            "SELECT person, permission_name, permission_item, permission_entity FROM permission
+            WHERE permission_entity IS NOT NULL
+            AND permission_entity NOT IN (SELECT entity_id FROM entity)
+            ORDER BY person",
+        )?
+        .query_map([], |row| {
+            Ok(OrphanPermission {
+                person: row.get(0)?,
+                permission_name: row.get(1)?,
+                permission_item: row.get(2)?,
+                permission_entity: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("- checking store locations");
+    (report.stale_full_paths, report.store_location_cycles) =
+        compute_store_location_full_paths(&tx)?;
+
+    info!("- checking storages");
+    report.orphan_history_storages = select_ids(
+        &tx,
+        "SELECT storage_id FROM storage
+        WHERE storage IS NOT NULL
+        AND storage NOT IN (SELECT storage_id FROM storage)
+        ORDER BY storage_id",
+    )?;
+
+    report.storages_in_non_storing_locations = select_ids(
+        &tx,
+        "SELECT storage_id FROM storage
+        JOIN store_location ON storage.store_location = store_location.store_location_id
+        WHERE storage.storage IS NULL
+        AND COALESCE(store_location.store_location_can_store, 0) = 0
+        ORDER BY storage_id",
+    )?;
+
+    let barecodes = tx
+        .prepare(
+            "SELECT storage_barecode, GROUP_CONCAT(storage_id) FROM storage
+            WHERE storage IS NULL
+            AND storage_barecode IS NOT NULL
+            GROUP BY storage_barecode
+            HAVING COUNT(DISTINCT product) > 1
+            ORDER BY storage_barecode",
+        )?
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (storage_barecode, storage_ids) in barecodes {
+        let mut storage_ids = storage_ids
+            .split(',')
+            .map(str::parse::<u64>)
+            .collect::<Result<Vec<_>, _>>()?;
+        storage_ids.sort_unstable();
+
+        report.duplicate_barecodes.push(DuplicateBarecode {
+            storage_barecode,
+            storage_ids,
+        });
+    }
+
+    info!("- checking products");
+    report.products_without_name = select_ids(
+        &tx,
+        "SELECT product_id FROM product
+        WHERE name IS NULL
+        OR name NOT IN (SELECT name_id FROM name)
+        ORDER BY product_id",
+    )?;
+
+    if repair {
+        info!("- repairing");
+
+        let nb_deleted = tx.execute(
+            "DELETE FROM permission
+            WHERE permission_entity IS NOT NULL
+            AND permission_entity NOT IN (SELECT entity_id FROM entity)",
+            [],
+        )?;
+        debug!("deleted orphan permissions: {nb_deleted}");
+
+        let nb_updated = update_store_location_full_paths(&tx)?;
+        debug!("updated full paths: {nb_updated}");
+
+        for storage_id in &report.orphan_history_storages {
+            tx.execute("DELETE FROM storage WHERE storage_id = ?1", [storage_id])?;
+        }
+
+        report.repaired = true;
+    }
+
+    tx.commit()?;
+
+    debug!("report: {report:#?}");
+
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     fn init_test() {
         let _ = env_logger::builder().is_test(true).try_init();
-        unsafe {
-            std::env::set_var("SQLITE_EXTENSION_DIR", "src/extensions");
-        };
     }
 
     #[test]
@@ -531,7 +1339,7 @@ mod tests {
     #[test]
     fn init_db_success() {
         init_test();
-        let mut db_connection = connect_test();
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
         create_tables(&mut db_connection).unwrap();
         assert!(populate_db_with_base_data(&mut db_connection).is_ok());
     }
@@ -539,10 +1347,603 @@ mod tests {
     #[test]
     fn update_ghs_statements_success() {
         init_test();
-        let mut db_connection = connect_test();
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
         create_tables(&mut db_connection).unwrap();
         populate_db_with_base_data(&mut db_connection).unwrap();
         let tx = db_connection.transaction().unwrap();
         assert!(update_ghs_statements(&tx).is_ok());
     }
+
+    #[test]
+    fn update_ghs_statements_from_file_success() {
+        init_test();
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
+        create_tables(&mut db_connection).unwrap();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        let nb_hazard_statements: usize = db_connection
+            .query_row("SELECT COUNT(*) FROM hazard_statement", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO product (product_id, product_type, name) VALUES (1, 'chem', 1);
+                INSERT INTO producthazardstatements (producthazardstatements_product_id, producthazardstatements_hazard_statement_id)
+                    SELECT 1, hazard_statement_id FROM hazard_statement WHERE hazard_statement_reference = 'H201';",
+            )
+            .unwrap();
+
+        let ghs_dir = tempfile::tempdir().unwrap();
+
+        // Same revision, nothing changes.
+        let tsv_path = ghs_dir.path().join("ghscode_11.txt");
+        std::fs::write(&tsv_path, include_str!("resources/ghscode_11.txt")).unwrap();
+
+        let report = update_ghs_statements_from_file(&mut db_connection, &tsv_path).unwrap();
+        assert!(report.added.is_empty());
+        assert!(report.changed.is_empty());
+        assert!(report.removed.is_empty());
+
+        // Hazard statements only.
+        let json_path = ghs_dir.path().join("ghscode.json");
+        std::fs::write(
+            &json_path,
+            r#"[
+                {"code": "H200", "statement": "Unstable explosive"},
+                {"reference": "H999", "label": "New hazard"}
+            ]"#,
+        )
+        .unwrap();
+
+        let report = update_ghs_statements_from_file(&mut db_connection, &json_path).unwrap();
+
+        assert_eq!(
+            report.added,
+            vec![GhsStatement {
+                reference: "H999".to_string(),
+                label: "New hazard".to_string(),
+            }]
+        );
+        assert_eq!(
+            report.changed,
+            vec![GhsStatementChange {
+                reference: "H200".to_string(),
+                old_label: "(Deleted) Unstable Explosive".to_string(),
+                new_label: "Unstable explosive".to_string(),
+            }]
+        );
+        assert_eq!(report.removed.len(), nb_hazard_statements - 1);
+        assert!(
+            report
+                .removed
+                .iter()
+                .all(|statement| statement.reference.starts_with('H')
+                    || statement.reference.starts_with("EUH"))
+        );
+        assert_eq!(report.products_with_removed_statements, vec![1]);
+
+        let label: String = db_connection
+            .query_row(
+                "SELECT hazard_statement_label FROM hazard_statement WHERE hazard_statement_reference = 'H200'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(label, "Unstable explosive");
+    }
+
+    #[test]
+    fn parse_ghs_statements_pubchem_success() {
+        init_test();
+
+        let (statements, full_revision) = parse_ghs_statements(
+            r#"{
+                "Record": {
+                    "RecordType": "CID",
+                    "Section": [{
+                        "TOCHeading": "Safety and Hazards",
+                        "Section": [{
+                            "TOCHeading": "GHS Classification",
+                            "Information": [
+                                {"Name": "Signal", "Value": {"StringWithMarkup": [{"String": "Danger"}]}},
+                                {"Name": "GHS Hazard Statements", "Value": {"StringWithMarkup": [
+                                    {"String": "H225 (100%): Highly Flammable liquid and vapor [Danger Flammable liquids]"},
+                                    {"String": "EUH066: Repeated exposure may cause skin dryness or cracking."}
+                                ]}},
+                                {"Name": "Precautionary Statement Codes", "Value": {"StringWithMarkup": [
+                                    {"String": "P210, P233, P240, P241"},
+                                    {"String": "P305+P351+P338: IF IN EYES: Rinse cautiously with water for several minutes."}
+                                ]}}
+                            ]
+                        }]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            statements,
+            vec![
+                GhsStatement {
+                    reference: "H225".to_string(),
+                    label: "Highly Flammable liquid and vapor".to_string(),
+                },
+                GhsStatement {
+                    reference: "EUH066".to_string(),
+                    label: "Repeated exposure may cause skin dryness or cracking.".to_string(),
+                },
+                GhsStatement {
+                    reference: "P305+P351+P338".to_string(),
+                    label: "IF IN EYES: Rinse cautiously with water for several minutes."
+                        .to_string(),
+                },
+            ]
+        );
+        assert!(!full_revision);
+    }
+
+    #[test]
+    fn update_ghs_statements_from_pubchem_record_success() {
+        init_test();
+
+        let mut db_connection = crate::test_utils::init_test();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("ethanol.json");
+        std::fs::write(
+            &json_path,
+            r#"{
+                "Record": {
+                    "Section": [{
+                        "Information": [
+                            {"Name": "GHS Hazard Statements", "Value": {"StringWithMarkup": [
+                                {"String": "H225: Highly flammable liquid and vapour"}
+                            ]}},
+                            {"Name": "Precautionary Statement Codes", "Value": {"StringWithMarkup": [
+                                {"String": "P210: Keep away from heat."}
+                            ]}}
+                        ]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let report = update_ghs_statements_from_file(&mut db_connection, &json_path).unwrap();
+
+        // A single compound record is not a full GHS revision.
+        assert!(report.partial);
+        assert!(report.removed.is_empty());
+        assert!(report.products_with_removed_statements.is_empty());
+        assert!(report.added.is_empty());
+
+        let label: String = db_connection
+            .query_row(
+                "SELECT precautionary_statement_label FROM precautionary_statement WHERE precautionary_statement_reference = 'P210'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(label, "Keep away from heat.");
+    }
+
+    #[test]
+    fn populate_demo_data_success() {
+        init_test();
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
+        create_tables(&mut db_connection).unwrap();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+        assert!(populate_demo_data(&mut db_connection).is_ok());
+
+        let nb_products: u64 = db_connection
+            .query_row("SELECT COUNT(*) FROM product", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(nb_products, 20);
+    }
+
+    #[test]
+    fn sanitize_success() {
+        init_test();
+
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO producer (producer_id, producer_label) VALUES (1, 'acme'), (2, ' acme '), (3, 'sigma ');
+                INSERT INTO producer_ref (producer_ref_id, producer_ref_label, producer) VALUES (1, 'ref1', 2), (2, 'ref1 ', 1);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO product (product_id, product_type, name, producer_ref) VALUES (1, 'chem', 1, 1), (2, 'chem', 1, 2);",
+            )
+            .unwrap();
+
+        let producer_labels = |db_connection: &Connection| -> Vec<String> {
+            db_connection
+                .prepare("SELECT producer_label FROM producer ORDER BY producer_id")
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        // Dry run.
+        let report = sanitize(&mut db_connection, false, true).unwrap();
+
+        assert!(report.dry_run);
+        assert!(report.errors().is_empty());
+        assert_eq!(
+            report.changes,
+            vec![
+                SanitizeChange {
+                    table: "producer".to_string(),
+                    id: 2,
+                    old_label: " acme ".to_string(),
+                    new_label: "acme".to_string(),
+                    merged_into: Some(1),
+                    error: None,
+                },
+                SanitizeChange {
+                    table: "producer".to_string(),
+                    id: 3,
+                    old_label: "sigma ".to_string(),
+                    new_label: "sigma".to_string(),
+                    merged_into: None,
+                    error: None,
+                },
+                SanitizeChange {
+                    table: "producer_ref".to_string(),
+                    id: 2,
+                    old_label: "ref1 ".to_string(),
+                    new_label: "ref1".to_string(),
+                    merged_into: Some(1),
+                    error: None,
+                },
+            ]
+        );
+        assert_eq!(
+            producer_labels(&db_connection),
+            vec![
+                "acme".to_string(),
+                " acme ".to_string(),
+                "sigma ".to_string()
+            ]
+        );
+
+        // Commit.
+        let report = sanitize(&mut db_connection, false, false).unwrap();
+
+        assert!(!report.dry_run);
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(
+            producer_labels(&db_connection),
+            vec!["acme".to_string(), "sigma".to_string()]
+        );
+
+        // Both products reference the remaining producer reference.
+        let nb_products: u64 = db_connection
+            .query_row(
+                "SELECT COUNT(*) FROM product WHERE producer_ref = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(nb_products, 2);
+
+        let producer_ref_producer: u64 = db_connection
+            .query_row(
+                "SELECT producer FROM producer_ref WHERE producer_ref_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(producer_ref_producer, 1);
+
+        // Nothing left to sanitize.
+        let report = sanitize(&mut db_connection, false, false).unwrap();
+        assert!(report.changes.is_empty());
+    }
+
+    #[test]
+    fn normalize_formulas_success() {
+        init_test();
+
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES
+                    (1, 'C2H6O'), (2, 'OH6C2'), (3, 'H2O'), (4, 'C6H6)');
+                INSERT INTO linear_formula (linear_formula_id, linear_formula_label) VALUES (1, 'CH3COOH'), (2, 'CH3(');
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO product (product_id, product_type, name, empirical_formula, linear_formula) VALUES
+                    (1, 'chem', 1, 2, NULL), (2, 'chem', 1, NULL, 1), (3, 'chem', 1, NULL, 2);",
+            )
+            .unwrap();
+
+        // Invalid formulas abort the normalization.
+        assert!(normalize_formulas(&mut db_connection, false, false).is_err());
+
+        let report = normalize_formulas(&mut db_connection, true, false).unwrap();
+
+        assert_eq!(
+            report.changes,
+            vec![
+                SanitizeChange {
+                    table: "empirical_formula".to_string(),
+                    id: 2,
+                    old_label: "OH6C2".to_string(),
+                    new_label: "C2H6O".to_string(),
+                    merged_into: Some(1),
+                    error: None,
+                },
+                SanitizeChange {
+                    table: "empirical_formula".to_string(),
+                    id: 4,
+                    old_label: "C6H6)".to_string(),
+                    new_label: "C6H6)".to_string(),
+                    merged_into: None,
+                    error: Some("unbalanced parenthesis at position 4".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            report.derived,
+            vec![DerivedEmpiricalFormula {
+                product_id: 2,
+                linear_formula_label: "CH3COOH".to_string(),
+                empirical_formula_label: "C2H4O2".to_string(),
+            }]
+        );
+
+        let empirical_formulas: Vec<(u64, Option<String>)> = db_connection
+            .prepare(
+                "SELECT product_id, empirical_formula_label FROM product
+                LEFT JOIN empirical_formula ON product.empirical_formula = empirical_formula.empirical_formula_id
+                ORDER BY product_id",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            empirical_formulas,
+            vec![
+                (1, Some("C2H6O".to_string())),
+                (2, Some("C2H4O2".to_string())),
+                (3, None)
+            ]
+        );
+    }
+
+    fn create_legacy_database(path: &std::path::Path) {
+        let legacy_connection = Connection::open(path).unwrap();
+
+        legacy_connection
+            .execute_batch(
+                "CREATE TABLE bookmark (bookmark_id, person, product);
+                CREATE TABLE borrowing (borrowing_id, borrowing_comment, person, borrower, storage);
+                CREATE TABLE casnumber (casnumber_id, casnumber_label, casnumber_cmr);
+                CREATE TABLE cenumber (cenumber_id, cenumber_label);
+                CREATE TABLE category (category_id, category_label);
+                CREATE TABLE classofcompound (classofcompound_id, classofcompound_label);
+                CREATE TABLE empiricalformula (empiricalformula_id, empiricalformula_label);
+                CREATE TABLE linearformula (linearformula_id, linearformula_label);
+                CREATE TABLE entity (entity_id, entity_name, entity_description);
+                CREATE TABLE name (name_id, name_label);
+                CREATE TABLE entitypeople (entitypeople_entity_id, entitypeople_person_id);
+                CREATE TABLE hazardstatement (hazardstatement_id, hazardstatement_label, hazardstatement_reference, hazardstatement_cmr);
+                CREATE TABLE permission (person, permission_perm_name, permission_item_name, permission_entity_id);
+                CREATE TABLE person (person_id, person_email);
+                CREATE TABLE personentities (personentities_person_id, personentities_entity_id);
+                CREATE TABLE physicalstate (physicalstate_id, physicalstate_label);
+                CREATE TABLE precautionarystatement (precautionarystatement_id, precautionarystatement_label, precautionarystatement_reference);
+                CREATE TABLE producer (producer_id, producer_label);
+                CREATE TABLE producerref (producerref_id, producerref_label, producer);
+                CREATE TABLE product (product_id, product_specificity, product_msds, product_restricted, product_radioactive,
+                    product_threedformula, product_twodformula, product_disposalcomment, product_remark, product_qrcode,
+                    product_sheet, product_concentration, product_temperature, casnumber, cenumber, person, empiricalformula,
+                    linearformula, physicalstate, signalword, name, producerref, unit_temperature, category,
+                    product_number_per_carton, product_number_per_bag);
+                CREATE TABLE productclassofcompound (productclassofcompound_product_id, productclassofcompound_classofcompound_id);
+                CREATE TABLE producthazardstatements (producthazardstatements_product_id, producthazardstatements_hazardstatement_id);
+                CREATE TABLE productprecautionarystatements (productprecautionarystatements_product_id, productprecautionarystatements_precautionarystatement_id);
+                CREATE TABLE productsupplierrefs (productsupplierrefs_product_id, productsupplierrefs_supplierref_id);
+                CREATE TABLE productsymbols (productsymbols_product_id, productsymbols_symbol_id);
+                CREATE TABLE productsynonyms (productsynonyms_product_id, productsynonyms_name_id);
+                CREATE TABLE producttags (producttags_product_id, producttags_tag_id);
+                CREATE TABLE signalword (signalword_id, signalword_label);
+                CREATE TABLE storage (storage_id, storage_creationdate, storage_modificationdate, storage_entrydate,
+                    storage_exitdate, storage_openingdate, storage_expirationdate, storage_quantity, storage_barecode,
+                    storage_comment, storage_reference, storage_batchnumber, storage_todestroy, storage_archive,
+                    storage_qrcode, storage_concentration, storage_number_of_bag, storage_number_of_carton, person,
+                    product, storelocation, unit_concentration, unit_quantity, supplier, storage);
+                CREATE TABLE storelocation (storelocation_id, storelocation_name, storelocation_color, storelocation_canstore,
+                    storelocation_fullpath, entity, storelocation);
+                CREATE TABLE supplier (supplier_id, supplier_label);
+                CREATE TABLE supplierref (supplierref_id, supplierref_label, supplier);
+                CREATE TABLE tag (tag_id, tag_label);
+                CREATE TABLE unit (unit_id, unit_label, unit_multiplier, unit_type, unit);
+
+                INSERT INTO person VALUES (1, 'admin@chimitheque.fr'), (2, 'John@Example.com'), (3, 'john@example.com');
+                INSERT INTO entity VALUES (1, 'lab', NULL);
+                INSERT INTO permission VALUES (1, 'all', 'all', -1), (2, 'r', 'people', 1);
+                INSERT INTO producer VALUES (1, 'acme'), (2, 'acme');
+                INSERT INTO supplier VALUES (1, 'sigma');
+                INSERT INTO name VALUES (1, 'ETHANOL');
+                INSERT INTO product (product_id, person, name, product_restricted, product_radioactive)
+                    VALUES (1, 1, 1, 0, 0);
+                INSERT INTO storelocation VALUES (1, 'room', NULL, 0, 'wrong', 1, NULL), (2, 'shelf', NULL, 1, NULL, 1, 1);
+                INSERT INTO storage (storage_id, storage_creationdate, storage_modificationdate, person, product, storelocation, supplier, storage_todestroy, storage_archive)
+                    VALUES (1, '2020-01-01 00:00:00', '2020-01-01 00:00:00', 2, 1, 2, 1, NULL, 0);
+                INSERT INTO unit VALUES (1, 'L', 1.0, 'quantity', NULL);",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn import_legacy_database_success() {
+        init_test();
+
+        let legacy_dir = tempfile::tempdir().unwrap();
+        let legacy_path = legacy_dir.path().join("storage.db");
+        create_legacy_database(&legacy_path);
+
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
+        let report =
+            import_legacy_database(&mut db_connection, legacy_path.to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            report.renamed,
+            vec![
+                LegacyImportRename {
+                    table: "person".to_string(),
+                    id: 3,
+                    legacy_value: "john@example.com".to_string(),
+                    new_value: "john@example.com_3".to_string(),
+                },
+                LegacyImportRename {
+                    table: "producer".to_string(),
+                    id: 2,
+                    legacy_value: "acme".to_string(),
+                    new_value: "acme_2".to_string(),
+                },
+            ]
+        );
+        assert_eq!(report.store_locations_updated, 2);
+        assert_eq!(report.qrcodes_generated, 1);
+        assert!(report.foreign_key_violations.is_empty());
+
+        let full_path: String = db_connection
+            .query_row(
+                "SELECT store_location_full_path FROM store_location WHERE store_location_id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(full_path, "room/shelf");
+
+        let email: String = db_connection
+            .query_row(
+                "SELECT person_email FROM person WHERE person_id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(email, "john@example.com");
+
+        // The legacy database is detached.
+        assert!(
+            db_connection
+                .query_row("SELECT COUNT(*) FROM legacy.person", [], |row| row
+                    .get::<_, u64>(0))
+                .is_err()
+        );
+
+        // A second import is refused.
+        let err =
+            import_legacy_database(&mut db_connection, legacy_path.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LegacyImportError>(),
+            Some(&LegacyImportError::TargetDatabaseNotEmpty(
+                "entity".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn import_legacy_database_not_found() {
+        init_test();
+
+        let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
+        let err =
+            import_legacy_database(&mut db_connection, "/nonexistent/storage.db").unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<LegacyImportError>(),
+            Some(&LegacyImportError::LegacyDatabaseNotFound(
+                "/nonexistent/storage.db".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn check_consistency_success() {
+        init_test();
+
+        let mut db_connection = crate::test_utils::init_test();
+
+        // Consistent database.
+        let report = check_consistency(&mut db_connection, false).unwrap();
+        assert!(report.is_consistent());
+
+        db_connection
+            .execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                INSERT INTO person (person_id, person_email) VALUES (1, 'admin@chimitheque.fr');
+                INSERT INTO entity (entity_id, entity_name) VALUES (1, 'lab');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity)
+                    VALUES (1, 'r', 'products', 1), (1, 'w', 'storages', 42);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO product (product_id, product_type, name) VALUES (1, 'chem', 1), (2, 'chem', 1), (3, 'chem', 99);
+                INSERT INTO store_location (store_location_id, store_location_name, store_location_can_store, store_location_full_path, entity, store_location)
+                    VALUES (1, 'room', 0, 'room', 1, NULL), (2, 'shelf', 1, 'old/shelf', 1, 1),
+                    (3, 'a', 1, 'a', 1, 4), (4, 'b', 1, 'b', 1, 3);
+                INSERT INTO storage (storage_id, product, store_location, storage_barecode, storage)
+                    VALUES (1, 1, 2, 'A1.1', NULL), (2, 2, 2, 'A1.1', NULL), (3, 1, 1, 'A1.2', NULL),
+                    (4, 1, 2, 'A1.1', 1), (5, 1, 2, 'A1.1', 100);",
+            )
+            .unwrap();
+
+        let report = check_consistency(&mut db_connection, false).unwrap();
+
+        assert!(!report.is_consistent());
+        assert!(!report.repaired);
+        assert_eq!(
+            report.orphan_permissions,
+            vec![OrphanPermission {
+                person: 1,
+                permission_name: "w".to_string(),
+                permission_item: "storages".to_string(),
+                permission_entity: 42,
+            }]
+        );
+        assert_eq!(
+            report.stale_full_paths,
+            vec![StaleFullPath {
+                store_location_id: 2,
+                current_full_path: Some("old/shelf".to_string()),
+                expected_full_path: "room/shelf".to_string(),
+            }]
+        );
+        assert_eq!(report.store_location_cycles, vec![3, 4]);
+        assert_eq!(report.orphan_history_storages, vec![5]);
+        assert_eq!(report.storages_in_non_storing_locations, vec![3]);
+        assert_eq!(report.products_without_name, vec![3]);
+        assert_eq!(
+            report.duplicate_barecodes,
+            vec![DuplicateBarecode {
+                storage_barecode: "A1.1".to_string(),
+                storage_ids: vec![1, 2],
+            }]
+        );
+
+        // Repair.
+        let report = check_consistency(&mut db_connection, true).unwrap();
+        assert!(report.repaired);
+        assert_eq!(report.orphan_permissions.len(), 1);
+
+        let report = check_consistency(&mut db_connection, false).unwrap();
+        assert!(report.orphan_permissions.is_empty());
+        assert!(report.stale_full_paths.is_empty());
+        assert!(report.orphan_history_storages.is_empty());
+        // Not repairable.
+        assert_eq!(report.store_location_cycles, vec![3, 4]);
+        assert_eq!(report.products_without_name, vec![3]);
+    }
 }
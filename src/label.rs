@@ -0,0 +1,1181 @@
+use chimitheque_types::{product::Product as ProductStruct, requestfilter::RequestFilter};
+use image::{ImageFormat, Rgb, RgbImage};
+use log::debug;
+use rusqlite::Connection;
+use std::{
+    fmt::{Display, Formatter, Write},
+    io::Cursor,
+    str::FromStr,
+};
+
+use crate::{
+    pictogram::{Color, PICTOGRAM_BOX, Shape, pictogram_shapes},
+    product::get_products,
+    storage::get_storages,
+};
+
+// Resolution of the PNG labels.
+const LABEL_PNG_DPI: f64 = 300.0;
+
+// A4 sheet for the batch mode, in mm.
+const SHEET_WIDTH: f64 = 210.0;
+const SHEET_HEIGHT: f64 = 297.0;
+const SHEET_MARGIN: f64 = 10.0;
+const SHEET_GAP: f64 = 2.0;
+
+// Font of the SVG labels, monospaced so that the text wraps as in the PNG labels.
+const LABEL_FONT_FAMILY: &str = "DejaVu Sans Mono, monospace";
+
+// Width of a character relative to the font size.
+const CHAR_WIDTH: f64 = 0.6;
+
+// Height of a line of text relative to the font size.
+const LINE_HEIGHT: f64 = 1.2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LabelError {
+    ProductNotFound(u64),
+    StorageNotFound(u64),
+    UnknownSize(String),
+    UnknownFormat(String),
+    // The statements do not fit on the label, even as references.
+    StatementsDoNotFit(String),
+}
+
+impl Display for LabelError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            LabelError::ProductNotFound(id) => write!(f, "product {id} not found"),
+            LabelError::StorageNotFound(id) => write!(f, "storage {id} not found"),
+            LabelError::UnknownSize(s) => write!(f, "unknown label size {s}"),
+            LabelError::UnknownFormat(s) => write!(f, "unknown label format {s}"),
+            LabelError::StatementsDoNotFit(title) => write!(
+                f,
+                "the statements of {title} do not fit on the label, use a larger size"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LabelError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelSize {
+    // 50x25 mm, statements are printed as references only.
+    Small,
+    // 74x52 mm (A8).
+    Medium,
+    // 105x74 mm (A7).
+    Large,
+    // 148x105 mm (A6).
+    ExtraLarge,
+}
+
+impl LabelSize {
+    // Width and height in mm.
+    #[must_use]
+    pub fn dimensions(self) -> (f64, f64) {
+        match self {
+            LabelSize::Small => (50.0, 25.0),
+            LabelSize::Medium => (74.0, 52.0),
+            LabelSize::Large => (105.0, 74.0),
+            LabelSize::ExtraLarge => (148.0, 105.0),
+        }
+    }
+
+    fn full_statements(self) -> bool {
+        self != LabelSize::Small
+    }
+}
+
+impl FromStr for LabelSize {
+    type Err = LabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "small" => Ok(LabelSize::Small),
+            "medium" | "a8" => Ok(LabelSize::Medium),
+            "large" | "a7" => Ok(LabelSize::Large),
+            "extralarge" | "extra_large" | "a6" => Ok(LabelSize::ExtraLarge),
+            _ => Err(LabelError::UnknownSize(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+    Svg,
+    // Drawn with a built-in ASCII font, see glyph.
+    Png,
+}
+
+impl FromStr for LabelFormat {
+    type Err = LabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "svg" => Ok(LabelFormat::Svg),
+            "png" => Ok(LabelFormat::Png),
+            _ => Err(LabelError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// Content of a label.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Label {
+    pub title: String,
+    pub subtitle: Option<String>,
+    // Symbol labels, GHS01 to GHS09.
+    pub symbols: Vec<String>,
+    pub signal_word: Option<String>,
+    // (reference, label) of the statements.
+    pub hazard_statements: Vec<(String, String)>,
+    pub precautionary_statements: Vec<(String, String)>,
+    pub barecode: Option<String>,
+    // PNG image.
+    pub qrcode: Option<Vec<u8>>,
+}
+
+impl Label {
+    #[must_use]
+    pub fn from_product(product: &ProductStruct) -> Self {
+        let subtitle: Vec<String> = [
+            product.product_specificity.clone(),
+            product
+                .cas_number
+                .as_ref()
+                .map(|cas_number| format!("CAS {}", cas_number.cas_number_label)),
+            product
+                .ce_number
+                .as_ref()
+                .map(|ce_number| format!("EC {}", ce_number.ce_number_label)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect();
+
+        Label {
+            title: product.name.name_label.clone(),
+            subtitle: (!subtitle.is_empty()).then(|| subtitle.join(" - ")),
+            symbols: product
+                .symbols
+                .iter()
+                .flatten()
+                .map(|symbol| symbol.symbol_label.clone())
+                .collect(),
+            signal_word: product
+                .signal_word
+                .as_ref()
+                .map(|signal_word| signal_word.signal_word_label.clone()),
+            hazard_statements: product
+                .hazard_statements
+                .iter()
+                .flatten()
+                .map(|hazard_statement| {
+                    (
+                        hazard_statement.hazard_statement_reference.clone(),
+                        hazard_statement.hazard_statement_label.clone(),
+                    )
+                })
+                .collect(),
+            precautionary_statements: product
+                .precautionary_statements
+                .iter()
+                .flatten()
+                .map(|precautionary_statement| {
+                    (
+                        precautionary_statement
+                            .precautionary_statement_reference
+                            .clone(),
+                        precautionary_statement
+                            .precautionary_statement_label
+                            .clone(),
+                    )
+                })
+                .collect(),
+            barecode: None,
+            qrcode: None,
+        }
+    }
+}
+
+fn get_product(
+    db_connection: &Connection,
+    product_id: u64,
+    person_id: u64,
+) -> Result<ProductStruct, Box<dyn std::error::Error + Send + Sync>> {
+    let (mut products, _) = get_products(
+        db_connection,
+        RequestFilter {
+            id: Some(product_id),
+            ..Default::default()
+        },
+        person_id,
+    )?;
+
+    let Some(product) = products.pop() else {
+        return Err(Box::new(LabelError::ProductNotFound(product_id)));
+    };
+
+    Ok(product)
+}
+
+pub fn get_product_label(
+    db_connection: &Connection,
+    product_id: u64,
+    person_id: u64,
+) -> Result<Label, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("product_id:{product_id}");
+
+    Ok(Label::from_product(&get_product(
+        db_connection,
+        product_id,
+        person_id,
+    )?))
+}
+
+// Label of the storage product with the storage barecode and QR code.
+pub fn get_storage_label(
+    db_connection: &Connection,
+    storage_id: u64,
+    person_id: u64,
+) -> Result<Label, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("storage_id:{storage_id}");
+
+    // get_storages also returns the storages with the same barecode.
+    let (storages, _) = get_storages(
+        db_connection,
+        RequestFilter {
+            id: Some(storage_id),
+            ..Default::default()
+        },
+        person_id,
+    )?;
+
+    let Some(storage) = storages
+        .into_iter()
+        .find(|storage| storage.storage_id == Some(storage_id))
+    else {
+        return Err(Box::new(LabelError::StorageNotFound(storage_id)));
+    };
+
+    let Some(product_id) = storage.product.product_id else {
+        return Err(Box::new(LabelError::StorageNotFound(storage_id)));
+    };
+
+    let mut label = Label::from_product(&get_product(db_connection, product_id, person_id)?);
+    label.barecode = storage.storage_barecode;
+    label.qrcode = storage.storage_qrcode;
+
+    Ok(label)
+}
+
+//
+// Layout.
+//
+
+// Elements of a page, in mm.
+enum Element {
+    Shape {
+        shape: Shape,
+        color: Color,
+    },
+    // (x, y) is the top left corner of the text.
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        bold: bool,
+        text: String,
+    },
+    // Square PNG image.
+    Image {
+        x: f64,
+        y: f64,
+        side: f64,
+        png: Vec<u8>,
+    },
+    // Cutting outline of a label on a sheet.
+    Frame {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+}
+
+struct Page {
+    width: f64,
+    height: f64,
+    elements: Vec<Element>,
+}
+
+// Wrap the text in lines of at most max_chars characters, splitting the too long words.
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+
+        loop {
+            let line_len = line.chars().count();
+            let separator = usize::from(line_len > 0);
+
+            if line_len + separator + word.len() <= max_chars {
+                if separator > 0 {
+                    line.push(' ');
+                }
+                line.extend(word.iter());
+                break;
+            }
+
+            if line_len > 0 {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+
+            // Word longer than a line.
+            let rest = word.split_off(max_chars);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn max_chars(width: f64, size: f64) -> usize {
+    (width / (size * CHAR_WIDTH)).floor().max(1.0) as usize
+}
+
+// Replace the end of the line with "...", keeping its length.
+fn with_ellipsis(line: &str) -> String {
+    let mut chars: Vec<char> = line.chars().collect();
+    chars.truncate(chars.len().saturating_sub(3));
+
+    chars.into_iter().collect::<String>() + "..."
+}
+
+// Write the lines of text from y down to bottom, the last line ends with "..." if they do not all fit.
+// Return the y position after the text.
+fn push_lines(
+    elements: &mut Vec<Element>,
+    lines: Vec<String>,
+    x: f64,
+    mut y: f64,
+    bottom: f64,
+    size: f64,
+    bold: bool,
+) -> f64 {
+    let line_height = size * LINE_HEIGHT;
+    let nb_lines = lines.len();
+
+    for (i, mut line) in lines.into_iter().enumerate() {
+        if y + line_height > bottom {
+            break;
+        }
+
+        if i + 1 < nb_lines && y + 2.0 * line_height > bottom {
+            line = with_ellipsis(&line);
+        }
+
+        elements.push(Element::Text {
+            x,
+            y,
+            size,
+            bold,
+            text: line,
+        });
+
+        y += line_height;
+    }
+
+    y
+}
+
+// Return true if the lines of text fit from y down to bottom.
+#[allow(clippy::cast_precision_loss)]
+fn lines_fit(lines: &[String], y: f64, bottom: f64, size: f64) -> bool {
+    y + lines.len() as f64 * size * LINE_HEIGHT <= bottom + f64::EPSILON
+}
+
+// Statements lines as "H225 Highly flammable liquid and vapour".
+fn full_statement_lines(label: &Label, max_chars: usize) -> Vec<String> {
+    label
+        .hazard_statements
+        .iter()
+        .chain(label.precautionary_statements.iter())
+        .flat_map(|(reference, statement)| wrap(&format!("{reference} {statement}"), max_chars))
+        .collect()
+}
+
+// Statements lines as "H225, H319" then "P210, P280".
+fn reference_statement_lines(label: &Label, max_chars: usize) -> Vec<String> {
+    [&label.hazard_statements, &label.precautionary_statements]
+        .into_iter()
+        .filter(|statements| !statements.is_empty())
+        .flat_map(|statements| {
+            let references: Vec<&str> = statements
+                .iter()
+                .map(|(reference, _)| reference.as_str())
+                .collect();
+            wrap(&references.join(", "), max_chars)
+        })
+        .collect()
+}
+
+// Lay out the label with its top left corner at (x, y).
+// The statements are printed as references when their full text does not fit,
+// and an error is returned when the references do not fit either.
+#[allow(clippy::cast_precision_loss)]
+fn layout_label(
+    elements: &mut Vec<Element>,
+    label: &Label,
+    size: LabelSize,
+    x: f64,
+    y: f64,
+) -> Result<(), LabelError> {
+    let (width, height) = size.dimensions();
+    // Sizes grow with the label up to a readable size, leaving more room for the statements.
+    let padding = (height * 0.05).min(4.0);
+    let title_size = (height * 0.09).min(5.0);
+    let text_size = if size.full_statements() {
+        (height * 0.045).min(2.5)
+    } else {
+        height * 0.07
+    };
+    let bottom = y + height - padding;
+
+    // QR code and barecode on the right, the text is narrower down to qrcode_bottom.
+    let mut right = x + width - padding;
+    let mut qrcode_bottom = y;
+    if let Some(qrcode) = &label.qrcode {
+        let qrcode_side = (height * 0.4).min(width * 0.3).min(28.0);
+        qrcode_bottom = y + padding + qrcode_side + text_size * LINE_HEIGHT;
+
+        elements.push(Element::Image {
+            x: right - qrcode_side,
+            y: y + padding,
+            side: qrcode_side,
+            png: qrcode.clone(),
+        });
+
+        if let Some(barecode) = &label.barecode {
+            let barecode_width = barecode.chars().count() as f64 * text_size * CHAR_WIDTH;
+
+            elements.push(Element::Text {
+                x: right - qrcode_side / 2.0 - barecode_width / 2.0,
+                y: y + padding + qrcode_side,
+                size: text_size,
+                bold: true,
+                text: barecode.clone(),
+            });
+        }
+
+        right -= qrcode_side + padding;
+    }
+
+    let left = x + padding;
+    let text_width = right - left;
+    let mut cursor = y + padding;
+
+    // Title on two lines at most, and subtitle.
+    let title_chars = max_chars(text_width, title_size);
+    let mut title_lines = wrap(&label.title, title_chars);
+    if title_lines.len() > 2 {
+        title_lines.truncate(2);
+        title_lines[1] = with_ellipsis(&title_lines[1]);
+    }
+    cursor = push_lines(
+        elements,
+        title_lines,
+        left,
+        cursor,
+        bottom,
+        title_size,
+        true,
+    );
+
+    let mut subtitle = label.subtitle.clone().unwrap_or_default();
+    if label.qrcode.is_none()
+        && let Some(barecode) = &label.barecode
+    {
+        if !subtitle.is_empty() {
+            subtitle.push_str(" - ");
+        }
+        subtitle.push_str(barecode);
+    }
+    if !subtitle.is_empty() {
+        let text_chars = max_chars(text_width, text_size);
+        cursor = push_lines(
+            elements,
+            wrap(&subtitle, text_chars),
+            left,
+            cursor,
+            bottom,
+            text_size,
+            false,
+        );
+    }
+
+    // Pictograms on a single row, smaller when there are many of them,
+    // followed by the signal word if there is room left.
+    let pictograms: Vec<Vec<(Shape, Color)>> = label
+        .symbols
+        .iter()
+        .filter_map(|symbol| pictogram_shapes(symbol))
+        .collect();
+    let mut signal_word = label.signal_word.as_ref().map(|s| s.to_uppercase());
+
+    if !pictograms.is_empty() {
+        let row_right = if cursor < qrcode_bottom {
+            right
+        } else {
+            x + width - padding
+        };
+        let nb_pictograms = pictograms.len() as f64;
+        let pictogram_side = (height * 0.3)
+            .min(22.0)
+            .min((row_right - left) / (nb_pictograms * 1.05 - 0.05))
+            .min((bottom - cursor) / 1.1);
+
+        if pictogram_side > 0.0 {
+            let pictogram_gap = pictogram_side * 0.05;
+            let mut pictogram_x = left;
+            cursor += pictogram_gap;
+
+            for shapes in &pictograms {
+                for (shape, color) in shapes {
+                    elements.push(Element::Shape {
+                        shape: shape.place(pictogram_x, cursor, pictogram_side / PICTOGRAM_BOX),
+                        color: *color,
+                    });
+                }
+
+                pictogram_x += pictogram_side + pictogram_gap;
+            }
+
+            if let Some(text) = &signal_word {
+                let signal_word_x = pictogram_x + padding;
+                let signal_word_width = text.chars().count() as f64 * title_size * CHAR_WIDTH;
+
+                if signal_word_x + signal_word_width <= row_right {
+                    elements.push(Element::Text {
+                        x: signal_word_x,
+                        y: cursor + (pictogram_side - title_size) / 2.0,
+                        size: title_size,
+                        bold: true,
+                        text: text.clone(),
+                    });
+                    signal_word = None;
+                }
+            }
+
+            cursor += pictogram_side + pictogram_gap;
+        }
+    }
+
+    // Under the QR code, the text can use the whole label width.
+    if cursor >= qrcode_bottom {
+        right = x + width - padding;
+    }
+    let text_width = right - left;
+
+    // Signal word, when not beside the pictograms.
+    if let Some(text) = signal_word {
+        cursor = push_lines(elements, vec![text], left, cursor, bottom, title_size, true);
+    }
+
+    // Statements, never silently cut.
+    let text_chars = max_chars(text_width, text_size);
+    let mut lines = if size.full_statements() {
+        full_statement_lines(label, text_chars)
+    } else {
+        Vec::new()
+    };
+    if lines.is_empty() || !lines_fit(&lines, cursor, bottom, text_size) {
+        lines = reference_statement_lines(label, text_chars);
+    }
+    if !lines_fit(&lines, cursor, bottom, text_size) {
+        return Err(LabelError::StatementsDoNotFit(label.title.clone()));
+    }
+
+    push_lines(elements, lines, left, cursor, bottom, text_size, false);
+
+    Ok(())
+}
+
+//
+// SVG rendering.
+//
+
+// Escape a text for XML, removing the characters XML 1.0 does not allow.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn render_svg(page: &Page) -> Vec<u8> {
+    let mut svg = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{0}mm" height="{1}mm" viewBox="0 0 {0} {1}">
+<rect width="{0}" height="{1}" fill="{2}"/>
+"#,
+        page.width,
+        page.height,
+        Color::White.hex()
+    );
+
+    for element in &page.elements {
+        // Writing to a String can not fail.
+        let _ = match element {
+            Element::Shape {
+                shape: Shape::Polygon(points),
+                color,
+            } => writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                points
+                    .iter()
+                    .map(|(x, y)| format!("{x:.2},{y:.2}"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                color.hex()
+            ),
+            Element::Shape {
+                shape: Shape::Circle { cx, cy, r },
+                color,
+            } => writeln!(
+                svg,
+                r#"<circle cx="{cx:.2}" cy="{cy:.2}" r="{r:.2}" fill="{}"/>"#,
+                color.hex()
+            ),
+            Element::Text {
+                x,
+                y,
+                size,
+                bold,
+                text,
+            } => writeln!(
+                svg,
+                r#"<text x="{x:.2}" y="{:.2}" font-family="{LABEL_FONT_FAMILY}" font-size="{size:.2}"{} xml:space="preserve">{}</text>"#,
+                y + size * 0.8,
+                if *bold { r#" font-weight="bold""# } else { "" },
+                escape_xml(text)
+            ),
+            Element::Image { x, y, side, png } => writeln!(
+                svg,
+                r#"<image x="{x:.2}" y="{y:.2}" width="{side:.2}" height="{side:.2}" href="data:image/png;base64,{}"/>"#,
+                base64_encode(png)
+            ),
+            Element::Frame {
+                x,
+                y,
+                width,
+                height,
+            } => writeln!(
+                svg,
+                r#"<rect x="{x:.2}" y="{y:.2}" width="{width:.2}" height="{height:.2}" fill="none" stroke="{}" stroke-width="0.2"/>"#,
+                Color::Grey.hex()
+            ),
+        };
+    }
+
+    svg.push_str("</svg>\n");
+
+    svg.into_bytes()
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let triple = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(
+                    ALPHABET[((triple >> (18 - 6 * i)) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+//
+// PNG rendering.
+//
+
+// 5x7 font for the printable ASCII characters, one byte per column, least significant bit on top.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x56, 0x20, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+// Glyphs of the unit characters, that must not be replaced by a look-alike
+// letter (µL is not uL). The micro sign and the greek mu are drawn alike.
+const UNIT_GLYPHS: [(char, [u8; 5]); 4] = [
+    ('µ', [0x7e, 0x20, 0x20, 0x10, 0x3e]),
+    ('μ', [0x7e, 0x20, 0x20, 0x10, 0x3e]),
+    ('°', [0x00, 0x06, 0x09, 0x09, 0x06]),
+    ('±', [0x48, 0x48, 0x5e, 0x48, 0x48]),
+];
+
+// Glyph of the character. The font covers printable ASCII and the unit
+// characters: the accented latin letters are drawn without their accent,
+// and the other characters (greek letters, subscripts, arrows...) as '?'.
+// The SVG labels are not affected.
+fn glyph(c: char) -> &'static [u8; 5] {
+    if let Some((_, unit_glyph)) = UNIT_GLYPHS.iter().find(|(unit_char, _)| *unit_char == c) {
+        return unit_glyph;
+    }
+
+    let c = match c {
+        'à' | 'â' | 'ä' | 'á' => 'a',
+        'ç' => 'c',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'î' | 'ï' | 'í' => 'i',
+        'ô' | 'ö' | 'ó' => 'o',
+        'ù' | 'û' | 'ü' | 'ú' => 'u',
+        'À' | 'Â' | 'Ä' | 'Á' => 'A',
+        'Ç' => 'C',
+        'É' | 'È' | 'Ê' | 'Ë' => 'E',
+        'Î' | 'Ï' | 'Í' => 'I',
+        'Ô' | 'Ö' | 'Ó' => 'O',
+        'Ù' | 'Û' | 'Ü' | 'Ú' => 'U',
+        c if (' '..='~').contains(&c) => c,
+        _ => '?',
+    };
+
+    &FONT[c as usize - 0x20]
+}
+
+// Fill the pixels whose center is in the rectangle, coordinates in pixels.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn fill_rect(image: &mut RgbImage, x0: f64, y0: f64, x1: f64, y1: f64, color: Color) {
+    let x_start = (x0 - 0.5).ceil().max(0.0) as u32;
+    let y_start = (y0 - 0.5).ceil().max(0.0) as u32;
+    let x_end = ((x1 - 0.5).ceil().max(0.0) as u32).min(image.width());
+    let y_end = ((y1 - 0.5).ceil().max(0.0) as u32).min(image.height());
+
+    for py in y_start..y_end {
+        for px in x_start..x_end {
+            image.put_pixel(px, py, Rgb(color.rgb()));
+        }
+    }
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn fill_shape(image: &mut RgbImage, shape: &Shape, scale: f64, color: Color) {
+    match shape {
+        Shape::Polygon(points) => {
+            let points: Vec<(f64, f64)> =
+                points.iter().map(|(x, y)| (x * scale, y * scale)).collect();
+            let y_min = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+            let y_max = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
+            let y_start = (y_min - 0.5).ceil().max(0.0) as u32;
+            let y_end = ((y_max - 0.5).ceil().max(0.0) as u32).min(image.height());
+
+            // Scanline with the even-odd rule at the pixel centers.
+            for py in y_start..y_end {
+                let y = f64::from(py) + 0.5;
+                let mut crossings: Vec<f64> = Vec::new();
+
+                for (i, (x1, y1)) in points.iter().enumerate() {
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    if (*y1 <= y) != (y2 <= y) {
+                        crossings.push(x1 + (y - y1) / (y2 - y1) * (x2 - x1));
+                    }
+                }
+
+                crossings.sort_by(f64::total_cmp);
+
+                for pair in crossings.chunks_exact(2) {
+                    fill_rect(image, pair[0], y - 0.5, pair[1], y + 0.5, color);
+                }
+            }
+        }
+        Shape::Circle { cx, cy, r } => {
+            let (cx, cy, r) = (cx * scale, cy * scale, r * scale);
+
+            let y_start = (cy - r - 0.5).ceil().max(0.0) as u32;
+            let y_end = ((cy + r - 0.5).ceil().max(0.0) as u32).min(image.height());
+
+            for py in y_start..y_end {
+                let dy = f64::from(py) + 0.5 - cy;
+                let dx = (r * r - dy * dy).max(0.0).sqrt();
+
+                fill_rect(
+                    image,
+                    cx - dx,
+                    f64::from(py),
+                    cx + dx,
+                    f64::from(py) + 1.0,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn draw_text(image: &mut RgbImage, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+    // A character is a 6x10 grid of dots, the glyph starting at the second row.
+    let dot = size * CHAR_WIDTH / 6.0;
+    let dot_width = if bold { dot * 1.5 } else { dot };
+
+    for (i, c) in text.chars().enumerate() {
+        let char_x = x + i as f64 * dot * 6.0;
+
+        for (column, bits) in glyph(c).iter().enumerate() {
+            for row in 0..7 {
+                if (bits >> row) & 1 == 1 {
+                    let dot_x = char_x + column as f64 * dot;
+                    let dot_y = y + f64::from(row + 1) * dot;
+
+                    fill_rect(
+                        image,
+                        dot_x,
+                        dot_y,
+                        dot_x + dot_width,
+                        dot_y + dot,
+                        Color::Black,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn draw_image(
+    image: &mut RgbImage,
+    x: f64,
+    y: f64,
+    side: f64,
+    png: &[u8],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = image::load_from_memory_with_format(png, ImageFormat::Png)?.to_luma8();
+
+    let x_start = x.round().max(0.0) as u32;
+    let y_start = y.round().max(0.0) as u32;
+    let side_pixels = side.round() as u32;
+
+    // Nearest neighbour scaling.
+    for py in 0..side_pixels {
+        for px in 0..side_pixels {
+            let (target_x, target_y) = (x_start + px, y_start + py);
+            if target_x >= image.width() || target_y >= image.height() {
+                continue;
+            }
+
+            let source_x =
+                (u64::from(px) * u64::from(source.width()) / u64::from(side_pixels)) as u32;
+            let source_y =
+                (u64::from(py) * u64::from(source.height()) / u64::from(side_pixels)) as u32;
+            let luma = source.get_pixel(source_x, source_y).0[0];
+
+            image.put_pixel(target_x, target_y, Rgb([luma, luma, luma]));
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn render_png(page: &Page) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    // Pixels per mm.
+    let scale = LABEL_PNG_DPI / 25.4;
+
+    let mut image = RgbImage::from_pixel(
+        (page.width * scale).round() as u32,
+        (page.height * scale).round() as u32,
+        Rgb(Color::White.rgb()),
+    );
+
+    for element in &page.elements {
+        match element {
+            Element::Shape { shape, color } => fill_shape(&mut image, shape, scale, *color),
+            Element::Text {
+                x,
+                y,
+                size,
+                bold,
+                text,
+            } => draw_text(&mut image, x * scale, y * scale, size * scale, *bold, text),
+            Element::Image { x, y, side, png } => {
+                draw_image(&mut image, x * scale, y * scale, side * scale, png)?;
+            }
+            Element::Frame {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (x0, y0) = (x * scale, y * scale);
+                let (x1, y1) = ((x + width) * scale, (y + height) * scale);
+
+                fill_rect(&mut image, x0, y0, x1, y0 + 1.0, Color::Grey);
+                fill_rect(&mut image, x0, y1 - 1.0, x1, y1, Color::Grey);
+                fill_rect(&mut image, x0, y0, x0 + 1.0, y1, Color::Grey);
+                fill_rect(&mut image, x1 - 1.0, y0, x1, y1, Color::Grey);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+fn render_page(
+    page: &Page,
+    format: LabelFormat,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    match format {
+        LabelFormat::Svg => Ok(render_svg(page)),
+        LabelFormat::Png => render_png(page),
+    }
+}
+
+// Render a single label.
+pub fn render_label(
+    label: &Label,
+    size: LabelSize,
+    format: LabelFormat,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("label:{label:?}");
+
+    let (width, height) = size.dimensions();
+    let mut page = Page {
+        width,
+        height,
+        elements: Vec::new(),
+    };
+
+    layout_label(&mut page.elements, label, size, 0.0, 0.0)?;
+
+    render_page(&page, format)
+}
+
+// Number of labels per A4 sheet, as (columns, rows).
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+#[must_use]
+pub fn labels_per_sheet(size: LabelSize) -> (usize, usize) {
+    let (width, height) = size.dimensions();
+
+    (
+        ((SHEET_WIDTH - 2.0 * SHEET_MARGIN + SHEET_GAP) / (width + SHEET_GAP)).floor() as usize,
+        ((SHEET_HEIGHT - 2.0 * SHEET_MARGIN + SHEET_GAP) / (height + SHEET_GAP)).floor() as usize,
+    )
+}
+
+// Lay out the labels on A4 sheets with cutting outlines.
+// Return one rendered page per sheet.
+#[allow(clippy::cast_precision_loss)]
+pub fn render_label_sheets(
+    labels: &[Label],
+    size: LabelSize,
+    format: LabelFormat,
+) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("nb_labels:{}", labels.len());
+
+    let (width, height) = size.dimensions();
+    let (columns, rows) = labels_per_sheet(size);
+
+    // The labels are centered horizontally.
+    let grid_width = columns as f64 * (width + SHEET_GAP) - SHEET_GAP;
+    let left = (SHEET_WIDTH - grid_width) / 2.0;
+
+    labels
+        .chunks(columns * rows)
+        .map(|sheet_labels| {
+            let mut page = Page {
+                width: SHEET_WIDTH,
+                height: SHEET_HEIGHT,
+                elements: Vec::new(),
+            };
+
+            for (i, label) in sheet_labels.iter().enumerate() {
+                let x = left + (i % columns) as f64 * (width + SHEET_GAP);
+                let y = SHEET_MARGIN + (i / columns) as f64 * (height + SHEET_GAP);
+
+                page.elements.push(Element::Frame {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+                layout_label(&mut page.elements, label, size, x, y)?;
+            }
+
+            render_page(&page, format)
+        })
+        .collect()
+}
+
+// Render the labels of the storages on A4 sheets.
+pub fn render_storage_label_sheets(
+    db_connection: &Connection,
+    storage_ids: &[u64],
+    person_id: u64,
+    size: LabelSize,
+    format: LabelFormat,
+) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("storage_ids:{storage_ids:?}");
+
+    let labels = storage_ids
+        .iter()
+        .map(|storage_id| get_storage_label(db_connection, *storage_id, person_id))
+        .collect::<Result<Vec<Label>, _>>()?;
+
+    render_label_sheets(&labels, size, format)
+}
+
+#[cfg(test)]
+#[path = "label_tests.rs"]
+mod label_tests;
@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::{init::populate_db_with_base_data, label::*};
+
+    fn init_test_label() -> Connection {
+        let mut db_connection = crate::test_utils::init_test();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO entity (entity_id, entity_name) VALUES (1, 'lab');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES
+                    (1, 'all', 'all', NULL);
+                INSERT INTO store_location (store_location_id, store_location_name, store_location_can_store, store_location_full_path, entity, store_location) VALUES
+                    (1, 'cabinet [CAB]', 1, 'cabinet [CAB]', 1, NULL);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO cas_number (cas_number_id, cas_number_label) VALUES (1, '64-17-5');
+                INSERT INTO product (product_id, product_type, person, name, cas_number, product_specificity, signal_word) VALUES
+                    (1, 'chem', 1, 1, 1, 'absolute', (SELECT signal_word_id FROM signal_word WHERE signal_word_label = 'danger'));
+                INSERT INTO productsymbols (productsymbols_product_id, productsymbols_symbol_id)
+                    SELECT 1, symbol_id FROM symbol WHERE symbol_label IN ('GHS02', 'GHS07');
+                INSERT INTO producthazardstatements (producthazardstatements_product_id, producthazardstatements_hazard_statement_id)
+                    SELECT 1, hazard_statement_id FROM hazard_statement WHERE hazard_statement_reference = 'H225';
+                INSERT INTO storage (storage_id, storage_creation_date, storage_modification_date, storage_barecode, person, product, store_location, storage) VALUES
+                    (1, 1704067200, 1704067200, 'CAB1.1', 1, 1, 1, NULL);",
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    fn test_label() -> Label {
+        Label {
+            title: "ACIDE CHLORHYDRIQUE".to_string(),
+            subtitle: Some("CAS 7647-01-0".to_string()),
+            symbols: vec!["GHS05".to_string(), "GHS07".to_string()],
+            signal_word: Some("danger".to_string()),
+            hazard_statements: vec![(
+                "H314".to_string(),
+                "Causes severe skin burns & eye damage.".to_string(),
+            )],
+            precautionary_statements: vec![(
+                "P280".to_string(),
+                "Wear protective gloves.".to_string(),
+            )],
+            barecode: Some("CAB1.1".to_string()),
+            qrcode: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_size_and_format() {
+        assert_eq!("medium".parse::<LabelSize>(), Ok(LabelSize::Medium));
+        assert_eq!("A6".parse::<LabelSize>(), Ok(LabelSize::ExtraLarge));
+        assert_eq!(
+            "huge".parse::<LabelSize>(),
+            Err(LabelError::UnknownSize("huge".to_string()))
+        );
+        assert_eq!("PNG".parse::<LabelFormat>(), Ok(LabelFormat::Png));
+        assert_eq!(
+            "pdf".parse::<LabelFormat>(),
+            Err(LabelError::UnknownFormat("pdf".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("Causes severe skin burns", 12),
+            vec!["Causes", "severe skin", "burns"]
+        );
+        assert_eq!(wrap("P305+P351+P338", 5), vec!["P305+", "P351+", "P338"]);
+        assert!(wrap("  ", 5).is_empty());
+        assert_eq!(with_ellipsis("irritation"), "irritat...");
+    }
+
+    #[test]
+    fn test_glyph() {
+        assert_eq!(glyph('é'), glyph('e'));
+        assert_eq!(glyph('α'), glyph('?'));
+        // Unit characters keep their own glyph.
+        assert_ne!(glyph('µ'), glyph('u'));
+        assert_eq!(glyph('μ'), glyph('µ'));
+        assert_ne!(glyph('°'), glyph('o'));
+        assert_ne!(glyph('µ'), glyph('?'));
+        assert_ne!(glyph('°'), glyph('?'));
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"M"), "TQ==");
+        assert_eq!(base64_encode(b"Ma"), "TWE=");
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+    }
+
+    #[test]
+    fn test_render_label_svg() {
+        let svg = String::from_utf8(
+            render_label(&test_label(), LabelSize::Medium, LabelFormat::Svg).unwrap(),
+        )
+        .unwrap();
+
+        assert!(svg.contains(r#"width="74mm" height="52mm" viewBox="0 0 74 52""#));
+        assert!(svg.contains(">ACIDE CHLORHYDRIQUE</text>"));
+        assert!(svg.contains(">DANGER</text>"));
+        assert!(svg.contains(">H314 Causes severe skin burns &amp; eye damage.</text>"));
+        assert!(svg.contains(">CAS 7647-01-0 - CAB1.1</text>"));
+        // Two pictograms with their red border.
+        assert_eq!(svg.matches(r##"fill="#e30613""##).count(), 2);
+
+        // Small labels only show the statement references.
+        let svg = String::from_utf8(
+            render_label(&test_label(), LabelSize::Small, LabelFormat::Svg).unwrap(),
+        )
+        .unwrap();
+        assert!(svg.contains(">H314</text>"));
+        assert!(!svg.contains("Causes severe skin burns"));
+    }
+
+    #[test]
+    fn test_render_label_statements_do_not_fit() {
+        let mut label = test_label();
+        label.hazard_statements = (300..320)
+            .map(|reference| {
+                (
+                    format!("H{reference}"),
+                    "Causes severe skin burns and eye damage.".to_string(),
+                )
+            })
+            .collect();
+
+        // The full statements do not fit, the references are printed instead.
+        let svg =
+            String::from_utf8(render_label(&label, LabelSize::Medium, LabelFormat::Svg).unwrap())
+                .unwrap();
+        assert!(!svg.contains("Causes severe skin burns"));
+        assert!(svg.contains("H300, H301"));
+        assert!(svg.contains("H319</text>"));
+        assert!(svg.contains(">P280</text>"));
+
+        // Not even the references.
+        label.hazard_statements = (200..400)
+            .map(|reference| (format!("H{reference}"), String::new()))
+            .collect();
+        assert_eq!(
+            render_label(&label, LabelSize::Small, LabelFormat::Png)
+                .unwrap_err()
+                .downcast_ref::<LabelError>(),
+            Some(&LabelError::StatementsDoNotFit(
+                "ACIDE CHLORHYDRIQUE".to_string()
+            ))
+        );
+        assert!(render_label_sheets(&[label], LabelSize::Small, LabelFormat::Svg).is_err());
+    }
+
+    #[test]
+    fn test_render_label_png() {
+        let mut label = test_label();
+        label.qrcode = Some(
+            qrcode_png::QrCode::new("1", qrcode_png::QrCodeEcc::Medium)
+                .unwrap()
+                .generate(qrcode_png::Color::Grayscale(0, 255))
+                .unwrap(),
+        );
+
+        let png = render_label(&label, LabelSize::Large, LabelFormat::Png).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+
+        // 105x74 mm at 300 dpi.
+        assert_eq!(image.dimensions(), (1240, 874));
+        assert!(image.pixels().any(|pixel| pixel.0 == [227, 6, 19]));
+        assert!(image.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+
+        // The QR code is embedded in the SVG labels.
+        let svg =
+            String::from_utf8(render_label(&label, LabelSize::Large, LabelFormat::Svg).unwrap())
+                .unwrap();
+        assert!(svg.contains(r#"href="data:image/png;base64,iVBORw0KGgo"#));
+        assert!(svg.contains(">CAB1.1</text>"));
+    }
+
+    #[test]
+    fn test_render_label_sheets() {
+        assert_eq!(labels_per_sheet(LabelSize::Small), (3, 10));
+        assert_eq!(labels_per_sheet(LabelSize::Medium), (2, 5));
+        assert_eq!(labels_per_sheet(LabelSize::ExtraLarge), (1, 2));
+
+        let labels = vec![test_label(); 25];
+
+        let pages = render_label_sheets(&labels, LabelSize::Medium, LabelFormat::Svg).unwrap();
+        assert_eq!(pages.len(), 3);
+
+        let first_page = String::from_utf8(pages[0].clone()).unwrap();
+        assert!(first_page.contains(r#"width="210mm" height="297mm""#));
+        assert_eq!(first_page.matches(r#"fill="none""#).count(), 10);
+
+        let last_page = String::from_utf8(pages[2].clone()).unwrap();
+        assert_eq!(last_page.matches(r#"fill="none""#).count(), 5);
+
+        let pages = render_label_sheets(&labels[..3], LabelSize::Small, LabelFormat::Png).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            image::load_from_memory(&pages[0])
+                .unwrap()
+                .to_rgb8()
+                .dimensions(),
+            (2480, 3508)
+        );
+
+        assert!(
+            render_label_sheets(&[], LabelSize::Small, LabelFormat::Png)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_get_labels() {
+        let db_connection = init_test_label();
+
+        let label = get_product_label(&db_connection, 1, 1).unwrap();
+        assert_eq!(label.title, "ETHANOL");
+        assert_eq!(label.subtitle, Some("absolute - CAS 64-17-5".to_string()));
+        assert_eq!(label.symbols, vec!["GHS02", "GHS07"]);
+        assert_eq!(label.signal_word, Some("danger".to_string()));
+        assert_eq!(
+            label
+                .hazard_statements
+                .iter()
+                .map(|(reference, _)| reference.as_str())
+                .collect::<Vec<_>>(),
+            vec!["H225"]
+        );
+        assert_eq!(label.barecode, None);
+
+        let label = get_storage_label(&db_connection, 1, 1).unwrap();
+        assert_eq!(label.title, "ETHANOL");
+        assert_eq!(label.barecode, Some("CAB1.1".to_string()));
+
+        assert_eq!(
+            get_storage_label(&db_connection, 42, 1)
+                .unwrap_err()
+                .downcast_ref::<LabelError>(),
+            Some(&LabelError::StorageNotFound(42))
+        );
+
+        let pages = render_storage_label_sheets(
+            &db_connection,
+            &[1, 1],
+            1,
+            LabelSize::Medium,
+            LabelFormat::Svg,
+        )
+        .unwrap();
+        assert_eq!(pages.len(), 1);
+    }
+}
@@ -12,6 +12,7 @@
     clippy::too_many_lines
 )]
 
+pub mod backup;
 pub mod bookmark;
 pub mod borrowing;
 pub mod casbin;
@@ -19,24 +20,32 @@ pub mod casnumber;
 pub mod category;
 pub mod cenumber;
 pub mod classofcompound;
+pub mod connection;
 pub mod define;
 pub mod empiricalformula;
 pub mod entity;
 pub mod entitypeople;
+pub mod export;
 pub mod hazardstatement;
 pub mod init;
+pub mod label;
 pub mod linearformula;
+pub mod migration;
+pub mod molecularweight;
 pub mod name;
 pub mod permission;
 pub mod person;
 pub mod personentities;
 pub mod physicalstate;
+mod pictogram;
 pub mod precautionarystatement;
 pub mod producer;
 pub mod producerref;
 pub mod product;
 pub mod productclassesofcompounds;
 pub mod producthazardstatements;
+pub mod producthistory;
+pub mod productimport;
 pub mod productprecautionarystatements;
 pub mod productsupplierrefs;
 pub mod productsymbols;
@@ -45,12 +54,16 @@ pub mod producttags;
 pub mod pubchemproduct;
 pub mod searchable;
 pub mod signalword;
+pub mod sqlfunctions;
 pub mod stock;
 pub mod storage;
+pub mod storageimport;
 pub mod storelocation;
 pub mod supplier;
 pub mod supplierref;
 pub mod symbol;
+pub mod synthetic;
 pub mod tag;
 pub mod test_utils;
 pub mod unit;
+pub mod xlsx;
@@ -0,0 +1,187 @@
+use log::info;
+use rusqlite::{Batch, Connection, fallible_iterator::FallibleIterator};
+use std::fmt::{Display, Formatter};
+
+use crate::empiricalformula::update_empirical_formula_elements;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    DatabaseTooRecent {
+        database_version: u32,
+        library_version: u32,
+    },
+    UnknownVersion(u32),
+    Downgrade {
+        from_version: u32,
+        to_version: u32,
+    },
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            MigrationError::DatabaseTooRecent {
+                database_version,
+                library_version,
+            } => write!(
+                f,
+                "database schema version {database_version} is newer than the library schema version {library_version}"
+            ),
+            MigrationError::UnknownVersion(version) => {
+                write!(f, "unknown schema version {version}")
+            }
+            MigrationError::Downgrade {
+                from_version,
+                to_version,
+            } => write!(
+                f,
+                "can not downgrade schema from version {from_version} to version {to_version}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+// Data step run after the SQL of a migration, in the same transaction,
+// for the rows that can not be computed in SQL.
+// Return the number of updated rows.
+pub type MigrationData = fn(&Connection) -> Result<usize, Box<dyn std::error::Error + Send + Sync>>;
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+    pub data: Option<MigrationData>,
+}
+
+// Migration steps, ordered by version.
+// The version is stored in the database with PRAGMA user_version.
+// Version 10 is the base schema. It is also the version stamped by the
+// Go to Rust migration script, so imported databases start from there.
+// Never modify a released step: add a new one with a higher version instead.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 10,
+        description: "base schema",
+        sql: include_str!("resources/shema.sql"),
+        data: None,
+    },
+    Migration {
+        version: 11,
+        description: "CAS number CMR source",
+        sql: include_str!("resources/migrations/0011_cas_number_cmr_source.sql"),
+        data: None,
+    },
+    Migration {
+        version: 12,
+        description: "product full text search",
+        sql: include_str!("resources/migrations/0012_product_fts.sql"),
+        data: None,
+    },
+    Migration {
+        version: 13,
+        description: "empirical formula elements",
+        sql: include_str!("resources/migrations/0013_empirical_formula_element.sql"),
+        data: Some(update_empirical_formula_elements),
+    },
+    Migration {
+        version: 14,
+        description: "product merges",
+        sql: include_str!("resources/migrations/0014_product_merge.sql"),
+        data: None,
+    },
+    Migration {
+        version: 15,
+        description: "product revisions",
+        sql: include_str!("resources/migrations/0015_product_revision.sql"),
+        data: None,
+    },
+];
+
+#[must_use]
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub fn current_version(db_connection: &Connection) -> Result<u32, rusqlite::Error> {
+    db_connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+// Return the database version, or an error if the database has been
+// created by a more recent version of the library.
+pub fn check_version(
+    db_connection: &Connection,
+) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    let database_version = current_version(db_connection)?;
+    let library_version = latest_version();
+
+    if database_version > library_version {
+        return Err(Box::new(MigrationError::DatabaseTooRecent {
+            database_version,
+            library_version,
+        }));
+    }
+
+    Ok(database_version)
+}
+
+// Apply the migration steps up to (and including) the given version.
+// Each step runs in its own transaction.
+pub fn migrate_to(
+    db_connection: &mut Connection,
+    version: u32,
+) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    if !MIGRATIONS
+        .iter()
+        .any(|migration| migration.version == version)
+    {
+        return Err(Box::new(MigrationError::UnknownVersion(version)));
+    }
+
+    let database_version = check_version(db_connection)?;
+
+    if version < database_version {
+        return Err(Box::new(MigrationError::Downgrade {
+            from_version: database_version,
+            to_version: version,
+        }));
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > database_version && migration.version <= version)
+    {
+        info!(
+            "applying migration {}: {}",
+            migration.version, migration.description
+        );
+
+        let tx = db_connection.transaction()?;
+
+        let mut batch = Batch::new(&tx, migration.sql);
+        while let Some(mut stmt) = batch.next()? {
+            stmt.execute([])?;
+        }
+
+        if let Some(data) = migration.data {
+            let nb_updated = data(&tx)?;
+            info!("updated {nb_updated} rows");
+        }
+
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(current_version(db_connection)?)
+}
+
+pub fn migrate(
+    db_connection: &mut Connection,
+) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    migrate_to(db_connection, latest_version())
+}
+
+#[cfg(test)]
+#[path = "migration_tests.rs"]
+mod migration_tests;
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::migration::*;
+    use rusqlite::Connection;
+
+    fn init_test_migration() -> Connection {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        Connection::open_in_memory().unwrap()
+    }
+
+    #[test]
+    fn test_current_version_new_database() {
+        let db_connection = init_test_migration();
+
+        assert_eq!(current_version(&db_connection).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut db_connection = init_test_migration();
+
+        let version = migrate(&mut db_connection).unwrap();
+
+        assert_eq!(version, latest_version());
+        assert_eq!(current_version(&db_connection).unwrap(), latest_version());
+
+        // Tables are created.
+        let count: u64 = db_connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='storage'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Migrating twice is a no-op.
+        assert_eq!(migrate(&mut db_connection).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_migrate_data_step() {
+        let mut db_connection = init_test_migration();
+
+        migrate_to(&mut db_connection, 12).unwrap();
+        db_connection
+            .execute(
+                "INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES (1, 'C2H6O')",
+                [],
+            )
+            .unwrap();
+
+        // The existing formulas are indexed.
+        migrate_to(&mut db_connection, 13).unwrap();
+
+        let count: u64 = db_connection
+            .query_row(
+                "SELECT COUNT(*) FROM empirical_formula_element WHERE empirical_formula = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_migrate_to_unknown_version() {
+        let mut db_connection = init_test_migration();
+
+        let err = migrate_to(&mut db_connection, latest_version() + 1).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<MigrationError>(),
+            Some(&MigrationError::UnknownVersion(latest_version() + 1))
+        );
+        assert_eq!(current_version(&db_connection).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migrate_database_too_recent() {
+        let mut db_connection = init_test_migration();
+
+        db_connection
+            .pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        let err = check_version(&db_connection).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MigrationError>(),
+            Some(&MigrationError::DatabaseTooRecent {
+                database_version: latest_version() + 1,
+                library_version: latest_version(),
+            })
+        );
+
+        assert!(migrate(&mut db_connection).is_err());
+    }
+
+    #[test]
+    fn test_migrate_downgrade() {
+        let mut db_connection = init_test_migration();
+
+        migrate(&mut db_connection).unwrap();
+
+        let first_version = MIGRATIONS.first().unwrap().version;
+        if first_version < latest_version() {
+            let err = migrate_to(&mut db_connection, first_version).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<MigrationError>(),
+                Some(&MigrationError::Downgrade {
+                    from_version: latest_version(),
+                    to_version: first_version,
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use log::{debug, info, warn};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+use crate::empiricalformula::{FormulaError, parse_element_counts};
+
+// Standard atomic weights in g/mol (IUPAC abridged values).
+// Elements without a standard atomic weight use the mass number
+// of their longest-lived isotope.
+// D and T are accepted for deuterated and tritiated compounds.
+static ATOMIC_WEIGHTS: &[(&str, f64)] = &[
+    ("H", 1.008),
+    ("D", 2.014),
+    ("T", 3.016),
+    ("He", 4.0026),
+    ("Li", 6.94),
+    ("Be", 9.0122),
+    ("B", 10.81),
+    ("C", 12.011),
+    ("N", 14.007),
+    ("O", 15.999),
+    ("F", 18.998),
+    ("Ne", 20.180),
+    ("Na", 22.990),
+    ("Mg", 24.305),
+    ("Al", 26.982),
+    ("Si", 28.085),
+    ("P", 30.974),
+    ("S", 32.06),
+    ("Cl", 35.45),
+    ("Ar", 39.95),
+    ("K", 39.098),
+    ("Ca", 40.078),
+    ("Sc", 44.956),
+    ("Ti", 47.867),
+    ("V", 50.942),
+    ("Cr", 51.996),
+    ("Mn", 54.938),
+    ("Fe", 55.845),
+    ("Co", 58.933),
+    ("Ni", 58.693),
+    ("Cu", 63.546),
+    ("Zn", 65.38),
+    ("Ga", 69.723),
+    ("Ge", 72.630),
+    ("As", 74.922),
+    ("Se", 78.971),
+    ("Br", 79.904),
+    ("Kr", 83.798),
+    ("Rb", 85.468),
+    ("Sr", 87.62),
+    ("Y", 88.906),
+    ("Zr", 91.224),
+    ("Nb", 92.906),
+    ("Mo", 95.95),
+    ("Tc", 97.0),
+    ("Ru", 101.07),
+    ("Rh", 102.91),
+    ("Pd", 106.42),
+    ("Ag", 107.87),
+    ("Cd", 112.41),
+    ("In", 114.82),
+    ("Sn", 118.71),
+    ("Sb", 121.76),
+    ("Te", 127.60),
+    ("I", 126.90),
+    ("Xe", 131.29),
+    ("Cs", 132.91),
+    ("Ba", 137.33),
+    ("La", 138.91),
+    ("Ce", 140.12),
+    ("Pr", 140.91),
+    ("Nd", 144.24),
+    ("Pm", 145.0),
+    ("Sm", 150.36),
+    ("Eu", 151.96),
+    ("Gd", 157.25),
+    ("Tb", 158.93),
+    ("Dy", 162.50),
+    ("Ho", 164.93),
+    ("Er", 167.26),
+    ("Tm", 168.93),
+    ("Yb", 173.05),
+    ("Lu", 174.97),
+    ("Hf", 178.49),
+    ("Ta", 180.95),
+    ("W", 183.84),
+    ("Re", 186.21),
+    ("Os", 190.23),
+    ("Ir", 192.22),
+    ("Pt", 195.08),
+    ("Au", 196.97),
+    ("Hg", 200.59),
+    ("Tl", 204.38),
+    ("Pb", 207.2),
+    ("Bi", 208.98),
+    ("Po", 209.0),
+    ("At", 210.0),
+    ("Rn", 222.0),
+    ("Fr", 223.0),
+    ("Ra", 226.0),
+    ("Ac", 227.0),
+    ("Th", 232.04),
+    ("Pa", 231.04),
+    ("U", 238.03),
+    ("Np", 237.0),
+    ("Pu", 244.0),
+    ("Am", 243.0),
+    ("Cm", 247.0),
+    ("Bk", 247.0),
+    ("Cf", 251.0),
+    ("Es", 252.0),
+    ("Fm", 257.0),
+    ("Md", 258.0),
+    ("No", 259.0),
+    ("Lr", 266.0),
+    ("Rf", 267.0),
+    ("Db", 268.0),
+    ("Sg", 269.0),
+    ("Bh", 270.0),
+    ("Hs", 277.0),
+    ("Mt", 278.0),
+    ("Ds", 281.0),
+    ("Rg", 282.0),
+    ("Cn", 285.0),
+    ("Nh", 286.0),
+    ("Fl", 289.0),
+    ("Mc", 290.0),
+    ("Lv", 293.0),
+    ("Ts", 294.0),
+    ("Og", 294.0),
+];
+
+// Maximum difference in g/mol between an entered and a computed molecular weight.
+pub const DEFAULT_MOLECULAR_WEIGHT_TOLERANCE: f64 = 0.1;
+
+#[derive(Debug, PartialEq)]
+pub enum MolecularWeightError {
+    Formula(FormulaError),
+    UnknownElement(String),
+    Mismatch { entered: f64, computed: f64 },
+}
+
+impl Display for MolecularWeightError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            MolecularWeightError::Formula(err) => write!(f, "{err}"),
+            MolecularWeightError::UnknownElement(element) => {
+                write!(f, "unknown element: {element}")
+            }
+            MolecularWeightError::Mismatch { entered, computed } => write!(
+                f,
+                "molecular weight {entered} does not match the formula molecular weight {computed}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MolecularWeightError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MolecularWeightError::Formula(err) => Some(err),
+            MolecularWeightError::UnknownElement(_) | MolecularWeightError::Mismatch { .. } => None,
+        }
+    }
+}
+
+impl From<FormulaError> for MolecularWeightError {
+    fn from(err: FormulaError) -> Self {
+        match err {
+            FormulaError::UnknownElement { element, .. } => {
+                MolecularWeightError::UnknownElement(element)
+            }
+            err => MolecularWeightError::Formula(err),
+        }
+    }
+}
+
+#[must_use]
+pub fn atomic_weight(element: &str) -> Option<f64> {
+    ATOMIC_WEIGHTS
+        .iter()
+        .find(|(symbol, _)| *symbol == element)
+        .map(|(_, weight)| *weight)
+}
+
+// Round to the precision of the atomic weights table.
+fn round_molecular_weight(molecular_weight: f64) -> f64 {
+    (molecular_weight * 100.0).round() / 100.0
+}
+
+// Return the molecular weight in g/mol of an empirical or linear formula,
+// rounded to two decimals. Hydrates are supported: CuSO4.5H2O.
+pub fn compute_molecular_weight(formula: &str) -> Result<f64, MolecularWeightError> {
+    let mut molecular_weight = 0.0;
+
+    for (element, count) in parse_element_counts(formula)? {
+        let weight =
+            atomic_weight(&element).ok_or(MolecularWeightError::UnknownElement(element))?;
+
+        molecular_weight += weight * f64::from(count);
+    }
+
+    Ok(round_molecular_weight(molecular_weight))
+}
+
+// Check an entered molecular weight in g/mol against the one of the formula.
+// Return the computed molecular weight.
+pub fn validate_molecular_weight(
+    formula: &str,
+    molecular_weight: f64,
+    tolerance: f64,
+) -> Result<f64, MolecularWeightError> {
+    let computed = compute_molecular_weight(formula)?;
+
+    if (computed - molecular_weight).abs() > tolerance {
+        return Err(MolecularWeightError::Mismatch {
+            entered: molecular_weight,
+            computed,
+        });
+    }
+
+    Ok(computed)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MolecularWeightSkip {
+    pub product_id: u64,
+    pub empirical_formula_label: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MolecularWeightBackfillReport {
+    pub updated: usize,
+    pub skipped: Vec<MolecularWeightSkip>,
+    pub dry_run: bool,
+}
+
+// Set the molecular weight of the products having an empirical formula
+// but no molecular weight. The unit is set to g/mol, the one of the
+// computed weight, replacing any unit left without a weight.
+pub fn backfill_molecular_weights(
+    db_connection: &mut Connection,
+    dry_run: bool,
+) -> Result<MolecularWeightBackfillReport, Box<dyn std::error::Error + Send + Sync>> {
+    info!("backfilling molecular weights");
+
+    let mut report = MolecularWeightBackfillReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let tx = db_connection.transaction()?;
+
+    let maybe_unit_id: Option<u64> = tx
+        .query_row(
+            "SELECT unit_id FROM unit WHERE unit_label = 'g/mol' AND unit_type = 'molecular_weight'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let sql = "SELECT product.product_id, empirical_formula.empirical_formula_label FROM product
+        JOIN empirical_formula ON product.empirical_formula = empirical_formula.empirical_formula_id
+        WHERE product.product_molecular_weight IS NULL
+        ORDER BY product.product_id";
+
+    debug!("sql: {sql}");
+
+    let products = tx
+        .prepare(sql)?
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (product_id, empirical_formula_label) in products {
+        let molecular_weight = match compute_molecular_weight(&empirical_formula_label) {
+            Ok(molecular_weight) => molecular_weight,
+            Err(err) => {
+                warn!("skipping product {product_id} ({empirical_formula_label}): {err}");
+
+                report.skipped.push(MolecularWeightSkip {
+                    product_id,
+                    empirical_formula_label,
+                    error: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        tx.execute(
+            "UPDATE product SET product_molecular_weight = ?1, unit_molecular_weight = ?2
+            WHERE product_id = ?3",
+            (molecular_weight, maybe_unit_id, product_id),
+        )?;
+
+        report.updated += 1;
+    }
+
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+    }
+
+    info!("updated: {}", report.updated);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "molecularweight_tests.rs"]
+mod molecularweight_tests;
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines,
+        clippy::float_cmp
+    )]
+
+    use crate::empiricalformula::FormulaError;
+    use crate::molecularweight::*;
+
+    #[test]
+    fn test_compute_molecular_weight() {
+        assert_eq!(atomic_weight("C"), Some(12.011));
+        assert_eq!(atomic_weight("Xx"), None);
+
+        assert_eq!(compute_molecular_weight("C2H6O").unwrap(), 46.07);
+        assert_eq!(compute_molecular_weight("CH3CH2OH").unwrap(), 46.07);
+        assert_eq!(compute_molecular_weight("NaCl").unwrap(), 58.44);
+        // Hydrates.
+        assert_eq!(compute_molecular_weight("CuSO4.5H2O").unwrap(), 249.68);
+        assert_eq!(compute_molecular_weight("CuSO4·5H2O").unwrap(), 249.68);
+
+        assert_eq!(
+            compute_molecular_weight("C2Xx"),
+            Err(MolecularWeightError::UnknownElement("Xx".to_string()))
+        );
+        assert_eq!(
+            compute_molecular_weight(""),
+            Err(MolecularWeightError::Formula(FormulaError::Empty))
+        );
+    }
+
+    #[test]
+    fn test_validate_molecular_weight() {
+        assert_eq!(
+            validate_molecular_weight("C2H6O", 46.068, DEFAULT_MOLECULAR_WEIGHT_TOLERANCE).unwrap(),
+            46.07
+        );
+        assert_eq!(
+            validate_molecular_weight("C2H6O", 46.5, DEFAULT_MOLECULAR_WEIGHT_TOLERANCE),
+            Err(MolecularWeightError::Mismatch {
+                entered: 46.5,
+                computed: 46.07
+            })
+        );
+        assert!(validate_molecular_weight("C2H6O", 46.5, 1.0).is_ok());
+    }
+
+    #[test]
+    fn test_backfill_molecular_weights() {
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO unit (unit_id, unit_label, unit_multiplier, unit_type) VALUES
+                    (1, 'g/mol', 1, 'molecular_weight'), (2, 'kg/mol', 1000, 'molecular_weight');
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES
+                    (1, 'C2H6O'), (2, 'C6H6'), (3, 'C2Xx');
+                INSERT INTO product (product_id, product_type, name, empirical_formula, product_molecular_weight) VALUES
+                    (1, 'chem', 1, 1, NULL), (2, 'chem', 1, 2, 80.0), (3, 'chem', 1, 3, NULL), (4, 'chem', 1, NULL, NULL);
+                INSERT INTO product (product_id, product_type, name, empirical_formula, product_molecular_weight, unit_molecular_weight) VALUES
+                    (5, 'chem', 1, 2, NULL, 2);",
+            )
+            .unwrap();
+
+        let molecular_weight = |db_connection: &rusqlite::Connection,
+                                product_id: u64|
+         -> (Option<f64>, Option<u64>) {
+            db_connection
+                .query_row(
+                    "SELECT product_molecular_weight, unit_molecular_weight FROM product WHERE product_id = ?1",
+                    [product_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap()
+        };
+
+        // Dry run.
+        let report = backfill_molecular_weights(&mut db_connection, true).unwrap();
+        assert_eq!(report.updated, 2);
+        assert_eq!(molecular_weight(&db_connection, 1), (None, None));
+
+        let report = backfill_molecular_weights(&mut db_connection, false).unwrap();
+        assert_eq!(report.updated, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].product_id, 3);
+
+        assert_eq!(molecular_weight(&db_connection, 1), (Some(46.07), Some(1)));
+        // Existing values are kept.
+        assert_eq!(molecular_weight(&db_connection, 2), (Some(80.0), None));
+        assert_eq!(molecular_weight(&db_connection, 4), (None, None));
+        // The unit left without a weight is replaced by the one of the computed weight.
+        assert_eq!(molecular_weight(&db_connection, 5), (Some(78.11), Some(1)));
+    }
+}
@@ -0,0 +1,283 @@
+// GHS pictograms artwork.
+// Pictograms are drawn in a 100x100 box with simple filled shapes, so that
+// they can be rendered both as SVG and as PNG. They are simplified renditions
+// of the symbols, not the UNECE reference artwork.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Color {
+    Black,
+    White,
+    Red,
+    Grey,
+}
+
+impl Color {
+    pub(crate) fn hex(self) -> &'static str {
+        match self {
+            Color::Black => "#000000",
+            Color::White => "#ffffff",
+            Color::Red => "#e30613",
+            Color::Grey => "#b0b0b0",
+        }
+    }
+
+    pub(crate) fn rgb(self) -> [u8; 3] {
+        match self {
+            Color::Black => [0, 0, 0],
+            Color::White => [255, 255, 255],
+            Color::Red => [227, 6, 19],
+            Color::Grey => [176, 176, 176],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    // Filled with the even-odd rule.
+    Polygon(Vec<(f64, f64)>),
+    Circle { cx: f64, cy: f64, r: f64 },
+}
+
+impl Shape {
+    // Scale the shape by factor and move it to (x, y).
+    pub(crate) fn place(&self, x: f64, y: f64, factor: f64) -> Shape {
+        match self {
+            Shape::Polygon(points) => Shape::Polygon(
+                points
+                    .iter()
+                    .map(|(px, py)| (x + px * factor, y + py * factor))
+                    .collect(),
+            ),
+            Shape::Circle { cx, cy, r } => Shape::Circle {
+                cx: x + cx * factor,
+                cy: y + cy * factor,
+                r: r * factor,
+            },
+        }
+    }
+}
+
+// Size of the box pictograms are drawn in.
+pub(crate) const PICTOGRAM_BOX: f64 = 100.0;
+
+fn rect(x: f64, y: f64, width: f64, height: f64) -> Shape {
+    Shape::Polygon(vec![
+        (x, y),
+        (x + width, y),
+        (x + width, y + height),
+        (x, y + height),
+    ])
+}
+
+// Thick line from (x1, y1) to (x2, y2).
+fn bar(x1: f64, y1: f64, x2: f64, y2: f64, width: f64) -> Shape {
+    let length = (x2 - x1).hypot(y2 - y1);
+    let (nx, ny) = (
+        -(y2 - y1) / length * width / 2.0,
+        (x2 - x1) / length * width / 2.0,
+    );
+
+    Shape::Polygon(vec![
+        (x1 + nx, y1 + ny),
+        (x2 + nx, y2 + ny),
+        (x2 - nx, y2 - ny),
+        (x1 - nx, y1 - ny),
+    ])
+}
+
+fn circle(cx: f64, cy: f64, r: f64) -> Shape {
+    Shape::Circle { cx, cy, r }
+}
+
+fn polygon(points: &[(f64, f64)]) -> Shape {
+    Shape::Polygon(points.to_vec())
+}
+
+// Star with the given number of branches.
+fn star(cx: f64, cy: f64, outer: f64, inner: f64, branches: u32) -> Shape {
+    let step = std::f64::consts::PI / f64::from(branches);
+
+    Shape::Polygon(
+        (0..branches * 2)
+            .map(|i| {
+                let radius = if i % 2 == 0 { outer } else { inner };
+                let angle = f64::from(i) * step - std::f64::consts::FRAC_PI_2;
+
+                (cx + radius * angle.cos(), cy + radius * angle.sin())
+            })
+            .collect(),
+    )
+}
+
+// Return the shapes of the pictogram of the symbol (GHS01 to GHS09), None if unknown.
+pub(crate) fn pictogram_shapes(symbol_label: &str) -> Option<Vec<(Shape, Color)>> {
+    use Color::{Black, White};
+
+    let symbol: Vec<(Shape, Color)> = match symbol_label {
+        // Exploding bomb.
+        "GHS01" => vec![
+            (circle(42.0, 62.0, 12.0), Black),
+            (bar(49.0, 52.0, 56.0, 44.0, 3.0), Black),
+            (star(62.0, 39.0, 11.0, 5.0, 8), Black),
+            (rect(30.0, 38.0, 4.0, 4.0), Black),
+            (rect(68.0, 56.0, 4.0, 4.0), Black),
+        ],
+        // Flame.
+        "GHS02" => vec![
+            (
+                polygon(&[
+                    (38.0, 68.0),
+                    (34.0, 58.0),
+                    (37.0, 48.0),
+                    (42.0, 42.0),
+                    (42.0, 50.0),
+                    (46.0, 46.0),
+                    (48.0, 34.0),
+                    (54.0, 26.0),
+                    (53.0, 38.0),
+                    (58.0, 44.0),
+                    (60.0, 36.0),
+                    (65.0, 46.0),
+                    (66.0, 58.0),
+                    (62.0, 68.0),
+                ]),
+                Black,
+            ),
+            (rect(35.0, 70.0, 30.0, 4.0), Black),
+        ],
+        // Flame over circle.
+        "GHS03" => vec![
+            (
+                polygon(&[
+                    (40.0, 54.0),
+                    (38.0, 44.0),
+                    (42.0, 38.0),
+                    (44.0, 43.0),
+                    (48.0, 34.0),
+                    (52.0, 28.0),
+                    (53.0, 36.0),
+                    (57.0, 40.0),
+                    (59.0, 35.0),
+                    (62.0, 44.0),
+                    (60.0, 54.0),
+                ]),
+                Black,
+            ),
+            (circle(50.0, 60.0, 12.0), Black),
+            (circle(50.0, 60.0, 6.0), White),
+            (rect(37.0, 73.0, 26.0, 3.0), Black),
+        ],
+        // Gas cylinder.
+        "GHS04" => vec![
+            (bar(38.0, 62.0, 58.0, 44.0, 14.0), Black),
+            (circle(38.0, 62.0, 7.0), Black),
+            (circle(58.0, 44.0, 7.0), Black),
+            (bar(60.0, 42.0, 67.0, 35.0, 4.0), Black),
+            (bar(63.0, 32.0, 70.0, 39.0, 3.0), Black),
+        ],
+        // Corrosion.
+        "GHS05" => vec![
+            (bar(34.0, 28.0, 44.0, 40.0, 5.0), Black),
+            (bar(66.0, 28.0, 56.0, 40.0, 5.0), Black),
+            (circle(44.0, 47.0, 2.0), Black),
+            (circle(56.0, 47.0, 2.0), Black),
+            (
+                polygon(&[
+                    (30.0, 56.0),
+                    (40.0, 56.0),
+                    (42.0, 60.0),
+                    (46.0, 56.0),
+                    (48.0, 56.0),
+                    (48.0, 62.0),
+                    (30.0, 62.0),
+                ]),
+                Black,
+            ),
+            (
+                polygon(&[
+                    (52.0, 56.0),
+                    (56.0, 60.0),
+                    (58.0, 56.0),
+                    (70.0, 56.0),
+                    (70.0, 62.0),
+                    (52.0, 62.0),
+                ]),
+                Black,
+            ),
+            (rect(32.0, 66.0, 36.0, 5.0), Black),
+        ],
+        // Skull and crossbones.
+        "GHS06" => vec![
+            (circle(50.0, 40.0, 11.0), Black),
+            (rect(44.0, 46.0, 12.0, 8.0), Black),
+            (circle(46.0, 40.0, 3.0), White),
+            (circle(54.0, 40.0, 3.0), White),
+            (polygon(&[(50.0, 44.0), (48.0, 48.0), (52.0, 48.0)]), White),
+            (bar(36.0, 58.0, 64.0, 72.0, 4.0), Black),
+            (bar(36.0, 72.0, 64.0, 58.0, 4.0), Black),
+            (circle(36.0, 58.0, 2.5), Black),
+            (circle(64.0, 72.0, 2.5), Black),
+            (circle(36.0, 72.0, 2.5), Black),
+            (circle(64.0, 58.0, 2.5), Black),
+        ],
+        // Exclamation mark.
+        "GHS07" => vec![
+            (rect(45.0, 28.0, 10.0, 30.0), Black),
+            (circle(50.0, 66.0, 5.0), Black),
+        ],
+        // Health hazard.
+        "GHS08" => vec![
+            (circle(50.0, 30.0, 7.0), Black),
+            (
+                polygon(&[
+                    (36.0, 74.0),
+                    (36.0, 50.0),
+                    (42.0, 40.0),
+                    (58.0, 40.0),
+                    (64.0, 50.0),
+                    (64.0, 74.0),
+                ]),
+                Black,
+            ),
+            (star(50.0, 57.0, 9.0, 4.0, 8), White),
+        ],
+        // Environment.
+        "GHS09" => vec![
+            (bar(37.0, 70.0, 39.0, 36.0, 3.0), Black),
+            (bar(37.5, 50.0, 30.0, 42.0, 2.0), Black),
+            (bar(38.0, 44.0, 45.0, 36.0, 2.0), Black),
+            (bar(32.0, 70.0, 70.0, 70.0, 2.0), Black),
+            (
+                polygon(&[
+                    (45.0, 58.0),
+                    (50.0, 62.0),
+                    (55.0, 57.0),
+                    (62.0, 56.0),
+                    (67.0, 61.0),
+                    (62.0, 66.0),
+                    (55.0, 66.0),
+                    (50.0, 62.0),
+                    (45.0, 66.0),
+                ]),
+                Black,
+            ),
+            (circle(62.0, 60.0, 1.2), White),
+        ],
+        _ => return None,
+    };
+
+    // Red diamond border.
+    let mut shapes = vec![
+        (
+            polygon(&[(50.0, 1.0), (99.0, 50.0), (50.0, 99.0), (1.0, 50.0)]),
+            Color::Red,
+        ),
+        (
+            polygon(&[(50.0, 9.0), (91.0, 50.0), (50.0, 91.0), (9.0, 50.0)]),
+            White,
+        ),
+    ];
+    shapes.extend(symbol);
+
+    Some(shapes)
+}
@@ -58,7 +58,10 @@ pub fn get_producers(
     let (select_sql, select_values) = expression
         .columns([Producer::ProducerId, Producer::ProducerLabel])
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(Producer::ProducerLabel)),
+            Expr::cust_with_expr(
+                "? COLLATE UNICODE_NOCASE",
+                Expr::col(Producer::ProducerLabel),
+            ),
             Order::Asc,
         )
         .conditions(
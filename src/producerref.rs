@@ -94,7 +94,10 @@ pub fn get_producer_refs(
             Alias::new("producer.producer_label"),
         )
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(ProducerRef::ProducerRefLabel)),
+            Expr::cust_with_expr(
+                "? COLLATE UNICODE_NOCASE",
+                Expr::col(ProducerRef::ProducerRefLabel),
+            ),
             Order::Asc,
         )
         .conditions(
@@ -6,11 +6,14 @@ use crate::{
     cenumber::CeNumber,
     classofcompound::ClassOfCompound,
     define::STORAGE_BARECODE_RE,
-    empiricalformula::EmpiricalFormula,
+    empiricalformula::{EmpiricalFormula, to_hill_formula},
     entity::{Entity, EntityWrapper},
     entitypeople::Entitypeople,
     hazardstatement::HazardStatement,
     linearformula::LinearFormula,
+    molecularweight::{
+        DEFAULT_MOLECULAR_WEIGHT_TOLERANCE, MolecularWeightError, validate_molecular_weight,
+    },
     name::Name,
     permission::Permission,
     person::{Person, PersonWrapper},
@@ -20,6 +23,7 @@ use crate::{
     producerref::{self, ProducerRef},
     productclassesofcompounds::{Productclassesofcompounds, ProductclassesofcompoundsWrapper},
     producthazardstatements::{Producthazardstatements, ProducthazardstatementsWrapper},
+    producthistory::{create_product_baseline_revision, create_product_revision},
     productprecautionarystatements::{
         Productprecautionarystatements, ProductprecautionarystatementsWrapper,
     },
@@ -29,7 +33,7 @@ use crate::{
     producttags::{Producttags, ProducttagsWrapper},
     searchable,
     signalword::SignalWord,
-    storage::Storage,
+    storage::{Storage, move_storage_to_product},
     storelocation::StoreLocation,
     supplier::Supplier,
     supplierref::{self, SupplierRef},
@@ -51,15 +55,20 @@ use chimitheque_types::{
     tag::Tag as TagStruct, unit::Unit as UnitStruct, unittype::UnitType,
 };
 use csv::WriterBuilder;
-use log::debug;
-use rusqlite::{Connection, Row, Transaction};
+use log::{debug, warn};
+use rusqlite::{Connection, OptionalExtension, Row, ToSql, Transaction, params, params_from_iter};
 use sea_query::{
     Alias, ColumnRef, Cond, Expr, ExprTrait, Iden, IntoColumnRef, JoinType, OnConflict, Order,
     Query, SimpleExpr, SqliteQueryBuilder, any,
 };
 use sea_query_rusqlite::{RusqliteBinder, RusqliteValues};
 use serde::Serialize;
-use std::{collections::HashSet, io::BufWriter, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter},
+    io::BufWriter,
+    str::FromStr,
+};
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Iden)]
@@ -306,7 +315,7 @@ fn populate_product_availability(
             ])
             .group_by_col(Entity::EntityId)
             .order_by_expr(
-                Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(Entity::EntityName)),
+                Expr::cust_with_expr("? COLLATE UNICODE_NOCASE", Expr::col(Entity::EntityName)),
                 Order::Asc,
             )
             .from(Storage::Table)
@@ -1146,8 +1155,39 @@ pub fn get_products(
     db_connection: &Connection,
     filter: RequestFilter,
     person_id: u64,
+) -> Result<(Vec<ProductStruct>, usize), Box<dyn std::error::Error + Send + Sync>> {
+    get_products_with_ids(db_connection, filter, None, person_id)
+}
+
+// Return the products with the given ids, in a single query.
+pub(crate) fn get_products_by_ids(
+    db_connection: &Connection,
+    product_ids: &[u64],
+    person_id: u64,
+) -> Result<Vec<ProductStruct>, Box<dyn std::error::Error + Send + Sync>> {
+    if product_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (products, _) = get_products_with_ids(
+        db_connection,
+        RequestFilter::default(),
+        Some(product_ids),
+        person_id,
+    )?;
+
+    Ok(products)
+}
+
+// get_products, restricted to the product_ids if given.
+fn get_products_with_ids(
+    db_connection: &Connection,
+    filter: RequestFilter,
+    product_ids: Option<&[u64]>,
+    person_id: u64,
 ) -> Result<(Vec<ProductStruct>, usize), Box<dyn std::error::Error + Send + Sync>> {
     debug!("filter:{filter:?}");
+    debug!("product_ids:{product_ids:?}");
     debug!("person_id:{person_id:?}");
 
     // Does the person has the permission to access the restricted products?
@@ -1503,6 +1543,16 @@ pub fn get_products(
                     },
                     |_| {},
         )
+        .conditions(
+            product_ids.is_some(),
+            |q| {
+                q.and_where(
+                    Expr::col((Product::Table, Product::ProductId))
+                        .is_in(product_ids.unwrap_or_default().iter().copied()),
+                );
+            },
+            |_| {},
+        )
         .conditions(
             filter.custom_name_part_of.is_some(),
             |q| {
@@ -1862,7 +1912,7 @@ pub fn get_products(
         )
         .group_by_col((Product::Table, Product::ProductId))
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(order_by)),
+            Expr::cust_with_expr("? COLLATE UNICODE_NOCASE", Expr::col(order_by)),
             order,
         )
         .conditions(
@@ -1921,13 +1971,64 @@ pub fn get_products(
     Ok((products, count))
 }
 
+// Return the mismatch between the molecular weight in g/mol of the product
+// and the one computed from its empirical formula, for the caller to warn
+// about it. Products whose formula can not be computed have no mismatch.
+// A stored weight may legitimately differ: hydrate or salt weight with the
+// anhydrous formula, isotopic variant, rounded value...
+#[must_use]
+pub fn check_product_molecular_weight(product: &ProductStruct) -> Option<MolecularWeightError> {
+    let molecular_weight = product.product_molecular_weight?;
+    let empirical_formula = product.empirical_formula.as_ref()?;
+
+    if product
+        .unit_molecular_weight
+        .as_ref()
+        .is_some_and(|unit| unit.unit_label != "g/mol")
+    {
+        return None;
+    }
+
+    match validate_molecular_weight(
+        &empirical_formula.empirical_formula_label,
+        molecular_weight,
+        DEFAULT_MOLECULAR_WEIGHT_TOLERANCE,
+    ) {
+        Err(err @ MolecularWeightError::Mismatch { .. }) => Some(err),
+        Err(err) => {
+            debug!(
+                "can not check molecular weight against {}: {err}",
+                empirical_formula.empirical_formula_label
+            );
+            None
+        }
+        Ok(_) => None,
+    }
+}
+
 pub fn create_update_product(
     db_connection: &mut Connection,
+    product: ProductStruct,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let db_transaction = db_connection.transaction()?;
+
+    let product_id = create_update_product_in_transaction(&db_transaction, product)?;
+
+    db_transaction.commit()?;
+
+    Ok(product_id)
+}
+
+// Same as create_update_product, in a transaction managed by the caller.
+pub(crate) fn create_update_product_in_transaction(
+    db_transaction: &Transaction,
     mut product: ProductStruct,
 ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
     debug!("create_update_product: {product:#?}");
 
-    let db_transaction = db_connection.transaction()?;
+    if let Some(product_id) = product.product_id {
+        create_product_baseline_revision(db_transaction, product_id)?;
+    }
 
     //
     // name
@@ -1941,7 +2042,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             name.name_label.as_str(),
         )?;
         product.name = NameStruct {
@@ -1964,7 +2065,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             cas_number.cas_number_label.as_str(),
         )?;
         product.cas_number = Some(CasNumberStruct {
@@ -1987,7 +2088,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             ce_number.ce_number_label.as_str(),
         )?;
         product.ce_number = Some(CeNumberStruct {
@@ -1997,6 +2098,35 @@ pub fn create_update_product(
         });
     }
 
+    //
+    // empirical formula derived from the linear formula
+    //
+    if product.empirical_formula.is_none()
+        && let Some(linear_formula) = &product.linear_formula
+    {
+        match to_hill_formula(&linear_formula.linear_formula_label) {
+            Ok(empirical_formula_label) => {
+                let maybe_empirical_formula_id: Option<u64> = db_transaction
+                    .query_row(
+                        "SELECT empirical_formula_id FROM empirical_formula WHERE empirical_formula_label = ?1",
+                        [&empirical_formula_label],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+
+                product.empirical_formula = Some(EmpiricalFormulaStruct {
+                    empirical_formula_id: maybe_empirical_formula_id,
+                    empirical_formula_label,
+                    ..Default::default()
+                });
+            }
+            Err(err) => debug!(
+                "can not derive empirical formula from {}: {err}",
+                linear_formula.linear_formula_label
+            ),
+        }
+    }
+
     //
     // empirical formula
     //
@@ -2010,7 +2140,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             empirical_formula.empirical_formula_label.as_str(),
         )?;
         product.empirical_formula = Some(EmpiricalFormulaStruct {
@@ -2020,6 +2150,14 @@ pub fn create_update_product(
         });
     }
 
+    //
+    // molecular weight in g/mol checked against the empirical formula,
+    // a mismatch does not prevent saving the product
+    //
+    if let Some(err) = check_product_molecular_weight(&product) {
+        warn!("product {:?}: {err}", product.product_id);
+    }
+
     //
     // linear formula
     //
@@ -2033,7 +2171,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             linear_formula.linear_formula_label.as_str(),
         )?;
         product.linear_formula = Some(LinearFormulaStruct {
@@ -2056,7 +2194,7 @@ pub fn create_update_product(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             category.category_label.as_str(),
         )?;
         product.category = Some(CategoryStruct {
@@ -2073,7 +2211,7 @@ pub fn create_update_product(
         && producer_ref.producer_ref_id.is_none()
     {
         let producer_ref_id = Some(producerref::create_update_producer_ref(
-            &db_transaction,
+            db_transaction,
             &producer_ref,
         )?);
         product.producer_ref = Some(ProducerRefStruct {
@@ -2099,7 +2237,7 @@ pub fn create_update_product(
                         ..Default::default()
                     },
                     None,
-                    &db_transaction,
+                    db_transaction,
                     name.name_label.as_str(),
                 )?);
             }
@@ -2129,7 +2267,7 @@ pub fn create_update_product(
                         ..Default::default()
                     },
                     None,
-                    &db_transaction,
+                    db_transaction,
                     class_of_compound.class_of_compound_label.as_str(),
                 )?);
             }
@@ -2153,7 +2291,7 @@ pub fn create_update_product(
             let mut supplier_ref_id = supplier_ref.supplier_ref_id;
             if supplier_ref_id.is_none() {
                 supplier_ref_id = Some(supplierref::create_update_supplier_ref(
-                    &db_transaction,
+                    db_transaction,
                     &supplier_ref,
                 )?);
             }
@@ -2183,7 +2321,7 @@ pub fn create_update_product(
                         ..Default::default()
                     },
                     None,
-                    &db_transaction,
+                    db_transaction,
                     tag.tag_label.as_str(),
                 )?);
             }
@@ -2527,15 +2665,19 @@ pub fn create_update_product(
 
     // --
 
-    create_update_product_classes_of_compound(&db_transaction, &product)?;
-    create_update_product_synonyms(&db_transaction, &product)?;
-    create_update_product_symbols(&db_transaction, &product)?;
-    create_update_product_precautionary_statements(&db_transaction, &product)?;
-    create_update_product_hazard_statements(&db_transaction, &product)?;
-    create_update_product_supplier_refs(&db_transaction, &product)?;
-    create_update_product_tags(&db_transaction, &product)?;
+    create_update_product_classes_of_compound(db_transaction, &product)?;
+    create_update_product_synonyms(db_transaction, &product)?;
+    create_update_product_symbols(db_transaction, &product)?;
+    create_update_product_precautionary_statements(db_transaction, &product)?;
+    create_update_product_hazard_statements(db_transaction, &product)?;
+    create_update_product_supplier_refs(db_transaction, &product)?;
+    create_update_product_tags(db_transaction, &product)?;
 
-    db_transaction.commit()?;
+    create_product_revision(
+        db_transaction,
+        last_insert_update_id,
+        product.person.person_id,
+    )?;
 
     Ok(last_insert_update_id)
 }
@@ -2985,6 +3127,858 @@ fn create_update_product_classes_of_compound(
     Ok(())
 }
 
+//
+// Full text search.
+//
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ProductSearchResult {
+    pub product_id: u64,
+    pub name_label: String,
+    // BM25 rank, the lower the better.
+    pub rank: f64,
+    // Best matching column extract, matches surrounded by <b></b>.
+    pub snippet: String,
+}
+
+// SQL condition on the product table: as for get_products, the person must
+// have a products permission, and the rproducts permission to see the
+// restricted products.
+fn product_permission_condition(person_id_parameter: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM permission WHERE permission.person = {person_id_parameter}
+            AND permission.permission_item IN ('all', 'products')
+            AND permission.permission_name IN ('r', 'w', 'all'))
+        AND (product.product_restricted = 0
+            OR EXISTS (SELECT 1 FROM permission WHERE permission.person = {person_id_parameter}
+                AND permission.permission_item IN ('rproducts', 'all')
+                AND permission.permission_name != 'n'))"
+    )
+}
+
+// Build a FTS5 query from the user input: every word must match,
+// as a prefix. Words without letters or digits are ignored. Words are quoted so that CAS numbers and formulas
+// do not need to be escaped.
+fn build_fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Search the products by name, synonyms, CAS and CE numbers, formulas,
+// specificity, remark, tags and supplier references.
+// Results are ranked, the best first.
+pub fn search_products(
+    db_connection: &Connection,
+    query: &str,
+    person_id: u64,
+) -> Result<Vec<ProductSearchResult>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("query:{query:?}");
+    debug!("person_id:{person_id:?}");
+
+    let fts_query = build_fts_query(query);
+    if fts_query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let sql = format!(
+        "SELECT product.product_id, name.name_label, bm25(product_fts) AS rank,
+        snippet(product_fts, -1, '<b>', '</b>', '…', 10)
+        FROM product_fts
+        JOIN product ON product.product_id = product_fts.rowid
+        JOIN name ON product.name = name.name_id
+        WHERE product_fts MATCH ?1
+        AND {}
+        ORDER BY rank, product.product_id",
+        product_permission_condition("?2")
+    );
+
+    debug!("sql: {sql}");
+    debug!("fts_query: {fts_query}");
+
+    let results = db_connection
+        .prepare(&sql)?
+        .query_map((&fts_query, person_id), |row| {
+            Ok(ProductSearchResult {
+                product_id: row.get(0)?,
+                name_label: row.get(1)?,
+                rank: row.get(2)?,
+                snippet: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    debug!("results: {results:#?}");
+
+    Ok(results)
+}
+
+//
+// Element search.
+//
+
+// Products search by the elements of their empirical formula
+// and by molecular weight. All the conditions must match.
+#[derive(Debug, Default, Clone)]
+pub struct ElementFilter {
+    // Elements the formula must contain: ["Br"].
+    pub contains: Vec<String>,
+    // Elements the formula must not contain.
+    pub excludes: Vec<String>,
+    // Exact element counts: [("C", 6), ("N", 1)].
+    pub counts: Vec<(String, u32)>,
+    pub molecular_weight_min: Option<f64>,
+    pub molecular_weight_max: Option<f64>,
+}
+
+// Return the ids of the products matching the filter and visible to the person.
+// The element index is maintained when formulas are written, the search does not write.
+pub fn search_products_by_elements(
+    db_connection: &Connection,
+    filter: &ElementFilter,
+    person_id: u64,
+) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("filter:{filter:?}");
+    debug!("person_id:{person_id:?}");
+
+    let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(person_id)];
+    let mut conditions = vec![product_permission_condition("?1")];
+
+    let element_exists = |parameter: usize, count_parameter: Option<usize>| {
+        let count_condition = count_parameter
+            .map(|count_parameter| format!(" AND element_count = ?{count_parameter}"))
+            .unwrap_or_default();
+
+        format!(
+            "EXISTS (SELECT 1 FROM empirical_formula_element
+                WHERE empirical_formula_element.empirical_formula = product.empirical_formula
+                AND element = ?{parameter}{count_condition})"
+        )
+    };
+
+    for element in &filter.contains {
+        params.push(Box::new(element.clone()));
+        conditions.push(element_exists(params.len(), None));
+    }
+
+    for element in &filter.excludes {
+        params.push(Box::new(element.clone()));
+        conditions.push(format!("NOT {}", element_exists(params.len(), None)));
+    }
+
+    for (element, count) in &filter.counts {
+        params.push(Box::new(element.clone()));
+        params.push(Box::new(*count));
+        conditions.push(element_exists(params.len() - 1, Some(params.len())));
+    }
+
+    if !filter.contains.is_empty() || !filter.excludes.is_empty() || !filter.counts.is_empty() {
+        conditions.push("product.empirical_formula IS NOT NULL".to_string());
+    }
+
+    if let Some(molecular_weight_min) = filter.molecular_weight_min {
+        params.push(Box::new(molecular_weight_min));
+        conditions.push(format!(
+            "product.product_molecular_weight >= ?{}",
+            params.len()
+        ));
+    }
+
+    if let Some(molecular_weight_max) = filter.molecular_weight_max {
+        params.push(Box::new(molecular_weight_max));
+        conditions.push(format!(
+            "product.product_molecular_weight <= ?{}",
+            params.len()
+        ));
+    }
+
+    let sql = format!(
+        "SELECT product.product_id FROM product WHERE {} ORDER BY product.product_id",
+        conditions.join(" AND ")
+    );
+
+    debug!("sql: {sql}");
+
+    let product_ids = db_connection
+        .prepare(&sql)?
+        .query_map(params_from_iter(params.iter()), |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    debug!("product_ids: {product_ids:?}");
+
+    Ok(product_ids)
+}
+
+//
+// Duplicates.
+//
+
+// Why two products are likely duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum DuplicateReason {
+    // Same CAS number and same specificity.
+    CasNumberAndSpecificity,
+    InchiKey,
+    // A name or synonym in common and the same producer reference.
+    NameAndProducerRef,
+}
+
+impl DuplicateReason {
+    fn weight(self) -> f64 {
+        match self {
+            DuplicateReason::CasNumberAndSpecificity => 0.8,
+            DuplicateReason::InchiKey => 0.7,
+            DuplicateReason::NameAndProducerRef => 0.6,
+        }
+    }
+}
+
+// Similarity score between 0 and 1, higher when several reasons match.
+fn duplicate_score(reasons: &[DuplicateReason]) -> f64 {
+    1.0 - reasons
+        .iter()
+        .map(|reason| 1.0 - reason.weight())
+        .product::<f64>()
+}
+
+// Two products likely to be duplicates, product_id < duplicate_product_id.
+#[derive(Debug, Serialize)]
+pub struct DuplicateCandidate {
+    pub product_id: u64,
+    pub duplicate_product_id: u64,
+    pub reasons: Vec<DuplicateReason>,
+    pub score: f64,
+}
+
+// Products linked by duplicate candidate pairs, directly or through other products.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    // Sorted ids.
+    pub product_ids: Vec<u64>,
+    // Pairs linking the products of the group, the most similar first.
+    pub candidates: Vec<DuplicateCandidate>,
+    // Best score of the pairs.
+    pub score: f64,
+}
+
+// Return the root of the product in the union-find forest, compressing the path.
+fn find_group_root(parents: &mut BTreeMap<u64, u64>, product_id: u64) -> u64 {
+    let mut root = product_id;
+    while let Some(&parent) = parents.get(&root)
+        && parent != root
+    {
+        root = parent;
+    }
+
+    let mut current = product_id;
+    while current != root {
+        let next = parents.insert(current, root).unwrap_or(root);
+        current = next;
+    }
+
+    root
+}
+
+// An existing product matching a product about to be created or updated.
+#[derive(Debug, Serialize)]
+pub struct DuplicateMatch {
+    pub product_id: u64,
+    pub reasons: Vec<DuplicateReason>,
+    pub score: f64,
+}
+
+// Return the groups of products likely to be duplicates, the most similar first.
+// Pairs sharing a product are in the same group: if A is like B and B like C,
+// A, B and C are merged together.
+pub fn find_duplicate_candidates(
+    db_connection: &Connection,
+) -> Result<Vec<DuplicateGroup>, Box<dyn std::error::Error + Send + Sync>> {
+    let queries = [
+        (
+            DuplicateReason::CasNumberAndSpecificity,
+            "SELECT p1.product_id, p2.product_id FROM product p1
+            JOIN product p2 ON p1.cas_number = p2.cas_number AND p1.product_id < p2.product_id
+            WHERE normalize(COALESCE(p1.product_specificity, '')) = normalize(COALESCE(p2.product_specificity, ''))",
+        ),
+        (
+            DuplicateReason::InchiKey,
+            "SELECT p1.product_id, p2.product_id FROM product p1
+            JOIN product p2 ON p1.product_inchikey = p2.product_inchikey AND p1.product_id < p2.product_id
+            WHERE p1.product_inchikey != ''",
+        ),
+        (
+            DuplicateReason::NameAndProducerRef,
+            "WITH product_name (product_id, name_id) AS (
+                SELECT product_id, name FROM product
+                UNION SELECT productsynonyms_product_id, productsynonyms_name_id FROM productsynonyms
+            )
+            SELECT DISTINCT p1.product_id, p2.product_id FROM product p1
+            JOIN product p2 ON p1.producer_ref = p2.producer_ref AND p1.product_id < p2.product_id
+            JOIN product_name n1 ON n1.product_id = p1.product_id
+            JOIN product_name n2 ON n2.product_id = p2.product_id AND n1.name_id = n2.name_id",
+        ),
+    ];
+
+    let mut reasons_by_pair: BTreeMap<(u64, u64), Vec<DuplicateReason>> = BTreeMap::new();
+
+    for (reason, sql) in queries {
+        debug!("sql: {sql}");
+
+        let pairs = db_connection
+            .prepare(sql)?
+            .query_map([], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for pair in pairs {
+            reasons_by_pair.entry(pair).or_default().push(reason);
+        }
+    }
+
+    let mut candidates: Vec<DuplicateCandidate> = reasons_by_pair
+        .into_iter()
+        .map(
+            |((product_id, duplicate_product_id), reasons)| DuplicateCandidate {
+                product_id,
+                duplicate_product_id,
+                score: duplicate_score(&reasons),
+                reasons,
+            },
+        )
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    // Union-find on the product ids.
+    let mut parents: BTreeMap<u64, u64> = BTreeMap::new();
+    for candidate in &candidates {
+        let root = find_group_root(&mut parents, candidate.product_id);
+        let duplicate_root = find_group_root(&mut parents, candidate.duplicate_product_id);
+        parents.insert(root.max(duplicate_root), root.min(duplicate_root));
+    }
+
+    // Candidates are sorted, the first one of a group has its best score.
+    let mut groups_by_root: BTreeMap<u64, DuplicateGroup> = BTreeMap::new();
+    for candidate in candidates {
+        let root = find_group_root(&mut parents, candidate.product_id);
+        let group = groups_by_root
+            .entry(root)
+            .or_insert_with(|| DuplicateGroup {
+                product_ids: vec![],
+                candidates: vec![],
+                score: candidate.score,
+            });
+
+        group.product_ids.push(candidate.product_id);
+        group.product_ids.push(candidate.duplicate_product_id);
+        group.candidates.push(candidate);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = groups_by_root.into_values().collect();
+    for group in &mut groups {
+        group.product_ids.sort_unstable();
+        group.product_ids.dedup();
+    }
+    groups.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    debug!("groups: {groups:#?}");
+
+    Ok(groups)
+}
+
+// Return the existing products that are likely duplicates of the product,
+// the most similar first. Call it before create_update_product to warn the user.
+// Lookup values are compared by label as they may not be created yet.
+pub fn find_product_duplicates(
+    db_connection: &Connection,
+    product: &ProductStruct,
+) -> Result<Vec<DuplicateMatch>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("find_product_duplicates: {product:#?}");
+
+    let mut reasons_by_product: BTreeMap<u64, Vec<DuplicateReason>> = BTreeMap::new();
+
+    let mut add_matches = |reason: DuplicateReason,
+                           sql: &str,
+                           params: &[&dyn ToSql]|
+     -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        debug!("sql: {sql}");
+
+        let product_ids = db_connection
+            .prepare(sql)?
+            .query_map(params, |row| row.get::<_, u64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for product_id in product_ids {
+            reasons_by_product
+                .entry(product_id)
+                .or_default()
+                .push(reason);
+        }
+
+        Ok(())
+    };
+
+    if let Some(cas_number) = &product.cas_number {
+        add_matches(
+            DuplicateReason::CasNumberAndSpecificity,
+            "SELECT product.product_id FROM product
+            JOIN cas_number ON product.cas_number = cas_number.cas_number_id
+            WHERE cas_number.cas_number_label = ?1
+            AND normalize(COALESCE(product.product_specificity, '')) = normalize(?2)
+            AND product.product_id IS NOT ?3",
+            &[
+                &cas_number.cas_number_label,
+                &product.product_specificity.as_deref().unwrap_or_default(),
+                &product.product_id,
+            ],
+        )?;
+    }
+
+    if let Some(product_inchikey) = &product.product_inchikey
+        && !product_inchikey.is_empty()
+    {
+        add_matches(
+            DuplicateReason::InchiKey,
+            "SELECT product_id FROM product WHERE product_inchikey = ?1 AND product_id IS NOT ?2",
+            &[product_inchikey, &product.product_id],
+        )?;
+    }
+
+    if let Some(producer_ref) = &product.producer_ref {
+        let mut name_labels = vec![product.name.name_label.clone()];
+        if let Some(synonyms) = &product.synonyms {
+            name_labels.extend(synonyms.iter().map(|synonym| synonym.name_label.clone()));
+        }
+
+        add_matches(
+            DuplicateReason::NameAndProducerRef,
+            "SELECT product.product_id FROM product
+            JOIN producer_ref ON product.producer_ref = producer_ref.producer_ref_id
+            JOIN producer ON producer_ref.producer = producer.producer_id
+            WHERE producer_ref.producer_ref_label = ?1
+            AND producer.producer_label = ?2
+            AND product.product_id IS NOT ?3
+            AND EXISTS (SELECT 1 FROM name
+                WHERE normalize(name.name_label) IN (SELECT normalize(value) FROM json_each(?4))
+                AND (name.name_id = product.name
+                    OR name.name_id IN (SELECT productsynonyms_name_id FROM productsynonyms
+                        WHERE productsynonyms_product_id = product.product_id)))",
+            &[
+                &producer_ref.producer_ref_label,
+                &producer_ref.producer.producer_label,
+                &product.product_id,
+                &serde_json::to_string(&name_labels)?,
+            ],
+        )?;
+    }
+
+    let mut matches: Vec<DuplicateMatch> = reasons_by_product
+        .into_iter()
+        .map(|(product_id, reasons)| DuplicateMatch {
+            product_id,
+            score: duplicate_score(&reasons),
+            reasons,
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    debug!("matches: {matches:#?}");
+
+    Ok(matches)
+}
+
+//
+// Merge.
+//
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProductMergeError {
+    SameProduct(u64),
+    ProductNotFound(u64),
+}
+
+impl Display for ProductMergeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ProductMergeError::SameProduct(id) => {
+                write!(f, "can not merge product {id} into itself")
+            }
+            ProductMergeError::ProductNotFound(id) => write!(f, "product {id} not found"),
+        }
+    }
+}
+
+impl std::error::Error for ProductMergeError {}
+
+// A storage moved to the kept product.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct MovedStorage {
+    pub storage_id: u64,
+    pub old_barecode: Option<String>,
+    // Recomputed barecode, if asked.
+    pub new_barecode: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ProductMergeReport {
+    pub storages: Vec<MovedStorage>,
+    pub bookmarks: usize,
+}
+
+// Join tables of the product: (table, product column, related item column).
+static PRODUCT_JOIN_TABLES: &[(&str, &str, &str)] = &[
+    (
+        "productsynonyms",
+        "productsynonyms_product_id",
+        "productsynonyms_name_id",
+    ),
+    (
+        "producttags",
+        "producttags_product_id",
+        "producttags_tag_id",
+    ),
+    (
+        "productsymbols",
+        "productsymbols_product_id",
+        "productsymbols_symbol_id",
+    ),
+    (
+        "producthazardstatements",
+        "producthazardstatements_product_id",
+        "producthazardstatements_hazard_statement_id",
+    ),
+    (
+        "productprecautionarystatements",
+        "productprecautionarystatements_product_id",
+        "productprecautionarystatements_precautionary_statement_id",
+    ),
+    (
+        "productsupplierrefs",
+        "productsupplierrefs_product_id",
+        "productsupplierrefs_supplier_ref_id",
+    ),
+    (
+        "productclassesofcompounds",
+        "productclassesofcompounds_product_id",
+        "productclassesofcompounds_class_of_compound_id",
+    ),
+];
+
+// Return the name id and label and the specificity of a product.
+fn get_product_name_and_specificity(
+    db_transaction: &Transaction,
+    product_id: u64,
+) -> Result<Option<(u64, String, Option<String>)>, rusqlite::Error> {
+    db_transaction
+        .query_row(
+            "SELECT name.name_id, name.name_label, product.product_specificity FROM product
+            JOIN name ON product.name = name.name_id
+            WHERE product.product_id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+}
+
+// Merge the remove_id product into the keep_id product: storages, bookmarks
+// and relations are moved to keep_id, the name of remove_id becomes a synonym,
+// the merge is recorded and remove_id is deleted with its revisions.
+// The merges into remove_id are moved to keep_id.
+// Moved storages keep a history copy, and get a new barecode with recompute_barecodes.
+pub fn merge_products(
+    db_connection: &mut Connection,
+    keep_id: u64,
+    remove_id: u64,
+    person_id: u64,
+    recompute_barecodes: bool,
+) -> Result<ProductMergeReport, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("merge_products: {remove_id} -> {keep_id}");
+
+    if keep_id == remove_id {
+        return Err(Box::new(ProductMergeError::SameProduct(keep_id)));
+    }
+
+    let mut report = ProductMergeReport::default();
+
+    let db_transaction = db_connection.transaction()?;
+
+    let (keep_name_id, _, _) = get_product_name_and_specificity(&db_transaction, keep_id)?
+        .ok_or(ProductMergeError::ProductNotFound(keep_id))?;
+    let (remove_name_id, remove_name_label, remove_specificity) =
+        get_product_name_and_specificity(&db_transaction, remove_id)?
+            .ok_or(ProductMergeError::ProductNotFound(remove_id))?;
+
+    create_product_baseline_revision(&db_transaction, keep_id)?;
+
+    //
+    // storages
+    //
+    let storages = db_transaction
+        .prepare(
+            "SELECT storage_id, storage_barecode FROM storage
+            WHERE product = ?1 AND storage IS NULL
+            ORDER BY storage_id",
+        )?
+        .query_map([remove_id], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (storage_id, old_barecode) in storages {
+        let new_barecode = move_storage_to_product(
+            &db_transaction,
+            storage_id,
+            keep_id,
+            person_id,
+            recompute_barecodes,
+        )?;
+
+        report.storages.push(MovedStorage {
+            storage_id,
+            old_barecode,
+            new_barecode,
+        });
+    }
+
+    // History storages.
+    db_transaction.execute(
+        "UPDATE storage SET product = ?1 WHERE product = ?2",
+        [keep_id, remove_id],
+    )?;
+
+    //
+    // bookmarks
+    //
+    report.bookmarks = db_transaction.execute(
+        "UPDATE bookmark SET product = ?1 WHERE product = ?2
+        AND person NOT IN (SELECT person FROM bookmark WHERE product = ?1)",
+        [keep_id, remove_id],
+    )?;
+
+    //
+    // relations
+    //
+    // Rows already linked to the kept product are deleted with the removed one.
+    for (table, product_column, _) in PRODUCT_JOIN_TABLES {
+        let sql = format!(
+            "UPDATE OR IGNORE {table} SET {product_column} = ?1 WHERE {product_column} = ?2"
+        );
+        debug!("sql: {sql}");
+
+        db_transaction.execute(&sql, [keep_id, remove_id])?;
+    }
+
+    // The name of the removed product becomes a synonym.
+    db_transaction.execute(
+        "INSERT OR IGNORE INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (?1, ?2)",
+        [keep_id, remove_name_id],
+    )?;
+    db_transaction.execute(
+        "DELETE FROM productsynonyms WHERE productsynonyms_product_id = ?1 AND productsynonyms_name_id = ?2",
+        [keep_id, keep_name_id],
+    )?;
+
+    //
+    // history
+    //
+    db_transaction.execute(
+        "INSERT INTO product_merge (product_merge_removed_product_id, product_merge_removed_product_name,
+            product_merge_removed_product_specificity, product_merge_nb_storages, person, product)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            remove_id,
+            remove_name_label,
+            remove_specificity,
+            report.storages.len(),
+            person_id,
+            keep_id
+        ],
+    )?;
+
+    // Products previously merged into the removed one.
+    db_transaction.execute(
+        "UPDATE product_merge SET product = ?1 WHERE product = ?2",
+        [keep_id, remove_id],
+    )?;
+
+    // The revisions of the removed product describe an other product card,
+    // they can not be restored on the kept one and are deleted with it.
+    db_transaction.execute(
+        "DELETE FROM product_revision WHERE product = ?1",
+        [remove_id],
+    )?;
+
+    db_transaction.execute("DELETE FROM product WHERE product_id = ?1", [remove_id])?;
+
+    create_product_revision(&db_transaction, keep_id, Some(person_id))?;
+
+    db_transaction.commit()?;
+
+    debug!("report: {report:#?}");
+
+    Ok(report)
+}
+
+//
+// Clone.
+//
+
+// Columns identifying a product, not copied to a clone so that it is not
+// reported as a duplicate of the original. The InChI columns can be set
+// with the overrides.
+static CLONE_IDENTITY_COLUMNS: &[&str] = &["product_inchi", "product_inchikey", "product_qrcode"];
+
+// Fields replaced in a cloned product, None keeps the original value,
+// or leaves the identity columns empty.
+#[derive(Debug, Default, Clone)]
+pub struct ProductOverrides {
+    pub name_id: Option<u64>,
+    pub producer_ref_id: Option<u64>,
+    pub product_specificity: Option<String>,
+    pub product_concentration: Option<f64>,
+    pub product_temperature: Option<f64>,
+    pub product_msds: Option<String>,
+    pub product_sheet: Option<String>,
+    pub product_remark: Option<String>,
+    pub product_restricted: Option<bool>,
+    pub product_number_per_carton: Option<u64>,
+    pub product_number_per_bag: Option<u64>,
+    pub product_inchi: Option<String>,
+    pub product_inchikey: Option<String>,
+}
+
+fn boxed_value<T: ToSql + 'static>(maybe_value: Option<T>) -> Option<Box<dyn ToSql>> {
+    maybe_value.map(|value| Box::new(value) as Box<dyn ToSql>)
+}
+
+impl ProductOverrides {
+    // Product columns and their new values.
+    fn columns_values(&self) -> Vec<(&'static str, Box<dyn ToSql>)> {
+        [
+            ("name", boxed_value(self.name_id)),
+            ("producer_ref", boxed_value(self.producer_ref_id)),
+            (
+                "product_specificity",
+                boxed_value(self.product_specificity.clone()),
+            ),
+            (
+                "product_concentration",
+                boxed_value(self.product_concentration),
+            ),
+            ("product_temperature", boxed_value(self.product_temperature)),
+            ("product_msds", boxed_value(self.product_msds.clone())),
+            ("product_sheet", boxed_value(self.product_sheet.clone())),
+            ("product_remark", boxed_value(self.product_remark.clone())),
+            ("product_restricted", boxed_value(self.product_restricted)),
+            (
+                "product_number_per_carton",
+                boxed_value(self.product_number_per_carton),
+            ),
+            (
+                "product_number_per_bag",
+                boxed_value(self.product_number_per_bag),
+            ),
+            ("product_inchi", boxed_value(self.product_inchi.clone())),
+            (
+                "product_inchikey",
+                boxed_value(self.product_inchikey.clone()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(column, maybe_value)| maybe_value.map(|value| (column, value)))
+        .collect()
+    }
+}
+
+// Create a copy of a product with its synonyms, tags, symbols, hazard and
+// precautionary statements, supplier references and classes of compounds.
+// The copy belongs to person_id and the overrides are applied.
+// The identity columns (InChI, InChIKey, QR code) are not copied.
+// Return the id of the new product.
+pub fn clone_product(
+    db_connection: &mut Connection,
+    product_id: u64,
+    person_id: u64,
+    overrides: &ProductOverrides,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("clone_product: {product_id} {overrides:#?}");
+
+    let db_transaction = db_connection.transaction()?;
+
+    let product_exists: bool = db_transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM product WHERE product_id = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?;
+    if !product_exists {
+        return Err(format!("no product found for id {product_id}").into());
+    }
+
+    // Every column is copied, so that columns added by migrations are not forgotten.
+    let columns = db_transaction
+        .prepare("SELECT name FROM pragma_table_info('product') WHERE name != 'product_id'")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let overrides_columns_values = overrides.columns_values();
+
+    let mut values: Vec<&dyn ToSql> = vec![&product_id, &person_id];
+    let mut select_expressions = vec![];
+
+    for column in &columns {
+        if column == "person" {
+            select_expressions.push("?2".to_string());
+        } else if let Some((_, value)) = overrides_columns_values
+            .iter()
+            .find(|(override_column, _)| *override_column == column.as_str())
+        {
+            values.push(value.as_ref());
+            select_expressions.push(format!("?{}", values.len()));
+        } else if CLONE_IDENTITY_COLUMNS.contains(&column.as_str()) {
+            select_expressions.push("NULL".to_string());
+        } else {
+            select_expressions.push(column.clone());
+        }
+    }
+
+    let sql = format!(
+        "INSERT INTO product ({}) SELECT {} FROM product WHERE product_id = ?1",
+        columns.join(", "),
+        select_expressions.join(", ")
+    );
+    debug!("sql: {sql}");
+
+    db_transaction.execute(&sql, values.as_slice())?;
+
+    let new_product_id = u64::try_from(db_transaction.last_insert_rowid())?;
+
+    for (table, product_column, item_column) in PRODUCT_JOIN_TABLES {
+        let sql = format!(
+            "INSERT INTO {table} ({product_column}, {item_column})
+            SELECT ?1, {item_column} FROM {table} WHERE {product_column} = ?2"
+        );
+        debug!("sql: {sql}");
+
+        db_transaction.execute(&sql, [new_product_id, product_id])?;
+    }
+
+    // The new name must not be a synonym of the product.
+    db_transaction.execute(
+        "DELETE FROM productsynonyms WHERE productsynonyms_product_id = ?1
+        AND productsynonyms_name_id = (SELECT name FROM product WHERE product_id = ?1)",
+        [new_product_id],
+    )?;
+
+    create_product_revision(&db_transaction, new_product_id, Some(person_id))?;
+
+    db_transaction.commit()?;
+
+    debug!("new_product_id: {new_product_id}");
+
+    Ok(new_product_id)
+}
+
 pub fn delete_product(
     db_connection: &mut Connection,
     product_id: u64,
@@ -3000,3 +3994,7 @@ pub fn delete_product(
 
     Ok(())
 }
+
+#[cfg(test)]
+#[path = "product_tests.rs"]
+mod product_tests;
@@ -0,0 +1,547 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::molecularweight::MolecularWeightError;
+    use crate::product::*;
+    use chimitheque_types::{
+        casnumber::CasNumber as CasNumberStruct,
+        empiricalformula::EmpiricalFormula as EmpiricalFormulaStruct, name::Name as NameStruct,
+        person::Person as PersonStruct, producer::Producer as ProducerStruct,
+        producerref::ProducerRef as ProducerRefStruct, product::Product as ProductStruct,
+    };
+    use rusqlite::Connection;
+
+    fn init_test_product() -> Connection {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO person (person_id, person_email) VALUES
+                    (1, 'admin@chimitheque.fr'), (2, 'user@chimitheque.fr'), (3, 'nobody@chimitheque.fr');
+                INSERT INTO entity (entity_id, entity_name) VALUES (1, 'lab');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES
+                    (1, 'all', 'all', NULL), (2, 'r', 'products', 1);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ÉTHANOL'), (2, 'ALCOOL ÉTHYLIQUE'), (3, 'BENZÈNE');
+                INSERT INTO cas_number (cas_number_id, cas_number_label) VALUES (1, '64-17-5'), (2, '71-43-2');
+                INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES (1, 'C2H6O'), (2, 'C6H6');
+                INSERT INTO tag (tag_id, tag_label) VALUES (1, 'solvant');
+                INSERT INTO product (product_id, product_type, name, cas_number, empirical_formula, product_restricted) VALUES
+                    (1, 'chem', 1, 1, 1, 0), (2, 'chem', 3, 2, 2, 1);
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2);
+                INSERT INTO producttags (producttags_product_id, producttags_tag_id) VALUES (1, 1), (2, 1);",
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    fn search_product_ids(db_connection: &Connection, query: &str, person_id: u64) -> Vec<u64> {
+        search_products(db_connection, query, person_id)
+            .unwrap()
+            .iter()
+            .map(|result| result.product_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_products() {
+        let mut db_connection = init_test_product();
+
+        // Accents, prefixes, CAS numbers, synonyms and tags.
+        assert_eq!(search_product_ids(&db_connection, "ethanol", 1), vec![1]);
+        assert_eq!(search_product_ids(&db_connection, "benz", 1), vec![2]);
+        assert_eq!(search_product_ids(&db_connection, "64-17-5", 1), vec![1]);
+        assert_eq!(search_product_ids(&db_connection, "alcool", 1), vec![1]);
+        assert_eq!(search_product_ids(&db_connection, "C6H6", 1), vec![2]);
+        assert_eq!(search_product_ids(&db_connection, "solvant", 1).len(), 2);
+        assert_eq!(
+            search_product_ids(&db_connection, "solvant benz", 1),
+            vec![2]
+        );
+        assert!(search_product_ids(&db_connection, "  ", 1).is_empty());
+        assert!(search_product_ids(&db_connection, "\"", 1).is_empty());
+
+        let results = search_products(&db_connection, "ethyl", 1).unwrap();
+        assert_eq!(results[0].name_label, "ÉTHANOL");
+        assert!(results[0].snippet.contains("<b>"));
+
+        // Restricted products and permissions.
+        assert_eq!(search_product_ids(&db_connection, "solvant", 2), vec![1]);
+        assert!(search_product_ids(&db_connection, "solvant", 3).is_empty());
+
+        // The index follows the updates.
+        db_connection
+            .execute(
+                "UPDATE name SET name_label = 'ETHANOL ABSOLU' WHERE name_id = 1",
+                [],
+            )
+            .unwrap();
+        assert_eq!(search_product_ids(&db_connection, "absolu", 1), vec![1]);
+
+        db_connection
+            .execute(
+                "DELETE FROM producttags WHERE producttags_product_id = 1",
+                [],
+            )
+            .unwrap();
+        assert_eq!(search_product_ids(&db_connection, "solvant", 1), vec![2]);
+
+        delete_product(&mut db_connection, 1).unwrap();
+        assert!(search_product_ids(&db_connection, "ethanol", 1).is_empty());
+    }
+
+    #[test]
+    fn test_search_products_by_elements() {
+        let db_connection = init_test_product();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO empirical_formula (empirical_formula_id, empirical_formula_label) VALUES (3, 'C6H5Br'), (4, 'C6H7N');
+                INSERT INTO product (product_id, product_type, name, empirical_formula, product_molecular_weight) VALUES
+                    (3, 'chem', 1, 3, 157.01), (4, 'chem', 1, 4, 93.13);
+                UPDATE product SET product_molecular_weight = 46.07 WHERE product_id = 1;",
+            )
+            .unwrap();
+        crate::empiricalformula::update_empirical_formula_elements(&db_connection).unwrap();
+
+        let count_elements = || -> u64 {
+            db_connection
+                .query_row(
+                    "SELECT COUNT(*) FROM empirical_formula_element",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        let nb_elements = count_elements();
+
+        let search = |filter: ElementFilter, person_id: u64| -> Vec<u64> {
+            search_products_by_elements(&db_connection, &filter, person_id).unwrap()
+        };
+
+        assert_eq!(
+            search(
+                ElementFilter {
+                    contains: vec!["Br".to_string()],
+                    ..Default::default()
+                },
+                1
+            ),
+            vec![3]
+        );
+        assert_eq!(
+            search(
+                ElementFilter {
+                    counts: vec![("C".to_string(), 6)],
+                    ..Default::default()
+                },
+                1
+            ),
+            vec![2, 3, 4]
+        );
+        assert_eq!(
+            search(
+                ElementFilter {
+                    counts: vec![("C".to_string(), 6)],
+                    contains: vec!["N".to_string()],
+                    ..Default::default()
+                },
+                1
+            ),
+            vec![4]
+        );
+        assert_eq!(
+            search(
+                ElementFilter {
+                    contains: vec!["C".to_string()],
+                    excludes: vec!["Br".to_string(), "N".to_string()],
+                    ..Default::default()
+                },
+                1
+            ),
+            vec![1, 2]
+        );
+        assert_eq!(
+            search(
+                ElementFilter {
+                    molecular_weight_min: Some(50.0),
+                    molecular_weight_max: Some(200.0),
+                    ..Default::default()
+                },
+                1
+            ),
+            vec![3, 4]
+        );
+
+        // Restricted product 2 is hidden.
+        assert_eq!(
+            search(
+                ElementFilter {
+                    counts: vec![("H".to_string(), 6)],
+                    ..Default::default()
+                },
+                2
+            ),
+            vec![1]
+        );
+
+        // The search does not write.
+        assert_eq!(count_elements(), nb_elements);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let db_connection = init_test_product();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO producer (producer_id, producer_label) VALUES (1, 'acme');
+                INSERT INTO producer_ref (producer_ref_id, producer_ref_label, producer) VALUES (1, 'R1', 1);
+                UPDATE product SET producer_ref = 1, product_inchikey = 'LFQSCWFLJHTTHZ-UHFFFAOYSA-N' WHERE product_id = 1;
+                INSERT INTO product (product_id, product_type, name, cas_number, product_specificity, product_inchikey, producer_ref) VALUES
+                    (3, 'chem', 1, 1, NULL, 'LFQSCWFLJHTTHZ-UHFFFAOYSA-N', NULL),
+                    (4, 'chem', 1, 1, 'absolute', NULL, NULL),
+                    (5, 'chem', 2, NULL, NULL, NULL, 1),
+                    (6, 'chem', 3, 1, 'Absolute', NULL, NULL);",
+            )
+            .unwrap();
+
+        let groups = find_duplicate_candidates(&db_connection).unwrap();
+
+        // 3 and 5 are both like 1, they are in the same group.
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| group.product_ids.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![1, 3, 5], vec![4, 6]]
+        );
+        assert!((groups[0].score - 0.94).abs() < 1e-9);
+        assert!((groups[1].score - 0.8).abs() < 1e-9);
+
+        let candidates: Vec<(u64, u64, Vec<DuplicateReason>)> = groups[0]
+            .candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    candidate.product_id,
+                    candidate.duplicate_product_id,
+                    candidate.reasons.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            candidates,
+            vec![
+                (
+                    1,
+                    3,
+                    vec![
+                        DuplicateReason::CasNumberAndSpecificity,
+                        DuplicateReason::InchiKey
+                    ]
+                ),
+                (1, 5, vec![DuplicateReason::NameAndProducerRef]),
+            ]
+        );
+
+        // Check before creating a product.
+        let mut product = ProductStruct {
+            name: NameStruct {
+                name_label: "alcool ethylique".to_string(),
+                ..Default::default()
+            },
+            cas_number: Some(CasNumberStruct {
+                cas_number_label: "64-17-5".to_string(),
+                ..Default::default()
+            }),
+            producer_ref: Some(ProducerRefStruct {
+                producer_ref_label: "R1".to_string(),
+                producer: ProducerStruct {
+                    producer_label: "acme".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let matches = find_product_duplicates(&db_connection, &product).unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|duplicate_match| duplicate_match.product_id)
+                .collect::<Vec<_>>(),
+            vec![1, 3, 5]
+        );
+        assert_eq!(
+            matches[0].reasons,
+            vec![
+                DuplicateReason::CasNumberAndSpecificity,
+                DuplicateReason::NameAndProducerRef
+            ]
+        );
+        assert!(matches[0].score > matches[1].score);
+
+        // The product itself is not a duplicate.
+        product.product_id = Some(1);
+        product.product_specificity = Some("Absolute".to_string());
+        assert_eq!(
+            find_product_duplicates(&db_connection, &product)
+                .unwrap()
+                .iter()
+                .map(|duplicate_match| duplicate_match.product_id)
+                .collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+    }
+
+    #[test]
+    fn test_merge_products() {
+        let mut db_connection = init_test_product();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO store_location (store_location_id, store_location_name, store_location_can_store, store_location_full_path, entity) VALUES
+                    (1, 'cabinet [CAB]', 1, 'lab/cabinet [CAB]', 1);
+                INSERT INTO storage (storage_id, storage_barecode, product, store_location, storage) VALUES
+                    (1, 'CAB1.1', 1, 1, NULL), (2, 'CAB2.1', 2, 1, NULL), (3, 'CAB2.2', 2, 1, NULL), (4, 'CAB2.1', 2, 1, 2);
+                INSERT INTO bookmark (person, product) VALUES (1, 1), (1, 2), (2, 2);",
+            )
+            .unwrap();
+
+        assert_eq!(
+            merge_products(&mut db_connection, 1, 1, 1, false)
+                .unwrap_err()
+                .downcast_ref::<ProductMergeError>(),
+            Some(&ProductMergeError::SameProduct(1))
+        );
+        assert_eq!(
+            merge_products(&mut db_connection, 1, 9, 1, false)
+                .unwrap_err()
+                .downcast_ref::<ProductMergeError>(),
+            Some(&ProductMergeError::ProductNotFound(9))
+        );
+
+        let report = merge_products(&mut db_connection, 1, 2, 1, true).unwrap();
+
+        assert_eq!(
+            report.storages,
+            vec![
+                MovedStorage {
+                    storage_id: 2,
+                    old_barecode: Some("CAB2.1".to_string()),
+                    new_barecode: Some("CAB1.2".to_string()),
+                },
+                MovedStorage {
+                    storage_id: 3,
+                    old_barecode: Some("CAB2.2".to_string()),
+                    new_barecode: Some("CAB1.3".to_string()),
+                },
+            ]
+        );
+        assert_eq!(report.bookmarks, 1);
+
+        let count =
+            |sql: &str| -> u64 { db_connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+
+        assert_eq!(
+            count("SELECT COUNT(*) FROM product WHERE product_id = 2"),
+            0
+        );
+        // Storages, history included, and their history copies.
+        assert_eq!(count("SELECT COUNT(*) FROM storage WHERE product = 1"), 6);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM storage WHERE product = 1 AND storage IS NULL"),
+            3
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM bookmark WHERE product = 1"), 2);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM producttags WHERE producttags_product_id = 1"),
+            1
+        );
+        // The name of the removed product is a synonym.
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM productsynonyms WHERE productsynonyms_product_id = 1 AND productsynonyms_name_id IN (2, 3)"
+            ),
+            2
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM product_merge WHERE product = 1 AND product_merge_removed_product_id = 2
+                AND product_merge_removed_product_name = 'BENZÈNE' AND product_merge_nb_storages = 2"),
+            1
+        );
+    }
+
+    #[test]
+    fn test_merge_products_chain() {
+        let mut db_connection = init_test_product();
+
+        db_connection
+            .execute(
+                "INSERT INTO product (product_id, product_type, name) VALUES (3, 'chem', 1)",
+                [],
+            )
+            .unwrap();
+
+        // 2 into 1, then 1 into 3.
+        merge_products(&mut db_connection, 1, 2, 1, false).unwrap();
+        merge_products(&mut db_connection, 3, 1, 1, false).unwrap();
+
+        let merges: Vec<(u64, u64)> = db_connection
+            .prepare(
+                "SELECT product, product_merge_removed_product_id FROM product_merge ORDER BY product_merge_id",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(merges, vec![(3, 2), (3, 1)]);
+
+        // Only the revisions of the kept product remain.
+        let revision_products: Vec<u64> = db_connection
+            .prepare("SELECT DISTINCT product FROM product_revision")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(revision_products, vec![3]);
+    }
+
+    #[test]
+    fn test_clone_product() {
+        let mut db_connection = init_test_product();
+
+        assert!(clone_product(&mut db_connection, 9, 1, &ProductOverrides::default()).is_err());
+
+        let product_id = clone_product(
+            &mut db_connection,
+            1,
+            2,
+            &ProductOverrides {
+                product_specificity: Some("absolute".to_string()),
+                product_concentration: Some(99.8),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(product_id, 3);
+
+        let (name, cas_number, person, product_specificity): (u64, u64, u64, String) = db_connection
+            .query_row(
+                "SELECT name, cas_number, person, product_specificity FROM product WHERE product_id = ?1",
+                [product_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name, cas_number, person, product_specificity.as_str()),
+            (1, 1, 2, "absolute")
+        );
+
+        let count = |sql: &str| -> u64 {
+            db_connection
+                .query_row(sql, [product_id], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(
+            count("SELECT COUNT(*) FROM productsynonyms WHERE productsynonyms_product_id = ?1"),
+            1
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM producttags WHERE producttags_product_id = ?1"),
+            1
+        );
+
+        // The copy is indexed.
+        let mut product_ids = search_product_ids(&db_connection, "ethanol", 1);
+        product_ids.sort_unstable();
+        assert_eq!(product_ids, vec![1, 3]);
+
+        // The identity columns are not copied, unless overridden.
+        db_connection
+            .execute(
+                "UPDATE product SET product_inchi = 'InChI=1S/C2H6O/c1-2-3/h3H,2H2,1H3',
+                    product_inchikey = 'LFQSCWFLJHTTHZ-UHFFFAOYSA-N', product_qrcode = 'QR1'
+                WHERE product_id = 1",
+                [],
+            )
+            .unwrap();
+
+        let identity = |db_connection: &Connection, product_id: u64| {
+            db_connection
+                .query_row(
+                    "SELECT product_inchi, product_inchikey, product_qrcode FROM product WHERE product_id = ?1",
+                    [product_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .unwrap()
+        };
+
+        let product_id =
+            clone_product(&mut db_connection, 1, 1, &ProductOverrides::default()).unwrap();
+        let (inchi, inchikey, qrcode): (Option<String>, Option<String>, Option<String>) =
+            identity(&db_connection, product_id);
+        assert_eq!((inchi, inchikey, qrcode), (None, None, None));
+
+        let product_id = clone_product(
+            &mut db_connection,
+            1,
+            1,
+            &ProductOverrides {
+                product_inchikey: Some("IKHGUXGNUITLKF-UHFFFAOYSA-N".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (_, inchikey, qrcode): (Option<String>, Option<String>, Option<String>) =
+            identity(&db_connection, product_id);
+        assert_eq!(
+            (inchikey.as_deref(), qrcode),
+            (Some("IKHGUXGNUITLKF-UHFFFAOYSA-N"), None)
+        );
+    }
+
+    #[test]
+    fn test_create_update_product_molecular_weight() {
+        let mut db_connection = init_test_product();
+
+        let product = |molecular_weight: f64| ProductStruct {
+            name: NameStruct {
+                name_label: "propanol".to_string(),
+                ..Default::default()
+            },
+            empirical_formula: Some(EmpiricalFormulaStruct {
+                empirical_formula_label: "C3H8O".to_string(),
+                ..Default::default()
+            }),
+            product_molecular_weight: Some(molecular_weight),
+            person: PersonStruct {
+                person_id: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_product_molecular_weight(&product(46.07)),
+            Some(MolecularWeightError::Mismatch {
+                entered: 46.07,
+                computed: 60.1,
+            })
+        );
+        assert_eq!(check_product_molecular_weight(&product(60.09)), None);
+
+        // A mismatch does not prevent saving the product.
+        assert!(create_update_product(&mut db_connection, product(46.07)).is_ok());
+    }
+}
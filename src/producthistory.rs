@@ -0,0 +1,656 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use rusqlite::{
+    Connection, OptionalExtension, Row, Transaction, params, params_from_iter,
+    types::{Value as SqlValue, ValueRef},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProductHistoryError {
+    ProductNotFound(u64),
+    RevisionNotFound(u64),
+    // The two revision ids of a diff.
+    DifferentProducts(u64, u64),
+}
+
+impl Display for ProductHistoryError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ProductHistoryError::ProductNotFound(id) => write!(f, "product {id} not found"),
+            ProductHistoryError::RevisionNotFound(id) => {
+                write!(f, "product revision {id} not found")
+            }
+            ProductHistoryError::DifferentProducts(from_id, to_id) => {
+                write!(
+                    f,
+                    "product revisions {from_id} and {to_id} belong to different products"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProductHistoryError {}
+
+// A join table of the product and the table of its items.
+struct RevisionRelation {
+    table: &'static str,
+    product_column: &'static str,
+    item_column: &'static str,
+    item_table: &'static str,
+    item_id_column: &'static str,
+    item_label_column: &'static str,
+}
+
+static REVISION_RELATIONS: &[RevisionRelation] = &[
+    RevisionRelation {
+        table: "productsymbols",
+        product_column: "productsymbols_product_id",
+        item_column: "productsymbols_symbol_id",
+        item_table: "symbol",
+        item_id_column: "symbol_id",
+        item_label_column: "symbol_label",
+    },
+    RevisionRelation {
+        table: "producthazardstatements",
+        product_column: "producthazardstatements_product_id",
+        item_column: "producthazardstatements_hazard_statement_id",
+        item_table: "hazard_statement",
+        item_id_column: "hazard_statement_id",
+        item_label_column: "hazard_statement_reference",
+    },
+    RevisionRelation {
+        table: "productprecautionarystatements",
+        product_column: "productprecautionarystatements_product_id",
+        item_column: "productprecautionarystatements_precautionary_statement_id",
+        item_table: "precautionary_statement",
+        item_id_column: "precautionary_statement_id",
+        item_label_column: "precautionary_statement_reference",
+    },
+    RevisionRelation {
+        table: "productsynonyms",
+        product_column: "productsynonyms_product_id",
+        item_column: "productsynonyms_name_id",
+        item_table: "name",
+        item_id_column: "name_id",
+        item_label_column: "name_label",
+    },
+    RevisionRelation {
+        table: "producttags",
+        product_column: "producttags_product_id",
+        item_column: "producttags_tag_id",
+        item_table: "tag",
+        item_id_column: "tag_id",
+        item_label_column: "tag_label",
+    },
+    RevisionRelation {
+        table: "productsupplierrefs",
+        product_column: "productsupplierrefs_product_id",
+        item_column: "productsupplierrefs_supplier_ref_id",
+        item_table: "supplier_ref",
+        item_id_column: "supplier_ref_id",
+        item_label_column: "supplier_ref_label",
+    },
+    RevisionRelation {
+        table: "productclassesofcompounds",
+        product_column: "productclassesofcompounds_product_id",
+        item_column: "productclassesofcompounds_class_of_compound_id",
+        item_table: "class_of_compound",
+        item_id_column: "class_of_compound_id",
+        item_label_column: "class_of_compound_label",
+    },
+];
+
+// A product column referencing an item of an other table.
+struct RevisionReference {
+    column: &'static str,
+    item_table: &'static str,
+    item_id_column: &'static str,
+    item_label_column: &'static str,
+}
+
+static REVISION_REFERENCES: &[RevisionReference] = &[
+    RevisionReference {
+        column: "cas_number",
+        item_table: "cas_number",
+        item_id_column: "cas_number_id",
+        item_label_column: "cas_number_label",
+    },
+    RevisionReference {
+        column: "category",
+        item_table: "category",
+        item_id_column: "category_id",
+        item_label_column: "category_label",
+    },
+    RevisionReference {
+        column: "ce_number",
+        item_table: "ce_number",
+        item_id_column: "ce_number_id",
+        item_label_column: "ce_number_label",
+    },
+    RevisionReference {
+        column: "empirical_formula",
+        item_table: "empirical_formula",
+        item_id_column: "empirical_formula_id",
+        item_label_column: "empirical_formula_label",
+    },
+    RevisionReference {
+        column: "linear_formula",
+        item_table: "linear_formula",
+        item_id_column: "linear_formula_id",
+        item_label_column: "linear_formula_label",
+    },
+    RevisionReference {
+        column: "name",
+        item_table: "name",
+        item_id_column: "name_id",
+        item_label_column: "name_label",
+    },
+    RevisionReference {
+        column: "person",
+        item_table: "person",
+        item_id_column: "person_id",
+        item_label_column: "person_email",
+    },
+    RevisionReference {
+        column: "physical_state",
+        item_table: "physical_state",
+        item_id_column: "physical_state_id",
+        item_label_column: "physical_state_label",
+    },
+    RevisionReference {
+        column: "producer_ref",
+        item_table: "producer_ref",
+        item_id_column: "producer_ref_id",
+        item_label_column: "producer_ref_label",
+    },
+    RevisionReference {
+        column: "signal_word",
+        item_table: "signal_word",
+        item_id_column: "signal_word_id",
+        item_label_column: "signal_word_label",
+    },
+    RevisionReference {
+        column: "unit_molecular_weight",
+        item_table: "unit",
+        item_id_column: "unit_id",
+        item_label_column: "unit_label",
+    },
+    RevisionReference {
+        column: "unit_temperature",
+        item_table: "unit",
+        item_id_column: "unit_id",
+        item_label_column: "unit_label",
+    },
+];
+
+fn get_revision_reference(column: &str) -> Option<&'static RevisionReference> {
+    REVISION_REFERENCES
+        .iter()
+        .find(|reference| reference.column == column)
+}
+
+// State of a product card at a revision.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProductSnapshot {
+    // Product columns, product_id excepted.
+    pub fields: BTreeMap<String, Value>,
+    // Item ids by join table.
+    pub relations: BTreeMap<String, Vec<u64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductRevision {
+    pub product_revision_id: u64,
+    pub product_id: u64,
+    pub product_revision_date: Option<DateTime<Utc>>,
+    // None for the revisions recorded before the first tracked change.
+    pub person_id: Option<u64>,
+    pub person_email: Option<String>,
+    pub snapshot: ProductSnapshot,
+}
+
+fn product_revision_from_row(row: &Row) -> Result<ProductRevision, rusqlite::Error> {
+    let snapshot: String = row.get("product_revision_snapshot")?;
+
+    Ok(ProductRevision {
+        product_revision_id: row.get("product_revision_id")?,
+        product_id: row.get("product")?,
+        product_revision_date: DateTime::from_timestamp(row.get("product_revision_date")?, 0),
+        person_id: row.get("person")?,
+        person_email: row.get("product_revision_person_email")?,
+        snapshot: serde_json::from_str(&snapshot).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(err))
+        })?,
+    })
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old_value: Value,
+    pub new_value: Value,
+    // Labels of the referenced items for the reference fields (name, cas_number...),
+    // None for the other fields or if the item has been deleted since.
+    pub old_label: Option<String>,
+    pub new_label: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RelationItem {
+    pub id: u64,
+    // None if the item has been deleted since.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RelationChange {
+    pub relation: String,
+    pub added: Vec<RelationItem>,
+    pub removed: Vec<RelationItem>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ProductRevisionDiff {
+    pub fields: Vec<FieldChange>,
+    pub relations: Vec<RelationChange>,
+}
+
+// An item of a revision deleted since, by a merge or a purge.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct MissingItem {
+    // Product column or join table.
+    pub field: String,
+    pub id: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ProductRestoreReport {
+    // Revision recording the restoration.
+    pub product_revision_id: u64,
+    // Items not restored. A missing field item leaves the current value.
+    pub missing_items: Vec<MissingItem>,
+}
+
+fn sql_to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Integer(integer) => Value::from(integer),
+        ValueRef::Real(real) => Value::from(real),
+        ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+    }
+}
+
+fn json_to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(boolean) => SqlValue::Integer(i64::from(*boolean)),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => SqlValue::Integer(integer),
+            None => number.as_f64().map_or(SqlValue::Null, SqlValue::Real),
+        },
+        Value::String(string) => SqlValue::Text(string.clone()),
+        Value::Array(_) | Value::Object(_) => SqlValue::Text(value.to_string()),
+    }
+}
+
+fn get_product_snapshot(
+    db_connection: &Connection,
+    product_id: u64,
+) -> Result<Option<ProductSnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut stmt = db_connection.prepare("SELECT * FROM product WHERE product_id = ?1")?;
+    let column_names: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .map(|column_name| (*column_name).to_string())
+        .collect();
+
+    let maybe_fields = stmt
+        .query_row([product_id], |row| {
+            let mut fields = BTreeMap::new();
+            for (index, column_name) in column_names.iter().enumerate() {
+                if column_name != "product_id" {
+                    fields.insert(column_name.clone(), sql_to_json(row.get_ref(index)?));
+                }
+            }
+            Ok(fields)
+        })
+        .optional()?;
+
+    let Some(fields) = maybe_fields else {
+        return Ok(None);
+    };
+
+    let mut relations = BTreeMap::new();
+
+    for relation in REVISION_RELATIONS {
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} = ?1 ORDER BY 1",
+            relation.item_column, relation.table, relation.product_column
+        );
+        debug!("sql: {sql}");
+
+        let item_ids = db_connection
+            .prepare(&sql)?
+            .query_map([product_id], |row| row.get::<_, u64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        relations.insert(relation.table.to_string(), item_ids);
+    }
+
+    Ok(Some(ProductSnapshot { fields, relations }))
+}
+
+// Record the current state of a product changed by person_id.
+// Return the revision id.
+pub(crate) fn create_product_revision(
+    db_transaction: &Transaction,
+    product_id: u64,
+    person_id: Option<u64>,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let snapshot = get_product_snapshot(db_transaction, product_id)?
+        .ok_or(ProductHistoryError::ProductNotFound(product_id))?;
+
+    db_transaction.execute(
+        "INSERT INTO product_revision (product_revision_person_email, product_revision_snapshot, person, product)
+        VALUES ((SELECT person_email FROM person WHERE person_id = ?1), ?2, ?1, ?3)",
+        params![person_id, serde_json::to_string(&snapshot)?, product_id],
+    )?;
+
+    Ok(u64::try_from(db_transaction.last_insert_rowid())?)
+}
+
+// Record the current state of a product changed before the history existed,
+// so that its first tracked change can be diffed.
+pub(crate) fn create_product_baseline_revision(
+    db_transaction: &Transaction,
+    product_id: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let needs_baseline: bool = db_transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM product WHERE product_id = ?1)
+        AND NOT EXISTS (SELECT 1 FROM product_revision WHERE product = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?;
+
+    if needs_baseline {
+        create_product_revision(db_transaction, product_id, None)?;
+    }
+
+    Ok(())
+}
+
+// Return the revisions of a product, the most recent first.
+pub fn get_product_revisions(
+    db_connection: &Connection,
+    product_id: u64,
+) -> Result<Vec<ProductRevision>, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("product_id: {product_id}");
+
+    let revisions = db_connection
+        .prepare(
+            "SELECT * FROM product_revision WHERE product = ?1
+            ORDER BY product_revision_id DESC",
+        )?
+        .query_map([product_id], product_revision_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(revisions)
+}
+
+fn get_product_revision(
+    db_connection: &Connection,
+    product_revision_id: u64,
+) -> Result<ProductRevision, Box<dyn std::error::Error + Send + Sync>> {
+    db_connection
+        .query_row(
+            "SELECT * FROM product_revision WHERE product_revision_id = ?1",
+            [product_revision_id],
+            product_revision_from_row,
+        )
+        .optional()?
+        .ok_or_else(|| ProductHistoryError::RevisionNotFound(product_revision_id).into())
+}
+
+// Return the label of the item of a reference field value,
+// None if the value is not an id or the item does not exist.
+fn get_reference_label(
+    db_connection: &Connection,
+    reference: &RevisionReference,
+    value: &Value,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(item_id) = value.as_u64() else {
+        return Ok(None);
+    };
+
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} = ?1",
+        reference.item_label_column, reference.item_table, reference.item_id_column
+    );
+
+    Ok(db_connection
+        .query_row(&sql, [item_id], |row| row.get(0))
+        .optional()?)
+}
+
+fn get_relation_items(
+    db_connection: &Connection,
+    relation: &RevisionRelation,
+    item_ids: &BTreeSet<u64>,
+) -> Result<Vec<RelationItem>, Box<dyn std::error::Error + Send + Sync>> {
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} = ?1",
+        relation.item_label_column, relation.item_table, relation.item_id_column
+    );
+
+    let mut stmt = db_connection.prepare(&sql)?;
+    let mut items = Vec::with_capacity(item_ids.len());
+
+    for id in item_ids {
+        items.push(RelationItem {
+            id: *id,
+            label: stmt.query_row([id], |row| row.get(0)).optional()?,
+        });
+    }
+
+    Ok(items)
+}
+
+// Return the changes from a revision to another one of the same product.
+// Revisions of two different products are refused.
+pub fn diff_product_revisions(
+    db_connection: &Connection,
+    from_revision_id: u64,
+    to_revision_id: u64,
+) -> Result<ProductRevisionDiff, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("from_revision_id: {from_revision_id} to_revision_id: {to_revision_id}");
+
+    let from_revision = get_product_revision(db_connection, from_revision_id)?;
+    let to_revision = get_product_revision(db_connection, to_revision_id)?;
+
+    if from_revision.product_id != to_revision.product_id {
+        return Err(Box::new(ProductHistoryError::DifferentProducts(
+            from_revision_id,
+            to_revision_id,
+        )));
+    }
+
+    let from = from_revision.snapshot;
+    let to = to_revision.snapshot;
+
+    let mut diff = ProductRevisionDiff::default();
+
+    let fields: BTreeSet<&String> = from.fields.keys().chain(to.fields.keys()).collect();
+    for field in fields {
+        let old_value = from.fields.get(field).cloned().unwrap_or_default();
+        let new_value = to.fields.get(field).cloned().unwrap_or_default();
+
+        if old_value != new_value {
+            let (old_label, new_label) = match get_revision_reference(field) {
+                Some(reference) => (
+                    get_reference_label(db_connection, reference, &old_value)?,
+                    get_reference_label(db_connection, reference, &new_value)?,
+                ),
+                None => (None, None),
+            };
+
+            diff.fields.push(FieldChange {
+                field: field.clone(),
+                old_value,
+                new_value,
+                old_label,
+                new_label,
+            });
+        }
+    }
+
+    for relation in REVISION_RELATIONS {
+        let item_ids = |snapshot: &ProductSnapshot| -> BTreeSet<u64> {
+            snapshot
+                .relations
+                .get(relation.table)
+                .map(|ids| ids.iter().copied().collect())
+                .unwrap_or_default()
+        };
+        let from_ids = item_ids(&from);
+        let to_ids = item_ids(&to);
+
+        if from_ids == to_ids {
+            continue;
+        }
+
+        diff.relations.push(RelationChange {
+            relation: relation.table.to_string(),
+            added: get_relation_items(
+                db_connection,
+                relation,
+                &to_ids.difference(&from_ids).copied().collect(),
+            )?,
+            removed: get_relation_items(
+                db_connection,
+                relation,
+                &from_ids.difference(&to_ids).copied().collect(),
+            )?,
+        });
+    }
+
+    debug!("diff: {diff:#?}");
+
+    Ok(diff)
+}
+
+// Restore a product to the state of a revision. The restoration is itself
+// recorded as a new revision by person_id.
+// Columns dropped since the revision are ignored. Items deleted since the
+// revision (merged or purged names, CAS numbers, tags...) are not restored
+// and are reported, a reference field keeping its current value.
+pub fn restore_product_revision(
+    db_connection: &mut Connection,
+    product_revision_id: u64,
+    person_id: u64,
+) -> Result<ProductRestoreReport, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("product_revision_id: {product_revision_id}");
+
+    let db_transaction = db_connection.transaction()?;
+
+    let revision = get_product_revision(&db_transaction, product_revision_id)?;
+    let product_id = revision.product_id;
+
+    let columns = db_transaction
+        .prepare("SELECT name FROM pragma_table_info('product') WHERE name != 'product_id'")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<BTreeSet<_>, _>>()?;
+
+    let mut missing_items = vec![];
+    let mut assignments = vec![];
+    let mut values = vec![];
+    for (field, value) in &revision.snapshot.fields {
+        if !columns.contains(field) {
+            continue;
+        }
+
+        if let Some(reference) = get_revision_reference(field)
+            && let Some(item_id) = value.as_u64()
+            && get_reference_label(&db_transaction, reference, value)?.is_none()
+        {
+            debug!("missing {field} {item_id}");
+            missing_items.push(MissingItem {
+                field: field.clone(),
+                id: item_id,
+            });
+            continue;
+        }
+
+        values.push(json_to_sql(value));
+        assignments.push(format!("{field} = ?{}", values.len()));
+    }
+
+    if !assignments.is_empty() {
+        values.push(SqlValue::from(i64::try_from(product_id)?));
+
+        let sql = format!(
+            "UPDATE product SET {} WHERE product_id = ?{}",
+            assignments.join(", "),
+            values.len()
+        );
+        debug!("sql: {sql}");
+
+        db_transaction.execute(&sql, params_from_iter(values.iter()))?;
+    }
+
+    for relation in REVISION_RELATIONS {
+        let RevisionRelation {
+            table,
+            product_column,
+            item_column,
+            item_table,
+            item_id_column,
+            ..
+        } = relation;
+
+        db_transaction.execute(
+            &format!("DELETE FROM {table} WHERE {product_column} = ?1"),
+            [product_id],
+        )?;
+
+        let insert_sql = format!(
+            "INSERT INTO {table} ({product_column}, {item_column})
+            SELECT ?1, {item_id_column} FROM {item_table} WHERE {item_id_column} = ?2"
+        );
+        debug!("sql: {insert_sql}");
+
+        for item_id in revision
+            .snapshot
+            .relations
+            .get(*table)
+            .into_iter()
+            .flatten()
+        {
+            if db_transaction.execute(&insert_sql, [product_id, *item_id])? == 0 {
+                debug!("missing {table} {item_id}");
+                missing_items.push(MissingItem {
+                    field: (*table).to_string(),
+                    id: *item_id,
+                });
+            }
+        }
+    }
+
+    let product_revision_id =
+        create_product_revision(&db_transaction, product_id, Some(person_id))?;
+
+    db_transaction.commit()?;
+
+    Ok(ProductRestoreReport {
+        product_revision_id,
+        missing_items,
+    })
+}
+
+#[cfg(test)]
+#[path = "producthistory_tests.rs"]
+mod producthistory_tests;
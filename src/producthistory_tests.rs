@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::producthistory::*;
+    use rusqlite::Connection;
+    use serde_json::Value;
+
+    fn init_test_producthistory() -> Connection {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO person (person_id, person_email) VALUES (1, 'admin@chimitheque.fr'), (2, 'user@chimitheque.fr');
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ALCOOL ETHYLIQUE');
+                INSERT INTO hazard_statement (hazard_statement_id, hazard_statement_label, hazard_statement_reference) VALUES
+                    (1, 'Highly flammable liquid and vapour', 'H225'), (2, 'Causes serious eye irritation', 'H319');
+                INSERT INTO product (product_id, product_type, name, product_specificity) VALUES (1, 'chem', 1, 'absolute');
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2);
+                INSERT INTO producthazardstatements (producthazardstatements_product_id, producthazardstatements_hazard_statement_id) VALUES
+                    (1, 1), (1, 2);",
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    fn create_revision(db_connection: &mut Connection, person_id: Option<u64>) -> u64 {
+        let db_transaction = db_connection.transaction().unwrap();
+        let revision_id = create_product_revision(&db_transaction, 1, person_id).unwrap();
+        db_transaction.commit().unwrap();
+
+        revision_id
+    }
+
+    #[test]
+    fn test_product_revisions() {
+        let mut db_connection = init_test_producthistory();
+
+        // Baseline, then a change by person 2.
+        let db_transaction = db_connection.transaction().unwrap();
+        create_product_baseline_revision(&db_transaction, 1).unwrap();
+        create_product_baseline_revision(&db_transaction, 1).unwrap();
+        db_transaction.commit().unwrap();
+
+        db_connection
+            .execute_batch(
+                "UPDATE product SET product_specificity = '96%' WHERE product_id = 1;
+                DELETE FROM producthazardstatements WHERE producthazardstatements_hazard_statement_id = 1;",
+            )
+            .unwrap();
+        let revision_id = create_revision(&mut db_connection, Some(2));
+
+        let revisions = get_product_revisions(&db_connection, 1).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].product_revision_id, revision_id);
+        assert_eq!(
+            revisions[0].person_email,
+            Some("user@chimitheque.fr".to_string())
+        );
+        assert_eq!(revisions[1].person_id, None);
+        assert_eq!(
+            revisions[1].snapshot.relations["producthazardstatements"],
+            vec![1, 2]
+        );
+
+        // Who removed the hazard statement.
+        let diff = diff_product_revisions(
+            &db_connection,
+            revisions[1].product_revision_id,
+            revision_id,
+        )
+        .unwrap();
+        assert_eq!(
+            diff.fields,
+            vec![FieldChange {
+                field: "product_specificity".to_string(),
+                old_value: Value::from("absolute"),
+                new_value: Value::from("96%"),
+                old_label: None,
+                new_label: None,
+            }]
+        );
+        assert_eq!(
+            diff.relations,
+            vec![RelationChange {
+                relation: "producthazardstatements".to_string(),
+                added: vec![],
+                removed: vec![RelationItem {
+                    id: 1,
+                    label: Some("H225".to_string())
+                }],
+            }]
+        );
+
+        // Restore.
+        let report =
+            restore_product_revision(&mut db_connection, revisions[1].product_revision_id, 1)
+                .unwrap();
+        assert!(report.missing_items.is_empty());
+        let restored_revision_id = report.product_revision_id;
+
+        let product_specificity: String = db_connection
+            .query_row(
+                "SELECT product_specificity FROM product WHERE product_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(product_specificity, "absolute");
+
+        let diff = diff_product_revisions(
+            &db_connection,
+            revisions[1].product_revision_id,
+            restored_revision_id,
+        )
+        .unwrap();
+        assert_eq!(diff, ProductRevisionDiff::default());
+        assert_eq!(get_product_revisions(&db_connection, 1).unwrap().len(), 3);
+
+        assert_eq!(
+            diff_product_revisions(&db_connection, 1, 99)
+                .unwrap_err()
+                .downcast_ref::<ProductHistoryError>(),
+            Some(&ProductHistoryError::RevisionNotFound(99))
+        );
+    }
+
+    #[test]
+    fn test_diff_product_revisions_different_products() {
+        let mut db_connection = init_test_producthistory();
+
+        db_connection
+            .execute(
+                "INSERT INTO product (product_id, product_type, name) VALUES (2, 'chem', 2)",
+                [],
+            )
+            .unwrap();
+
+        let db_transaction = db_connection.transaction().unwrap();
+        let first_revision_id = create_product_revision(&db_transaction, 1, Some(1)).unwrap();
+        let second_revision_id = create_product_revision(&db_transaction, 2, Some(1)).unwrap();
+        db_transaction.commit().unwrap();
+
+        assert_eq!(
+            diff_product_revisions(&db_connection, first_revision_id, second_revision_id)
+                .unwrap_err()
+                .downcast_ref::<ProductHistoryError>(),
+            Some(&ProductHistoryError::DifferentProducts(
+                first_revision_id,
+                second_revision_id
+            ))
+        );
+    }
+
+    #[test]
+    fn test_restore_product_revision_deleted_items() {
+        let mut db_connection = init_test_producthistory();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (3, 'ETHYL ALCOHOL');
+                INSERT INTO cas_number (cas_number_id, cas_number_label) VALUES (1, '64-17-5');
+                UPDATE product SET cas_number = 1 WHERE product_id = 1;",
+            )
+            .unwrap();
+        let first_revision_id = create_revision(&mut db_connection, Some(1));
+
+        // The name and the synonym are merged into an other one, the CAS number is removed.
+        db_connection
+            .execute_batch(
+                "UPDATE product SET name = 3, cas_number = NULL WHERE product_id = 1;
+                DELETE FROM productsynonyms;
+                DELETE FROM name WHERE name_id IN (1, 2);",
+            )
+            .unwrap();
+        let second_revision_id = create_revision(&mut db_connection, Some(1));
+
+        // Reference fields are diffed with their labels.
+        let diff =
+            diff_product_revisions(&db_connection, first_revision_id, second_revision_id).unwrap();
+        assert_eq!(
+            diff.fields,
+            vec![
+                FieldChange {
+                    field: "cas_number".to_string(),
+                    old_value: Value::from(1),
+                    new_value: Value::Null,
+                    old_label: Some("64-17-5".to_string()),
+                    new_label: None,
+                },
+                FieldChange {
+                    field: "name".to_string(),
+                    old_value: Value::from(1),
+                    new_value: Value::from(3),
+                    old_label: None,
+                    new_label: Some("ETHYL ALCOHOL".to_string()),
+                },
+            ]
+        );
+
+        // The deleted items are reported instead of failing on the foreign keys.
+        let report = restore_product_revision(&mut db_connection, first_revision_id, 1).unwrap();
+        assert_eq!(
+            report.missing_items,
+            vec![
+                MissingItem {
+                    field: "name".to_string(),
+                    id: 1
+                },
+                MissingItem {
+                    field: "productsynonyms".to_string(),
+                    id: 2
+                },
+            ]
+        );
+
+        let (name, cas_number): (u64, Option<u64>) = db_connection
+            .query_row(
+                "SELECT name, cas_number FROM product WHERE product_id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((name, cas_number), (3, Some(1)));
+    }
+}
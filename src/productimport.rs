@@ -0,0 +1,582 @@
+use crate::{
+    empiricalformula::to_hill_formula,
+    hazardstatement, precautionarystatement,
+    product::create_update_product_in_transaction,
+    searchable::{create_update, parse},
+};
+use chimitheque_traits::searchable::Searchable;
+use chimitheque_types::{
+    casnumber::CasNumber as CasNumberStruct, category::Category as CategoryStruct,
+    cenumber::CeNumber as CeNumberStruct,
+    empiricalformula::EmpiricalFormula as EmpiricalFormulaStruct,
+    hazardstatement::HazardStatement as HazardStatementStruct, name::Name as NameStruct,
+    person::Person as PersonStruct,
+    precautionarystatement::PrecautionaryStatement as PrecautionaryStatementStruct,
+    product::Product as ProductStruct, supplier::Supplier as SupplierStruct,
+    supplierref::SupplierRef as SupplierRefStruct, symbol::Symbol as SymbolStruct,
+    tag::Tag as TagStruct,
+};
+use chimitheque_utils::{casnumber::is_cas_number, cenumber::is_ce_number};
+use csv::{ReaderBuilder, StringRecord};
+use log::{debug, info, warn};
+use rusqlite::{Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use std::{
+    fmt::{Display, Formatter},
+    io::Read,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProductImportError {
+    MissingColumn(String),
+    EmptyName,
+    InvalidCasnumber(String),
+    InvalidCenumber(String),
+    InvalidEmpiricalformula(String),
+    UnknownSymbol(String),
+    UnknownHazardstatement(String),
+    UnknownPrecautionarystatement(String),
+    InvalidSupplierref(String),
+}
+
+impl Display for ProductImportError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ProductImportError::MissingColumn(s) => write!(f, "missing column {s}"),
+            ProductImportError::EmptyName => write!(f, "empty name"),
+            ProductImportError::InvalidCasnumber(s) => write!(f, "invalid cas number {s}"),
+            ProductImportError::InvalidCenumber(s) => write!(f, "invalid ce number {s}"),
+            ProductImportError::InvalidEmpiricalformula(s) => {
+                write!(f, "invalid empirical formula {s}")
+            }
+            ProductImportError::UnknownSymbol(s) => write!(f, "unknown symbol {s}"),
+            ProductImportError::UnknownHazardstatement(s) => {
+                write!(f, "unknown hazard statement {s}")
+            }
+            ProductImportError::UnknownPrecautionarystatement(s) => {
+                write!(f, "unknown precautionary statement {s}")
+            }
+            ProductImportError::InvalidSupplierref(s) => {
+                write!(
+                    f,
+                    "invalid supplier reference {s}, expected supplier:reference"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProductImportError {}
+
+// Header of the CSV column of each product field, None to ignore the field.
+// The defaults match the headers written by product::export_products.
+#[derive(Debug, Clone)]
+pub struct ProductImportMapping {
+    pub name: String,
+    pub specificity: Option<String>,
+    pub cas_number: Option<String>,
+    pub ce_number: Option<String>,
+    pub empirical_formula: Option<String>,
+    pub symbols: Option<String>,
+    pub hazard_statements: Option<String>,
+    pub precautionary_statements: Option<String>,
+    pub tags: Option<String>,
+    pub category: Option<String>,
+    pub supplier_refs: Option<String>,
+    // CSV field delimiter.
+    pub delimiter: u8,
+    // Separator of the values of the list columns.
+    // Symbols and statements may also be separated by spaces.
+    pub list_separator: char,
+}
+
+impl Default for ProductImportMapping {
+    fn default() -> Self {
+        Self {
+            name: "NAME".to_string(),
+            specificity: Some("PRODUCT_SPECIFICITY".to_string()),
+            cas_number: Some("CAS_NUMBER".to_string()),
+            ce_number: Some("CE_NUMBER".to_string()),
+            empirical_formula: Some("EMPIRICAL_FORMULA".to_string()),
+            symbols: Some("SYMBOLS".to_string()),
+            hazard_statements: Some("HAZARD_STATEMENTS".to_string()),
+            precautionary_statements: Some("PRECAUTIONARY_STATEMENTS".to_string()),
+            tags: Some("TAGS".to_string()),
+            category: Some("CATEGORY".to_string()),
+            supplier_refs: Some("SUPPLIER_REFS".to_string()),
+            delimiter: b',',
+            list_separator: ';',
+        }
+    }
+}
+
+// Positions of the mapped columns in the CSV header.
+#[derive(Debug, Default)]
+struct ProductImportColumns {
+    name: usize,
+    specificity: Option<usize>,
+    cas_number: Option<usize>,
+    ce_number: Option<usize>,
+    empirical_formula: Option<usize>,
+    symbols: Option<usize>,
+    hazard_statements: Option<usize>,
+    precautionary_statements: Option<usize>,
+    tags: Option<usize>,
+    category: Option<usize>,
+    supplier_refs: Option<usize>,
+}
+
+impl ProductImportColumns {
+    fn new(
+        mapping: &ProductImportMapping,
+        headers: &StringRecord,
+    ) -> Result<Self, ProductImportError> {
+        let position = |header: &str| header_position(headers, header);
+        let optional_position =
+            |maybe_header: &Option<String>| maybe_header.as_deref().and_then(position);
+
+        let name = position(&mapping.name)
+            .ok_or_else(|| ProductImportError::MissingColumn(mapping.name.clone()))?;
+
+        Ok(Self {
+            name,
+            specificity: optional_position(&mapping.specificity),
+            cas_number: optional_position(&mapping.cas_number),
+            ce_number: optional_position(&mapping.ce_number),
+            empirical_formula: optional_position(&mapping.empirical_formula),
+            symbols: optional_position(&mapping.symbols),
+            hazard_statements: optional_position(&mapping.hazard_statements),
+            precautionary_statements: optional_position(&mapping.precautionary_statements),
+            tags: optional_position(&mapping.tags),
+            category: optional_position(&mapping.category),
+            supplier_refs: optional_position(&mapping.supplier_refs),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductImportLineError {
+    // Line number in the CSV file, the header being line 1.
+    pub line: u64,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ProductImportReport {
+    // Number of lines without errors.
+    pub valid: usize,
+    // Ids of the created products, empty on dry run.
+    pub created: Vec<u64>,
+    pub errors: Vec<ProductImportLineError>,
+    pub dry_run: bool,
+}
+
+// Return the position of a column in the CSV header, ignoring case.
+pub(crate) fn header_position(headers: &StringRecord, header: &str) -> Option<usize> {
+    headers
+        .iter()
+        .position(|field| field.trim().eq_ignore_ascii_case(header))
+}
+
+// Return the trimmed, non empty, value of a column.
+pub(crate) fn field(record: &StringRecord, maybe_column: Option<usize>) -> Option<&str> {
+    maybe_column
+        .and_then(|column| record.get(column))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+// Split a list column on the separators.
+fn split_list<'a>(value: &'a str, separators: &[char]) -> Vec<&'a str> {
+    value
+        .split(|c: char| separators.contains(&c))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Return the id of the item with the given label, if it exists.
+fn parse_id(
+    item: &(impl Searchable + std::fmt::Debug + Default + Serialize),
+    db_connection: &Connection,
+    s: &str,
+) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(parse(item, db_connection, s)?.and_then(|item| item.get_id()))
+}
+
+// Build the product of a CSV line, looking up the existing lookup values.
+// Lookup values not found keep a None id and are created by
+// create_update_product, except the symbols and statements that must exist.
+// Return the product with every error of the line.
+#[allow(clippy::too_many_lines)]
+fn product_from_record(
+    db_connection: &Connection,
+    record: &StringRecord,
+    columns: &ProductImportColumns,
+    list_separator: char,
+    person_id: u64,
+) -> Result<(ProductStruct, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut errors: Vec<String> = Vec::new();
+
+    let code_separators = [list_separator, ' ', ',', ';'];
+
+    let mut product = ProductStruct {
+        person: PersonStruct {
+            person_id: Some(person_id),
+            ..Default::default()
+        },
+        product_specificity: field(record, columns.specificity).map(ToString::to_string),
+        ..Default::default()
+    };
+
+    // Name.
+    if let Some(name_label) = field(record, Some(columns.name)) {
+        product.name = NameStruct {
+            name_id: parse_id(&NameStruct::default(), db_connection, name_label)?,
+            name_label: name_label.to_string(),
+            ..Default::default()
+        };
+    } else {
+        errors.push(ProductImportError::EmptyName.to_string());
+    }
+
+    // Cas number.
+    if let Some(cas_number_label) = field(record, columns.cas_number) {
+        if let Err(err) = is_cas_number(cas_number_label) {
+            errors.push(
+                ProductImportError::InvalidCasnumber(format!("{cas_number_label}: {err}"))
+                    .to_string(),
+            );
+        } else {
+            product.cas_number = Some(CasNumberStruct {
+                cas_number_id: parse_id(
+                    &CasNumberStruct::default(),
+                    db_connection,
+                    cas_number_label,
+                )?,
+                cas_number_label: cas_number_label.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Ce number.
+    if let Some(ce_number_label) = field(record, columns.ce_number) {
+        if let Err(err) = is_ce_number(ce_number_label) {
+            errors.push(
+                ProductImportError::InvalidCenumber(format!("{ce_number_label}: {err}"))
+                    .to_string(),
+            );
+        } else {
+            product.ce_number = Some(CeNumberStruct {
+                ce_number_id: parse_id(&CeNumberStruct::default(), db_connection, ce_number_label)?,
+                ce_number_label: ce_number_label.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Empirical formula.
+    if let Some(empirical_formula_text) = field(record, columns.empirical_formula) {
+        match to_hill_formula(empirical_formula_text) {
+            Ok(empirical_formula_label) => {
+                product.empirical_formula = Some(EmpiricalFormulaStruct {
+                    empirical_formula_id: parse_id(
+                        &EmpiricalFormulaStruct::default(),
+                        db_connection,
+                        &empirical_formula_label,
+                    )?,
+                    empirical_formula_label,
+                    ..Default::default()
+                });
+            }
+            Err(err) => errors.push(
+                ProductImportError::InvalidEmpiricalformula(format!(
+                    "{empirical_formula_text}: {err}"
+                ))
+                .to_string(),
+            ),
+        }
+    }
+
+    // Symbols.
+    if let Some(symbols_text) = field(record, columns.symbols) {
+        let mut symbols: Vec<SymbolStruct> = Vec::new();
+
+        for symbol_label in split_list(symbols_text, &code_separators) {
+            match parse_id(&SymbolStruct::default(), db_connection, symbol_label)? {
+                Some(symbol_id) => symbols.push(SymbolStruct {
+                    symbol_id: Some(symbol_id),
+                    symbol_label: symbol_label.to_string(),
+                    ..Default::default()
+                }),
+                None => errors
+                    .push(ProductImportError::UnknownSymbol(symbol_label.to_string()).to_string()),
+            }
+        }
+
+        product.symbols = Some(symbols);
+    }
+
+    // Hazard statements.
+    if let Some(hazard_statements_text) = field(record, columns.hazard_statements) {
+        let mut hazard_statements: Vec<HazardStatementStruct> = Vec::new();
+
+        for reference in split_list(hazard_statements_text, &code_separators) {
+            match hazardstatement::parse(db_connection, reference)? {
+                Some(hazard_statement) => hazard_statements.push(hazard_statement),
+                None => errors.push(
+                    ProductImportError::UnknownHazardstatement(reference.to_string()).to_string(),
+                ),
+            }
+        }
+
+        product.hazard_statements = Some(hazard_statements);
+    }
+
+    // Precautionary statements.
+    if let Some(precautionary_statements_text) = field(record, columns.precautionary_statements) {
+        let mut precautionary_statements: Vec<PrecautionaryStatementStruct> = Vec::new();
+
+        for reference in split_list(precautionary_statements_text, &code_separators) {
+            match precautionarystatement::parse(db_connection, reference)? {
+                Some(precautionary_statement) => {
+                    precautionary_statements.push(precautionary_statement);
+                }
+                None => errors.push(
+                    ProductImportError::UnknownPrecautionarystatement(reference.to_string())
+                        .to_string(),
+                ),
+            }
+        }
+
+        product.precautionary_statements = Some(precautionary_statements);
+    }
+
+    // Tags.
+    if let Some(tags_text) = field(record, columns.tags) {
+        let mut tags: Vec<TagStruct> = Vec::new();
+
+        for tag_label in split_list(tags_text, &[list_separator]) {
+            tags.push(TagStruct {
+                tag_id: parse_id(&TagStruct::default(), db_connection, tag_label)?,
+                tag_label: tag_label.to_string(),
+                ..Default::default()
+            });
+        }
+
+        product.tags = Some(tags);
+    }
+
+    // Category.
+    if let Some(category_label) = field(record, columns.category) {
+        product.category = Some(CategoryStruct {
+            category_id: parse_id(&CategoryStruct::default(), db_connection, category_label)?,
+            category_label: category_label.to_string(),
+            ..Default::default()
+        });
+    }
+
+    // Supplier references, as supplier:reference.
+    if let Some(supplier_refs_text) = field(record, columns.supplier_refs) {
+        let mut supplier_refs: Vec<SupplierRefStruct> = Vec::new();
+
+        for supplier_ref_text in split_list(supplier_refs_text, &[list_separator]) {
+            let Some((supplier_label, supplier_ref_label)) = supplier_ref_text
+                .split_once(':')
+                .map(|(supplier, reference)| (supplier.trim(), reference.trim()))
+                .filter(|(supplier, reference)| !supplier.is_empty() && !reference.is_empty())
+            else {
+                errors.push(
+                    ProductImportError::InvalidSupplierref(supplier_ref_text.to_string())
+                        .to_string(),
+                );
+                continue;
+            };
+
+            let supplier_id = parse_id(&SupplierStruct::default(), db_connection, supplier_label)?;
+
+            let supplier_ref_id: Option<u64> = match supplier_id {
+                Some(supplier_id) => db_connection
+                    .query_row(
+                        "SELECT supplier_ref_id FROM supplier_ref WHERE supplier_ref_label = ?1 AND supplier = ?2",
+                        (supplier_ref_label, supplier_id),
+                        |row| row.get(0),
+                    )
+                    .optional()?,
+                None => None,
+            };
+
+            supplier_refs.push(SupplierRefStruct {
+                supplier_ref_id,
+                supplier_ref_label: supplier_ref_label.to_string(),
+                supplier: SupplierStruct {
+                    supplier_id,
+                    supplier_label: supplier_label.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        }
+
+        product.supplier_refs = Some(supplier_refs);
+    }
+
+    Ok((product, errors))
+}
+
+// Create the missing suppliers of the supplier references, that
+// create_update_product does not create.
+fn create_product_suppliers(
+    db_connection: &Connection,
+    product: &mut ProductStruct,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(supplier_refs) = product.supplier_refs.as_mut() {
+        for supplier_ref in supplier_refs {
+            if supplier_ref.supplier.supplier_id.is_none() {
+                let supplier_label = supplier_ref.supplier.supplier_label.clone();
+
+                supplier_ref.supplier.supplier_id = Some(
+                    match parse_id(&SupplierStruct::default(), db_connection, &supplier_label)? {
+                        Some(supplier_id) => supplier_id,
+                        None => create_update(
+                            &SupplierStruct::default(),
+                            None,
+                            db_connection,
+                            &supplier_label,
+                        )?,
+                    },
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Run f in a savepoint of the transaction, so that a line failing to be
+// written leaves nothing behind.
+pub(crate) fn in_savepoint<T>(
+    db_transaction: &Transaction,
+    f: impl FnOnce() -> Result<T, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+    db_transaction.execute_batch("SAVEPOINT import_line")?;
+
+    match f() {
+        Ok(value) => {
+            db_transaction.execute_batch("RELEASE import_line")?;
+            Ok(value)
+        }
+        Err(err) => {
+            db_transaction.execute_batch("ROLLBACK TO import_line; RELEASE import_line")?;
+            Err(err)
+        }
+    }
+}
+
+// Import the products of a CSV file with a header line.
+// Every line is checked and its errors reported, invalid lines are skipped.
+// The valid lines are created with their missing lookup values (names, tags,
+// categories...), on dry run the whole import is then rolled back.
+pub fn import_products(
+    db_connection: &mut Connection,
+    reader: impl Read,
+    mapping: &ProductImportMapping,
+    person_id: u64,
+    dry_run: bool,
+) -> Result<ProductImportReport, Box<dyn std::error::Error + Send + Sync>> {
+    info!("importing products, dry run: {dry_run}");
+
+    let mut report = ProductImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .delimiter(mapping.delimiter)
+        .from_reader(reader);
+
+    let columns = ProductImportColumns::new(mapping, csv_reader.headers()?)?;
+
+    debug!("columns: {columns:?}");
+
+    // The lines are written even on dry run, so that the errors raised when
+    // writing them are reported, and the transaction is rolled back.
+    let db_transaction = db_connection.transaction()?;
+
+    for mayerr_record in csv_reader.records() {
+        let record = match mayerr_record {
+            Ok(record) => record,
+            Err(err) => {
+                report.errors.push(ProductImportLineError {
+                    line: err.position().map_or(0, csv::Position::line),
+                    error: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, csv::Position::line);
+
+        let (mut product, errors) = product_from_record(
+            &db_transaction,
+            &record,
+            &columns,
+            mapping.list_separator,
+            person_id,
+        )?;
+
+        if !errors.is_empty() {
+            for error in errors {
+                warn!("line {line}: {error}");
+                report.errors.push(ProductImportLineError { line, error });
+            }
+            continue;
+        }
+
+        report.valid += 1;
+
+        let mayerr_product_id = in_savepoint(&db_transaction, || {
+            create_product_suppliers(&db_transaction, &mut product)?;
+            create_update_product_in_transaction(&db_transaction, product)
+        });
+
+        match mayerr_product_id {
+            Ok(product_id) => {
+                if !dry_run {
+                    report.created.push(product_id);
+                }
+            }
+            Err(err) => {
+                warn!("line {line}: {err}");
+
+                report.valid -= 1;
+                report.errors.push(ProductImportLineError {
+                    line,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    if dry_run {
+        db_transaction.rollback()?;
+    } else {
+        db_transaction.commit()?;
+    }
+
+    info!(
+        "valid: {} created: {} errors: {}",
+        report.valid,
+        report.created.len(),
+        report.errors.len()
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "productimport_tests.rs"]
+mod productimport_tests;
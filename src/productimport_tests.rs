@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::{init::populate_db_with_base_data, productimport::*, test_utils::count};
+
+    fn init_test_productimport() -> Connection {
+        let mut db_connection = crate::test_utils::init_test();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        db_connection
+    }
+
+    const CSV: &str =
+        "NAME,CAS_NUMBER,EMPIRICAL_FORMULA,SYMBOLS,HAZARD_STATEMENTS,TAGS,CATEGORY,SUPPLIER_REFS
+ethanol,64-17-5,C2H6O,GHS02 GHS07,H225,solvent;organic,Drug,Abcam:E1234;New supplier:NS-1
+,64-17-5,,,,,,
+acetone,64-17-6,,GHS99,H999,,,Abcam
+";
+
+    #[test]
+    fn test_import_products_dry_run() {
+        let mut db_connection = init_test_productimport();
+
+        let report = import_products(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &ProductImportMapping::default(),
+            1,
+            true,
+        )
+        .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.valid, 1);
+        assert!(report.created.is_empty());
+
+        let errors: Vec<(u64, &str)> = report
+            .errors
+            .iter()
+            .map(|error| (error.line, error.error.as_str()))
+            .collect();
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[0], (3, "empty name"));
+        assert_eq!(errors[1].0, 4);
+        assert!(errors[1].1.starts_with("invalid cas number 64-17-6"));
+        assert_eq!(errors[2], (4, "unknown symbol GHS99"));
+        assert_eq!(errors[3], (4, "unknown hazard statement H999"));
+        assert_eq!(
+            errors[4],
+            (
+                4,
+                "invalid supplier reference Abcam, expected supplier:reference"
+            )
+        );
+
+        // Nothing written.
+        assert_eq!(count(&db_connection, "SELECT COUNT(*) FROM product"), 0);
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM tag WHERE tag_label = 'solvent'"
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_import_products_write_errors() {
+        let mut db_connection = init_test_productimport();
+
+        // The unknown person is only detected when writing the product.
+        let report = import_products(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &ProductImportMapping::default(),
+            42,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(report.valid, 0);
+        assert!(report.created.is_empty());
+        assert_eq!(report.errors.len(), 6);
+        assert_eq!(report.errors[0].line, 2);
+        assert!(
+            report.errors[0]
+                .error
+                .contains("FOREIGN KEY constraint failed")
+        );
+
+        // The failing line leaves nothing behind, even when not on dry run.
+        let report = import_products(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &ProductImportMapping::default(),
+            42,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(report.valid, 0);
+        assert!(report.created.is_empty());
+        assert_eq!(count(&db_connection, "SELECT COUNT(*) FROM product"), 0);
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM supplier WHERE supplier_label = 'New supplier'"
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_import_products() {
+        let mut db_connection = init_test_productimport();
+
+        let report = import_products(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &ProductImportMapping::default(),
+            1,
+            false,
+        )
+        .unwrap();
+
+        assert!(!report.dry_run);
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(report.errors.len(), 5);
+
+        let product_id = report.created[0];
+
+        assert_eq!(count(&db_connection, "SELECT COUNT(*) FROM product"), 1);
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM product
+                    JOIN cas_number ON product.cas_number = cas_number.cas_number_id
+                    JOIN category ON product.category = category.category_id
+                    WHERE product_id = {product_id} AND cas_number_label = '64-17-5' AND category_label = 'Drug'"
+                )
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM productsymbols WHERE productsymbols_product_id = {product_id}"
+                )
+            ),
+            2
+        );
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM producthazardstatements WHERE producthazardstatements_product_id = {product_id}"
+                )
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM producttags WHERE producttags_product_id = {product_id}"
+                )
+            ),
+            2
+        );
+
+        // The missing supplier is created.
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM productsupplierrefs
+                    JOIN supplier_ref ON productsupplierrefs.productsupplierrefs_supplier_ref_id = supplier_ref.supplier_ref_id
+                    JOIN supplier ON supplier_ref.supplier = supplier.supplier_id
+                    WHERE productsupplierrefs_product_id = {product_id}"
+                )
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_import_products_empirical_formula() {
+        let mut db_connection = init_test_productimport();
+
+        let report = import_products(
+            &mut db_connection,
+            "NAME,EMPIRICAL_FORMULA\nethanol,CH3CH2OH\nbenzyl,C6H5Ph\n".as_bytes(),
+            &ProductImportMapping::default(),
+            1,
+            false,
+        )
+        .unwrap();
+
+        // The formula is stored in Hill notation.
+        assert_eq!(report.created.len(), 1);
+        assert_eq!(
+            count(
+                &db_connection,
+                &format!(
+                    "SELECT COUNT(*) FROM product
+                    JOIN empirical_formula ON product.empirical_formula = empirical_formula.empirical_formula_id
+                    WHERE product_id = {} AND empirical_formula_label = 'C2H6O'",
+                    report.created[0]
+                )
+            ),
+            1
+        );
+
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].line, 3);
+        assert_eq!(
+            report.errors[0].error,
+            "invalid empirical formula C6H5Ph: unknown element Ph at position 4"
+        );
+    }
+
+    #[test]
+    fn test_import_products_mapping() {
+        let mut db_connection = init_test_productimport();
+
+        let mapping = ProductImportMapping {
+            name: "Nom".to_string(),
+            cas_number: Some("CAS".to_string()),
+            delimiter: b';',
+            ..Default::default()
+        };
+
+        let report = import_products(
+            &mut db_connection,
+            "nom;cas\nmethanol;67-56-1\n".as_bytes(),
+            &mapping,
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.created.len(), 1);
+        assert!(report.errors.is_empty());
+
+        // Missing name column.
+        assert_eq!(
+            import_products(
+                &mut db_connection,
+                "name;cas\nmethanol;67-56-1\n".as_bytes(),
+                &mapping,
+                1,
+                false,
+            )
+            .unwrap_err()
+            .downcast_ref::<ProductImportError>(),
+            Some(&ProductImportError::MissingColumn("Nom".to_string()))
+        );
+    }
+}
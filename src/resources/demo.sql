@@ -1,5 +1,3 @@
-BEGIN TRANSACTION;
-
 DELETE FROM product;
 
 -- =========================
@@ -19,7 +17,7 @@ INSERT INTO category VALUES
 -- CAS NUMBERS
 -- =========================
 DELETE FROM cas_number;
-INSERT INTO cas_number VALUES
+INSERT INTO cas_number (cas_number_id, cas_number_label, cas_number_cmr) VALUES
 (1,'64-17-5',NULL),
 (2,'67-56-1',NULL),
 (3,'67-64-1',NULL),
@@ -172,4 +170,3 @@ INSERT INTO "store_location" VALUES (3,'Tiroir1','',1,'[P]Placard1/Tiroir1',1,2)
 INSERT INTO "store_location" VALUES (4,'Tiroir2','',1,'[P]Placard1/Tiroir2',1,2);
 INSERT INTO "store_location" VALUES (5,'Frigo','',1,'Frigo',1,NULL);
 
-COMMIT;
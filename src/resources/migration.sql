@@ -1,6 +1,6 @@
-ATTACH DATABASE './storage.db' as 'Y';
-
-PRAGMA foreign_keys=off;
+-- Legacy (Go) Chimithèque database import.
+-- Run by init::import_legacy_database with the legacy database attached as "legacy".
+-- Persons, producers and suppliers are imported from Rust to handle duplicates.
 
 INSERT INTO bookmark (
 	bookmark_id,
@@ -10,7 +10,7 @@ INSERT INTO bookmark (
 SELECT bookmark_id,
 	person,
 	product
-FROM Y.bookmark;
+FROM legacy.bookmark;
 
 INSERT INTO borrowing (
 	borrowing_id,
@@ -24,7 +24,7 @@ SELECT borrowing_id,
 	person,
 	borrower,
 	storage
-FROM Y.borrowing;
+FROM legacy.borrowing;
 
 INSERT INTO cas_number (
 	cas_number_id,
@@ -34,7 +34,7 @@ INSERT INTO cas_number (
 SELECT casnumber_id,
 	casnumber_label,
 	casnumber_cmr
-FROM Y.casnumber;
+FROM legacy.casnumber;
 
 INSERT INTO ce_number (
 	ce_number_id,
@@ -42,7 +42,7 @@ INSERT INTO ce_number (
 )
 SELECT cenumber_id,
 	cenumber_label
-FROM Y.cenumber;
+FROM legacy.cenumber;
 
 INSERT INTO category (
 	category_id,
@@ -50,7 +50,7 @@ INSERT INTO category (
 )
 SELECT category_id,
 	category_label
-FROM Y.category;
+FROM legacy.category;
 
 INSERT INTO class_of_compound (
 	class_of_compound_id,
@@ -58,7 +58,7 @@ INSERT INTO class_of_compound (
 )
 SELECT classofcompound_id,
 	classofcompound_label
-FROM Y.classofcompound;
+FROM legacy.classofcompound;
 
 INSERT INTO empirical_formula (
 	empirical_formula_id,
@@ -66,7 +66,7 @@ INSERT INTO empirical_formula (
 )
 SELECT empiricalformula_id,
 	empiricalformula_label
-FROM Y.empiricalformula;
+FROM legacy.empiricalformula;
 
 INSERT INTO linear_formula (
 	linear_formula_id,
@@ -74,7 +74,7 @@ INSERT INTO linear_formula (
 )
 SELECT linearformula_id,
 	linearformula_label
-FROM Y.linearformula;
+FROM legacy.linearformula;
 
 INSERT INTO entity (
 	entity_id,
@@ -84,7 +84,7 @@ INSERT INTO entity (
 SELECT entity_id,
 	entity_name,
 	entity_description
-FROM Y.entity;
+FROM legacy.entity;
 
 INSERT INTO name (
 	name_id,
@@ -92,14 +92,14 @@ INSERT INTO name (
 )
 SELECT name_id,
 	name_label
-FROM Y.name;
+FROM legacy.name;
 
 INSERT into entitypeople (
 	entitypeople_entity_id,
 	entitypeople_person_id
 )
 SELECT entitypeople_entity_id, entitypeople_person_id
-FROM Y.entitypeople;
+FROM legacy.entitypeople;
 
 INSERT INTO hazard_statement (
 	hazard_statement_id,
@@ -111,7 +111,7 @@ SELECT hazardstatement_id,
 	hazardstatement_label,
 	hazardstatement_reference,
 	hazardstatement_cmr
-FROM Y.hazardstatement;
+FROM legacy.hazardstatement;
 
 INSERT INTO permission (
 	person,
@@ -123,40 +123,19 @@ SELECT person,
 	permission_perm_name,
 	permission_item_name,
 	permission_entity_id
-FROM Y.permission;
+FROM legacy.permission;
 
 DELETE FROM permission WHERE permission_item = 'people';
 UPDATE permission
 SET permission_entity = NULL
 WHERE permission_entity == -1;
 
--- Step 1: Try to insert normally
-INSERT OR IGNORE INTO person (person_id, person_email)
-SELECT person_id, LOWER(person_email)
-FROM Y.person;
-
--- Step 2: Insert conflicting rows with modified email
-INSERT INTO person (person_id, person_email)
-SELECT
-    person_id,
-    LOWER(person_email) || '_' || person_id
-FROM Y.person
-WHERE LOWER(person_email) IN (
-    SELECT LOWER(person_email)
-    FROM Y.person
-    GROUP BY LOWER(person_email)
-    HAVING COUNT(*) > 1
-)
-AND person_id NOT IN (
-    SELECT person_id FROM person
-);
-
 INSERT INTO personentities (
 	personentities_person_id,
 	personentities_entity_id
 )
 SELECT personentities_person_id, personentities_entity_id
-FROM Y.personentities;
+FROM legacy.personentities;
 
 INSERT into physical_state (
 	physical_state_id,
@@ -164,7 +143,7 @@ INSERT into physical_state (
 )
 SELECT physicalstate_id,
 	physicalstate_label
-FROM Y.physicalstate;
+FROM legacy.physicalstate;
 
 INSERT INTO precautionary_statement (
 	precautionary_statement_id,
@@ -174,28 +153,7 @@ INSERT INTO precautionary_statement (
 SELECT precautionarystatement_id,
 	precautionarystatement_label,
 	precautionarystatement_reference
-FROM Y.precautionarystatement;
-
--- Step 1: Try to insert normally
-INSERT OR IGNORE INTO producer (producer_id, producer_label)
-SELECT producer_id, producer_label
-FROM Y.producer;
-
--- Step 2: Insert conflicting rows with modified label
-INSERT INTO producer (producer_id, producer_label)
-SELECT
-    producer_id,
-    producer_label || '_' || producer_id
-FROM Y.producer
-WHERE producer_label IN (
-    SELECT producer_label
-    FROM Y.producer
-    GROUP BY producer_label
-    HAVING COUNT(*) > 1
-)
-AND producer_id NOT IN (
-    SELECT producer_id FROM producer
-);
+FROM legacy.precautionarystatement;
 
 INSERT INTO producer_ref (
 	producer_ref_id,
@@ -205,7 +163,7 @@ INSERT INTO producer_ref (
 SELECT producerref_id,
 	producerref_label,
 	producer
-FROM Y.producerref;
+FROM legacy.producerref;
 
 INSERT into product (
 	product_id,
@@ -263,7 +221,7 @@ SELECT product_id,
 	category,
 	product_number_per_carton,
 	product_number_per_bag
-FROM Y.product;
+FROM legacy.product;
 
 UPDATE product SET product_type = 'cons' WHERE (product_number_per_carton IS NOT NULL AND product_number_per_carton != 0);
 UPDATE product SET product_type = 'bio' WHERE (producer_ref IS NOT NULL AND (product_number_per_carton IS NULL OR product_number_per_carton == 0));
@@ -275,7 +233,7 @@ INSERT INTO productclassesofcompounds (
 )
 SELECT productclassofcompound_product_id,
 productclassofcompound_classofcompound_id
-FROM Y.productclassofcompound;
+FROM legacy.productclassofcompound;
 
 INSERT INTO producthazardstatements (
 	producthazardstatements_product_id,
@@ -283,7 +241,7 @@ INSERT INTO producthazardstatements (
 )
 SELECT producthazardstatements_product_id,
 producthazardstatements_hazardstatement_id
-FROM Y.producthazardstatements;
+FROM legacy.producthazardstatements;
 
 INSERT INTO productprecautionarystatements (
 	productprecautionarystatements_product_id,
@@ -291,7 +249,7 @@ INSERT INTO productprecautionarystatements (
 )
 SELECT productprecautionarystatements_product_id,
 productprecautionarystatements_precautionarystatement_id
-FROM Y.productprecautionarystatements;
+FROM legacy.productprecautionarystatements;
 
 INSERT INTO productsupplierrefs (
 	productsupplierrefs_product_id,
@@ -299,7 +257,7 @@ INSERT INTO productsupplierrefs (
 )
 SELECT productsupplierrefs_product_id,
 productsupplierrefs_supplierref_id
-FROM Y.productsupplierrefs;
+FROM legacy.productsupplierrefs;
 
 INSERT INTO productsymbols (
 	productsymbols_product_id,
@@ -307,7 +265,7 @@ INSERT INTO productsymbols (
 )
 SELECT productsymbols_product_id,
 productsymbols_symbol_id
-FROM Y.productsymbols;
+FROM legacy.productsymbols;
 
 INSERT INTO productsynonyms (
 	productsynonyms_product_id,
@@ -315,14 +273,14 @@ INSERT INTO productsynonyms (
 )
 SELECT productsynonyms_product_id,
 productsynonyms_name_id
-FROM Y.productsynonyms;
+FROM legacy.productsynonyms;
 
 INSERT INTO producttags (
 	producttags_product_id,
 	producttags_tag_id
 )
 SELECT producttags_product_id, producttags_tag_id
-FROM Y.producttags;
+FROM legacy.producttags;
 
 INSERT INTO signal_word (
 	signal_word_id,
@@ -330,7 +288,7 @@ INSERT INTO signal_word (
 )
 SELECT signalword_id,
 	signalword_label
-FROM Y.signalword;
+FROM legacy.signalword;
 
 INSERT INTO storage (
 	storage_id,
@@ -384,7 +342,7 @@ unit_concentration,
 unit_quantity,
 supplier,
 storage
-FROM Y.storage;
+FROM legacy.storage;
 
 UPDATE storage SET storage_to_destroy = 0 WHERE storage_to_destroy is NULL;
 
@@ -404,28 +362,7 @@ SELECT storelocation_id,
 	storelocation_fullpath,
 	entity,
 	storelocation
-FROM Y.storelocation;
-
--- Step 1: Try to insert normally
-INSERT OR IGNORE INTO supplier (supplier_id, supplier_label)
-SELECT supplier_id, supplier_label
-FROM Y.supplier;
-
--- Step 2: Insert conflicting rows with modified label
-INSERT INTO supplier (supplier_id, supplier_label)
-SELECT
-    supplier_id,
-    supplier_label || '_' || supplier_id
-FROM Y.supplier
-WHERE supplier_label IN (
-    SELECT supplier_label
-    FROM Y.supplier
-    GROUP BY supplier_label
-    HAVING COUNT(*) > 1
-)
-AND supplier_id NOT IN (
-    SELECT supplier_id FROM supplier
-);
+FROM legacy.storelocation;
 
 INSERT INTO supplier_ref (
 	supplier_ref_id,
@@ -435,7 +372,7 @@ INSERT INTO supplier_ref (
 SELECT supplierref_id,
 	supplierref_label,
 	supplier
-FROM Y.supplierref;
+FROM legacy.supplierref;
 
 INSERT INTO symbol (symbol_label) VALUES ('GHS01'), ('GHS02'), ('GHS03'), ('GHS04'), ('GHS05'), ('GHS06'), ('GHS07'), ('GHS08'), ('GHS09');
 
@@ -445,7 +382,7 @@ INSERT INTO tag (
 )
 SELECT tag_id,
 	tag_label
-FROM Y.tag;
+FROM legacy.tag;
 
 INSERT INTO unit (
 	unit_id,
@@ -459,9 +396,7 @@ SELECT unit_id,
 	unit_multiplier,
 	unit_type,
 	unit
-FROM Y.unit;
+FROM legacy.unit;
 
 INSERT INTO unit (unit_label, unit_multiplier, unit_type) VALUES ('g/mol', 1, 'molecular_weight');
 
-PRAGMA user_version=10;
-PRAGMA foreign_keys=on;
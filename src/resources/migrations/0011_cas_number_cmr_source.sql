@@ -0,0 +1,3 @@
+-- Revision of the classification the CMR category of a CAS number comes from,
+-- for example "ATP 21". NULL for the categories of the embedded CMR list.
+ALTER TABLE cas_number ADD COLUMN cas_number_cmr_source TEXT;
@@ -0,0 +1,190 @@
+-- Full text search index over the products.
+-- The index rowid is the product id. The rows are rebuilt from the
+-- product_fts_document view by triggers on the product, its join tables
+-- and the labels it references.
+
+CREATE VIEW IF NOT EXISTS product_fts_document AS
+SELECT
+	product.product_id AS product_id,
+	name.name_label AS name,
+	(SELECT GROUP_CONCAT(name.name_label, ' ') FROM productsynonyms
+		JOIN name ON productsynonyms.productsynonyms_name_id = name.name_id
+		WHERE productsynonyms.productsynonyms_product_id = product.product_id) AS synonyms,
+	cas_number.cas_number_label AS cas_number,
+	ce_number.ce_number_label AS ce_number,
+	empirical_formula.empirical_formula_label AS empirical_formula,
+	linear_formula.linear_formula_label AS linear_formula,
+	product.product_specificity AS specificity,
+	product.product_remark AS remark,
+	(SELECT GROUP_CONCAT(tag.tag_label, ' ') FROM producttags
+		JOIN tag ON producttags.producttags_tag_id = tag.tag_id
+		WHERE producttags.producttags_product_id = product.product_id) AS tags,
+	(SELECT GROUP_CONCAT(supplier_ref.supplier_ref_label, ' ') FROM productsupplierrefs
+		JOIN supplier_ref ON productsupplierrefs.productsupplierrefs_supplier_ref_id = supplier_ref.supplier_ref_id
+		WHERE productsupplierrefs.productsupplierrefs_product_id = product.product_id) AS supplier_refs
+FROM product
+LEFT JOIN name ON product.name = name.name_id
+LEFT JOIN cas_number ON product.cas_number = cas_number.cas_number_id
+LEFT JOIN ce_number ON product.ce_number = ce_number.ce_number_id
+LEFT JOIN empirical_formula ON product.empirical_formula = empirical_formula.empirical_formula_id
+LEFT JOIN linear_formula ON product.linear_formula = linear_formula.linear_formula_id;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS product_fts USING fts5 (
+	name,
+	synonyms,
+	cas_number,
+	ce_number,
+	empirical_formula,
+	linear_formula,
+	specificity,
+	remark,
+	tags,
+	supplier_refs,
+	tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+SELECT * FROM product_fts_document;
+
+--
+-- product
+--
+CREATE TRIGGER IF NOT EXISTS product_fts_product_insert AFTER INSERT ON product
+BEGIN
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = NEW.product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_product_update AFTER UPDATE ON product
+BEGIN
+	DELETE FROM product_fts WHERE rowid = OLD.product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = NEW.product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_product_delete AFTER DELETE ON product
+BEGIN
+	DELETE FROM product_fts WHERE rowid = OLD.product_id;
+END;
+
+--
+-- join tables
+--
+CREATE TRIGGER IF NOT EXISTS product_fts_productsynonyms_insert AFTER INSERT ON productsynonyms
+BEGIN
+	DELETE FROM product_fts WHERE rowid = NEW.productsynonyms_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = NEW.productsynonyms_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_productsynonyms_delete AFTER DELETE ON productsynonyms
+BEGIN
+	DELETE FROM product_fts WHERE rowid = OLD.productsynonyms_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = OLD.productsynonyms_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_productsynonyms_update AFTER UPDATE ON productsynonyms
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (OLD.productsynonyms_product_id, NEW.productsynonyms_product_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (OLD.productsynonyms_product_id, NEW.productsynonyms_product_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_producttags_insert AFTER INSERT ON producttags
+BEGIN
+	DELETE FROM product_fts WHERE rowid = NEW.producttags_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = NEW.producttags_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_producttags_delete AFTER DELETE ON producttags
+BEGIN
+	DELETE FROM product_fts WHERE rowid = OLD.producttags_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = OLD.producttags_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_producttags_update AFTER UPDATE ON producttags
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (OLD.producttags_product_id, NEW.producttags_product_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (OLD.producttags_product_id, NEW.producttags_product_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_productsupplierrefs_insert AFTER INSERT ON productsupplierrefs
+BEGIN
+	DELETE FROM product_fts WHERE rowid = NEW.productsupplierrefs_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = NEW.productsupplierrefs_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_productsupplierrefs_delete AFTER DELETE ON productsupplierrefs
+BEGIN
+	DELETE FROM product_fts WHERE rowid = OLD.productsupplierrefs_product_id;
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id = OLD.productsupplierrefs_product_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_productsupplierrefs_update AFTER UPDATE ON productsupplierrefs
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (OLD.productsupplierrefs_product_id, NEW.productsupplierrefs_product_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (OLD.productsupplierrefs_product_id, NEW.productsupplierrefs_product_id);
+END;
+
+--
+-- labels
+--
+CREATE TRIGGER IF NOT EXISTS product_fts_name_update AFTER UPDATE OF name_label ON name
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (
+		SELECT product_id FROM product WHERE name = NEW.name_id
+		UNION SELECT productsynonyms_product_id FROM productsynonyms WHERE productsynonyms_name_id = NEW.name_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (
+		SELECT product_id FROM product WHERE name = NEW.name_id
+		UNION SELECT productsynonyms_product_id FROM productsynonyms WHERE productsynonyms_name_id = NEW.name_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_cas_number_update AFTER UPDATE OF cas_number_label ON cas_number
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT product_id FROM product WHERE cas_number = NEW.cas_number_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT product_id FROM product WHERE cas_number = NEW.cas_number_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_ce_number_update AFTER UPDATE OF ce_number_label ON ce_number
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT product_id FROM product WHERE ce_number = NEW.ce_number_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT product_id FROM product WHERE ce_number = NEW.ce_number_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_empirical_formula_update AFTER UPDATE OF empirical_formula_label ON empirical_formula
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT product_id FROM product WHERE empirical_formula = NEW.empirical_formula_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT product_id FROM product WHERE empirical_formula = NEW.empirical_formula_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_linear_formula_update AFTER UPDATE OF linear_formula_label ON linear_formula
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT product_id FROM product WHERE linear_formula = NEW.linear_formula_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT product_id FROM product WHERE linear_formula = NEW.linear_formula_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_tag_update AFTER UPDATE OF tag_label ON tag
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT producttags_product_id FROM producttags WHERE producttags_tag_id = NEW.tag_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT producttags_product_id FROM producttags WHERE producttags_tag_id = NEW.tag_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS product_fts_supplier_ref_update AFTER UPDATE OF supplier_ref_label ON supplier_ref
+BEGIN
+	DELETE FROM product_fts WHERE rowid IN (SELECT productsupplierrefs_product_id FROM productsupplierrefs WHERE productsupplierrefs_supplier_ref_id = NEW.supplier_ref_id);
+	INSERT INTO product_fts (rowid, name, synonyms, cas_number, ce_number, empirical_formula, linear_formula, specificity, remark, tags, supplier_refs)
+	SELECT * FROM product_fts_document WHERE product_id IN (SELECT productsupplierrefs_product_id FROM productsupplierrefs WHERE productsupplierrefs_supplier_ref_id = NEW.supplier_ref_id);
+END;
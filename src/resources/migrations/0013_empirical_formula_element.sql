@@ -0,0 +1,18 @@
+-- Number of atoms of each element of the empirical formulas.
+-- Filled when the formulas are written, and for the existing formulas by the
+-- data step of the migration (empiricalformula::update_empirical_formula_elements).
+-- The rows of a formula are removed when its label changes.
+CREATE TABLE IF NOT EXISTS "empirical_formula_element" (
+	"empirical_formula"	INTEGER NOT NULL,
+	"element"	TEXT NOT NULL,
+	"element_count"	INTEGER NOT NULL,
+	PRIMARY KEY("empirical_formula","element"),
+	FOREIGN KEY("empirical_formula") REFERENCES "empirical_formula"("empirical_formula_id") ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS "idx_empirical_formula_element_element" ON "empirical_formula_element" ("element", "element_count");
+
+CREATE TRIGGER IF NOT EXISTS empirical_formula_element_label_update AFTER UPDATE OF empirical_formula_label ON empirical_formula
+BEGIN
+	DELETE FROM empirical_formula_element WHERE empirical_formula = NEW.empirical_formula_id;
+END;
@@ -0,0 +1,17 @@
+-- Products merged into another one with product::merge_products.
+-- The removed product is deleted, its name and specificity are kept here.
+CREATE TABLE IF NOT EXISTS "product_merge" (
+	"product_merge_id"	INTEGER,
+	"product_merge_date"	INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+	"product_merge_removed_product_id"	INTEGER NOT NULL,
+	"product_merge_removed_product_name"	TEXT NOT NULL,
+	"product_merge_removed_product_specificity"	TEXT,
+	"product_merge_nb_storages"	INTEGER NOT NULL DEFAULT 0,
+	"person"	INTEGER NOT NULL DEFAULT 1,
+	"product"	INTEGER NOT NULL,
+	PRIMARY KEY("product_merge_id"),
+	FOREIGN KEY("person") REFERENCES "person"("person_id") ON DELETE SET DEFAULT,
+	FOREIGN KEY("product") REFERENCES "product"("product_id") ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS "idx_product_merge_product" ON "product_merge" ("product");
@@ -0,0 +1,16 @@
+-- Revisions of the product cards, recorded by product::create_update_product.
+-- The snapshot is a JSON object with the product columns and the item ids
+-- of its join tables. The email is kept if the person is deleted.
+CREATE TABLE IF NOT EXISTS "product_revision" (
+	"product_revision_id"	INTEGER,
+	"product_revision_date"	INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+	"product_revision_person_email"	TEXT,
+	"product_revision_snapshot"	TEXT NOT NULL,
+	"person"	INTEGER,
+	"product"	INTEGER NOT NULL,
+	PRIMARY KEY("product_revision_id"),
+	FOREIGN KEY("person") REFERENCES "person"("person_id") ON DELETE SET NULL,
+	FOREIGN KEY("product") REFERENCES "product"("product_id") ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS "idx_product_revision_product" ON "product_revision" ("product");
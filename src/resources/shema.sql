@@ -1,5 +1,3 @@
-BEGIN TRANSACTION;
-
 CREATE TABLE IF NOT EXISTS "bookmark" (
 	"bookmark_id"	INTEGER,
 	"person"	INTEGER NOT NULL,
@@ -485,5 +483,3 @@ CREATE INDEX IF NOT EXISTS idx_storage_storage ON storage(storage);
 
 CREATE INDEX IF NOT EXISTS idx_personentities_entity ON personentities(personentities_entity_id);
 CREATE INDEX IF NOT EXISTS idx_personentities_person ON personentities(personentities_person_id);
-
-COMMIT;
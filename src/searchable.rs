@@ -1,10 +1,13 @@
 use chimitheque_traits::searchable::Searchable;
 use chimitheque_types::requestfilter::RequestFilter;
 use log::debug;
-use rusqlite::{Connection, ToSql, params_from_iter};
+use rusqlite::{Connection, OptionalExtension, ToSql, params, params_from_iter};
 use serde::Serialize;
-use std::fmt::Debug;
+use std::collections::HashMap;
 use std::fmt::Write as _; // import without risk of name clashing
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{empiricalformula::index_empirical_formula, sqlfunctions::normalize};
 
 pub fn parse(
     item: &(impl Searchable + Debug + Default + Serialize),
@@ -14,12 +17,14 @@ pub fn parse(
     debug!("s:{s:?}");
 
     // Select query statement.
+    // Formulas and numbers are compared exactly, as in create_update.
     let select_query = format!(
-        "SELECT {}, {} FROM {} WHERE {}==?1 COLLATE NOCASE",
+        "SELECT {}, {} FROM {} WHERE {}==?1 COLLATE {}",
         item.get_id_field_name(),
         item.get_text_field_name(),
         item.get_table_name(),
         item.get_text_field_name(),
+        label_collation(&item.get_table_name()),
     );
 
     // Perform select query.
@@ -82,7 +87,7 @@ pub fn get_many<Var: Searchable + Debug + Default + Serialize>(
     if let Some(_search) = maybe_search {
         write!(
             &mut select_query,
-            " WHERE {} LIKE (?1)",
+            " WHERE normalize({}) LIKE normalize(?1)",
             item.get_text_field_name()
         )
         .unwrap();
@@ -97,7 +102,7 @@ pub fn get_many<Var: Searchable + Debug + Default + Serialize>(
 
     write!(
         &mut select_query,
-        " ORDER BY {} COLLATE NOCASE ASC",
+        " ORDER BY {} COLLATE UNICODE_NOCASE ASC",
         item.get_text_field_name()
     )
     .unwrap();
@@ -128,7 +133,7 @@ pub fn get_many<Var: Searchable + Debug + Default + Serialize>(
         new_item.set_text_field(&row_text);
 
         if let Some(search) = maybe_search
-            && row_text.eq(search)
+            && normalize(&row_text).eq(&normalize(search))
         {
             new_item.set_exact_search(true);
         }
@@ -161,7 +166,7 @@ pub fn get_many<Var: Searchable + Debug + Default + Serialize>(
     if let Some(_search) = maybe_search {
         write!(
             &mut count_query,
-            " WHERE {} LIKE (?1)",
+            " WHERE normalize({}) LIKE normalize(?1)",
             item.get_text_field_name()
         )
         .unwrap();
@@ -192,6 +197,41 @@ pub fn get_many<Var: Searchable + Debug + Default + Serialize>(
     Ok((items, count))
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum CreateUpdateError {
+    // An other item has the same label, regardless of case and accents.
+    DuplicateLabel { label: String, existing_id: u64 },
+}
+
+impl Display for CreateUpdateError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            CreateUpdateError::DuplicateLabel { label, existing_id } => {
+                write!(f, "{label} already exists with id {existing_id}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CreateUpdateError {}
+
+// Tables whose labels are case sensitive.
+static CASE_SENSITIVE_TABLES: &[&str] = &[
+    "cas_number",
+    "ce_number",
+    "empirical_formula",
+    "linear_formula",
+];
+
+// Return the collation used to compare the labels of the table.
+fn label_collation(table_name: &str) -> &'static str {
+    if CASE_SENSITIVE_TABLES.contains(&table_name) {
+        "BINARY"
+    } else {
+        "UNICODE_NOCASE"
+    }
+}
+
 pub fn create_update(
     item: &impl Searchable,
     item_id: Option<u64>,
@@ -200,6 +240,30 @@ pub fn create_update(
 ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
     let last_insert_id: u64;
 
+    // Labels are unique regardless of case and accents,
+    // formulas and numbers are compared exactly (CO is not Co).
+    let duplicate_query = format!(
+        "SELECT {} FROM {} WHERE {}==?1 COLLATE {} AND {} IS NOT ?2",
+        item.get_id_field_name(),
+        item.get_table_name(),
+        item.get_text_field_name(),
+        label_collation(&item.get_table_name()),
+        item.get_id_field_name()
+    );
+
+    debug!("duplicate_query:{duplicate_query:?}");
+
+    let maybe_existing_id: Option<u64> = db_connection
+        .query_row(&duplicate_query, params![text, item_id], |row| row.get(0))
+        .optional()?;
+
+    if let Some(existing_id) = maybe_existing_id {
+        return Err(Box::new(CreateUpdateError::DuplicateLabel {
+            label: text.to_string(),
+            existing_id,
+        }));
+    }
+
     if let Some(item_id) = item_id {
         let query = format!(
             "UPDATE {} SET {}=?1 WHERE {}=?2",
@@ -227,9 +291,373 @@ pub fn create_update(
         last_insert_id = u64::try_from(db_connection.last_insert_rowid())?;
     }
 
+    // The element rows of a renamed formula are removed by a trigger.
+    if item.get_table_name() == "empirical_formula" {
+        index_empirical_formula(db_connection, last_insert_id, text)?;
+    }
+
     Ok(last_insert_id)
 }
 
+// Columns referencing a searchable table: (table, column, join table).
+// Join table references are part of the primary key.
+static REFERENCES: &[(&str, &[(&str, &str, bool)])] = &[
+    ("cas_number", &[("product", "cas_number", false)]),
+    ("ce_number", &[("product", "ce_number", false)]),
+    ("category", &[("product", "category", false)]),
+    (
+        "class_of_compound",
+        &[(
+            "productclassesofcompounds",
+            "productclassesofcompounds_class_of_compound_id",
+            true,
+        )],
+    ),
+    (
+        "empirical_formula",
+        &[("product", "empirical_formula", false)],
+    ),
+    ("linear_formula", &[("product", "linear_formula", false)]),
+    (
+        "name",
+        &[
+            ("product", "name", false),
+            ("productsynonyms", "productsynonyms_name_id", true),
+        ],
+    ),
+    ("physical_state", &[("product", "physical_state", false)]),
+    ("signal_word", &[("product", "signal_word", false)]),
+    (
+        "symbol",
+        &[("productsymbols", "productsymbols_symbol_id", true)],
+    ),
+    ("tag", &[("producttags", "producttags_tag_id", true)]),
+    ("producer", &[("producer_ref", "producer", false)]),
+    ("producer_ref", &[("product", "producer_ref", false)]),
+    (
+        "supplier",
+        &[
+            ("supplier_ref", "supplier", false),
+            ("storage", "supplier", false),
+        ],
+    ),
+    (
+        "supplier_ref",
+        &[(
+            "productsupplierrefs",
+            "productsupplierrefs_supplier_ref_id",
+            true,
+        )],
+    ),
+];
+
+// Return the columns referencing the given table.
+pub(crate) fn get_references(table_name: &str) -> &'static [(&'static str, &'static str, bool)] {
+    REFERENCES
+        .iter()
+        .find(|(table, _)| *table == table_name)
+        .map_or(&[], |(_, references)| references)
+}
+
+// Make the rows referencing from_id reference to_id instead, then delete from_id.
+// Join table rows already referencing to_id are dropped.
+// Return the number of updated references.
+pub(crate) fn merge_rows(
+    db_connection: &Connection,
+    table_name: &str,
+    id_field_name: &str,
+    from_id: u64,
+    to_id: u64,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("merge_rows: {table_name} {from_id} -> {to_id}");
+
+    let mut nb_updated = 0;
+
+    for (reference_table, reference_column, is_join) in get_references(table_name) {
+        let update_query = if *is_join {
+            format!(
+                "UPDATE OR IGNORE {reference_table} SET {reference_column}=?1 WHERE {reference_column}=?2"
+            )
+        } else {
+            format!(
+                "UPDATE {reference_table} SET {reference_column}=?1 WHERE {reference_column}=?2"
+            )
+        };
+
+        debug!("sql: {update_query}");
+
+        nb_updated += db_connection.execute(&update_query, [to_id, from_id])?;
+
+        if *is_join {
+            let delete_query = format!("DELETE FROM {reference_table} WHERE {reference_column}=?1");
+
+            debug!("sql: {delete_query}");
+
+            db_connection.execute(&delete_query, [from_id])?;
+        }
+    }
+
+    let delete_query = format!("DELETE FROM {table_name} WHERE {id_field_name}=?1");
+
+    debug!("sql: {delete_query}");
+
+    db_connection.execute(&delete_query, [from_id])?;
+
+    Ok(nb_updated)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MergeError {
+    SameItem(u64),
+    ItemNotFound(u64),
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            MergeError::SameItem(id) => write!(f, "can not merge item {id} into itself"),
+            MergeError::ItemNotFound(id) => write!(f, "item {id} not found"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+// Merge the from_id item into the into_id item: every reference to from_id
+// (foreign keys and join tables) is rewritten to into_id, then from_id is deleted.
+// Return the number of updated references.
+pub fn merge(
+    item: &impl Searchable,
+    db_connection: &mut Connection,
+    from_id: u64,
+    into_id: u64,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("merge: {from_id} -> {into_id}");
+
+    if from_id == into_id {
+        return Err(Box::new(MergeError::SameItem(from_id)));
+    }
+
+    let tx = db_connection.transaction()?;
+
+    let exists_query = format!(
+        "SELECT COUNT(*) FROM {} WHERE {}=?1",
+        item.get_table_name(),
+        item.get_id_field_name()
+    );
+
+    for id in [from_id, into_id] {
+        let count: u64 = tx.query_row(&exists_query, [id], |row| row.get(0))?;
+        if count == 0 {
+            return Err(Box::new(MergeError::ItemNotFound(id)));
+        }
+    }
+
+    let nb_updated = merge_rows(
+        &tx,
+        &item.get_table_name(),
+        &item.get_id_field_name(),
+        from_id,
+        into_id,
+    )?;
+
+    tx.commit()?;
+
+    Ok(nb_updated)
+}
+
+//
+// Usage counts.
+//
+
+// Return the number of rows referencing the given row.
+fn count_references(
+    db_connection: &Connection,
+    table_name: &str,
+    id: u64,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut count = 0;
+
+    for (reference_table, reference_column, _) in get_references(table_name) {
+        let query = format!("SELECT COUNT(*) FROM {reference_table} WHERE {reference_column}=?1");
+
+        debug!("sql: {query}");
+
+        count += db_connection.query_row(&query, [id], |row| row.get::<_, usize>(0))?;
+    }
+
+    Ok(count)
+}
+
+// Return the number of rows (products, synonyms, storages...) referencing the item.
+pub fn get_usage_count(
+    item: &impl Searchable,
+    db_connection: &Connection,
+    item_id: u64,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    count_references(db_connection, &item.get_table_name(), item_id)
+}
+
+// Return the number of rows referencing each of the given rows, in one query.
+// Rows referenced by nothing are missing from the result.
+fn count_references_by_id(
+    db_connection: &Connection,
+    table_name: &str,
+    ids: &[u64],
+) -> Result<HashMap<u64, usize>, Box<dyn std::error::Error + Send + Sync>> {
+    let references = get_references(table_name);
+
+    if references.is_empty() || ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // The ids are bound once as a JSON array, whatever their number, to stay
+    // below the SQLite bound parameters limit.
+    let query = format!(
+        "SELECT item_id, SUM(nb_references) FROM ({}) GROUP BY item_id",
+        references
+            .iter()
+            .map(|(reference_table, reference_column, _)| format!(
+                "SELECT {reference_column} AS item_id, COUNT(*) AS nb_references FROM {reference_table}
+                WHERE {reference_column} IN (SELECT value FROM json_each(?1)) GROUP BY {reference_column}"
+            ))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ")
+    );
+
+    debug!("sql: {query}");
+
+    let counts = db_connection
+        .prepare(&query)?
+        .query_map([serde_json::to_string(ids)?], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, usize>(1)?))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    Ok(counts)
+}
+
+// Same as get_many with the usage count of each item.
+pub fn get_many_with_usage_count<Var: Searchable + Debug + Default + Serialize>(
+    item: &Var,
+    db_connection: &Connection,
+    filter: &RequestFilter,
+) -> Result<(Vec<(Var, usize)>, usize), Box<dyn std::error::Error + Send + Sync>> {
+    let (items, count) = get_many(item, db_connection, filter)?;
+
+    let ids: Vec<u64> = items.iter().filter_map(Searchable::get_id).collect();
+    let usage_counts = count_references_by_id(db_connection, &item.get_table_name(), &ids)?;
+
+    let items_with_usage_count = items
+        .into_iter()
+        .map(|item| {
+            let usage_count = item
+                .get_id()
+                .and_then(|item_id| usage_counts.get(&item_id).copied())
+                .unwrap_or(0);
+
+            (item, usage_count)
+        })
+        .collect();
+
+    Ok((items_with_usage_count, count))
+}
+
+// Lookup tables filled when creating products, with an extra condition
+// protecting rows that are not orphans.
+// CAS numbers carry the CMR categories and are kept when they have one.
+static PURGEABLE_TABLES: &[(&str, Option<&str>)] = &[
+    ("name", None),
+    ("cas_number", Some("cas_number_cmr IS NULL")),
+    ("ce_number", None),
+    ("empirical_formula", None),
+    ("linear_formula", None),
+    ("supplier_ref", None),
+];
+
+// A lookup row referenced by nothing.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct UnusedRow {
+    pub table: String,
+    pub id: u64,
+    pub label: String,
+}
+
+// Delete the name, CAS number, CE number, empirical formula, linear formula
+// and supplier reference rows referenced by nothing.
+// Rows referenced by a product revision are kept, so that the revision can
+// still be restored.
+// With dry_run nothing is deleted.
+// Return the unused rows.
+pub fn purge_unused(
+    db_connection: &mut Connection,
+    dry_run: bool,
+) -> Result<Vec<UnusedRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut unused_rows = vec![];
+
+    let tx = db_connection.transaction()?;
+
+    for (table_name, maybe_condition) in PURGEABLE_TABLES {
+        let mut conditions: Vec<String> = vec![];
+
+        for (reference_table, reference_column, join_table) in get_references(table_name) {
+            conditions.push(format!(
+                "{table_name}_id NOT IN (SELECT {reference_column} FROM {reference_table} WHERE {reference_column} IS NOT NULL)"
+            ));
+
+            // Product columns and join tables are recorded in the revision snapshots.
+            if *join_table {
+                conditions.push(format!(
+                    "{table_name}_id NOT IN (SELECT value FROM product_revision,
+                        json_each(product_revision_snapshot, '$.relations.{reference_table}'))"
+                ));
+            } else if *reference_table == "product" {
+                conditions.push(format!(
+                    "{table_name}_id NOT IN (SELECT json_extract(product_revision_snapshot, '$.fields.{reference_column}') FROM product_revision
+                        WHERE json_extract(product_revision_snapshot, '$.fields.{reference_column}') IS NOT NULL)"
+                ));
+            }
+        }
+        if let Some(condition) = maybe_condition {
+            conditions.push((*condition).to_string());
+        }
+
+        let select_query = format!(
+            "SELECT {table_name}_id, {table_name}_label FROM {table_name} WHERE {} ORDER BY {table_name}_id",
+            conditions.join(" AND ")
+        );
+
+        debug!("sql: {select_query}");
+
+        let rows = tx
+            .prepare(&select_query)?
+            .query_map([], |row| {
+                Ok(UnusedRow {
+                    table: (*table_name).to_string(),
+                    id: row.get(0)?,
+                    label: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !dry_run {
+            let delete_query = format!("DELETE FROM {table_name} WHERE {table_name}_id=?1");
+
+            debug!("sql: {delete_query}");
+
+            for row in &rows {
+                tx.execute(&delete_query, [row.id])?;
+            }
+        }
+
+        unused_rows.extend(rows);
+    }
+
+    tx.commit()?;
+
+    Ok(unused_rows)
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -238,10 +666,337 @@ pub mod tests {
         init::populate_db_with_base_data,
         searchable::{get_many, parse},
     };
-    use chimitheque_types::requestfilter::RequestFilter;
+    use chimitheque_types::{
+        empiricalformula::EmpiricalFormula, name::Name, requestfilter::RequestFilter, tag::Tag,
+    };
     use log::info;
     use rusqlite::Connection;
 
+    #[test]
+    fn test_merge() {
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ETHANOL ABSOLUTE'), (3, 'METHANOL');
+                INSERT INTO tag (tag_id, tag_label) VALUES (1, 'solvent'), (2, 'solvant');
+                INSERT INTO product (product_id, product_type, name) VALUES (1, 'chem', 1), (2, 'chem', 2);
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2), (1, 3), (2, 3);
+                INSERT INTO producttags (producttags_product_id, producttags_tag_id) VALUES (1, 1), (1, 2), (2, 2);",
+            )
+            .unwrap();
+
+        // product 2 name, product 1 synonym.
+        let nb_updated = merge(&Name::default(), &mut db_connection, 2, 1).unwrap();
+        assert_eq!(nb_updated, 2);
+
+        let nb_names: u64 = db_connection
+            .query_row("SELECT COUNT(*) FROM name WHERE name_id = 2", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(nb_names, 0);
+
+        let nb_products: u64 = db_connection
+            .query_row("SELECT COUNT(*) FROM product WHERE name = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(nb_products, 2);
+
+        // Product 1 already has the tag 1, the colliding join row is dropped.
+        merge(&Tag::default(), &mut db_connection, 2, 1).unwrap();
+
+        let product_tags: Vec<(u64, u64)> = db_connection
+            .prepare(
+                "SELECT producttags_product_id, producttags_tag_id FROM producttags ORDER BY 1, 2",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(product_tags, vec![(1, 1), (2, 1)]);
+
+        assert_eq!(
+            merge(&Tag::default(), &mut db_connection, 1, 1)
+                .unwrap_err()
+                .downcast_ref::<MergeError>(),
+            Some(&MergeError::SameItem(1))
+        );
+        assert_eq!(
+            merge(&Tag::default(), &mut db_connection, 2, 1)
+                .unwrap_err()
+                .downcast_ref::<MergeError>(),
+            Some(&MergeError::ItemNotFound(2))
+        );
+    }
+
+    #[test]
+    fn test_accent_insensitive() {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ÉTHANOL'), (2, 'METHANOL'), (3, 'ÉTHER');",
+            )
+            .unwrap();
+
+        let name = parse(&Name::default(), &db_connection, "ethanol")
+            .unwrap()
+            .unwrap();
+        assert_eq!(name.get_id(), Some(1));
+
+        let (names, count) = get_many(
+            &Name::default(),
+            &db_connection,
+            &RequestFilter {
+                search: Some("eth".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            names.iter().map(Searchable::get_text).collect::<Vec<_>>(),
+            vec!["ÉTHANOL", "ÉTHER", "METHANOL"]
+        );
+
+        // Exact matches first.
+        let (names, _) = get_many(
+            &Name::default(),
+            &db_connection,
+            &RequestFilter {
+                search: Some("ether".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(names[0].get_text(), "ÉTHER");
+
+        // Uniqueness.
+        let err = create_update(&Name::default(), None, &db_connection, "Ethanol").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CreateUpdateError>(),
+            Some(&CreateUpdateError::DuplicateLabel {
+                label: "Ethanol".to_string(),
+                existing_id: 1,
+            })
+        );
+        assert!(create_update(&Name::default(), Some(1), &db_connection, "éthanol").is_ok());
+        assert!(create_update(&Name::default(), Some(2), &db_connection, "ethanol").is_err());
+    }
+
+    #[test]
+    fn test_create_update_case_sensitive_formulas() {
+        let db_connection = crate::test_utils::init_test();
+
+        // Cobalt and carbon monoxide.
+        let cobalt_id =
+            create_update(&EmpiricalFormula::default(), None, &db_connection, "Co").unwrap();
+        let carbon_monoxide_id =
+            create_update(&EmpiricalFormula::default(), None, &db_connection, "CO").unwrap();
+        assert_ne!(cobalt_id, carbon_monoxide_id);
+
+        assert_eq!(
+            create_update(&EmpiricalFormula::default(), None, &db_connection, "CO")
+                .unwrap_err()
+                .downcast_ref::<CreateUpdateError>(),
+            Some(&CreateUpdateError::DuplicateLabel {
+                label: "CO".to_string(),
+                existing_id: carbon_monoxide_id,
+            })
+        );
+
+        // Labels are still case insensitive.
+        create_update(&Name::default(), None, &db_connection, "Cobalt").unwrap();
+        assert!(create_update(&Name::default(), None, &db_connection, "COBALT").is_err());
+    }
+
+    #[test]
+    fn test_create_update_empirical_formula_elements() {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL');
+                INSERT INTO person (person_id, person_email) VALUES (1, 'admin@chimitheque.fr');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES
+                    (1, 'all', 'all', NULL);",
+            )
+            .unwrap();
+
+        let empirical_formula_id =
+            create_update(&EmpiricalFormula::default(), None, &db_connection, "C2H6O").unwrap();
+        db_connection
+            .execute(
+                "INSERT INTO product (product_id, product_type, name, empirical_formula) VALUES (1, 'chem', 1, ?1)",
+                [empirical_formula_id],
+            )
+            .unwrap();
+
+        let search = |element: &str| {
+            crate::product::search_products_by_elements(
+                &db_connection,
+                &crate::product::ElementFilter {
+                    contains: vec![element.to_string()],
+                    ..Default::default()
+                },
+                1,
+            )
+            .unwrap()
+        };
+        assert_eq!(search("O"), vec![1]);
+
+        // Renamed formula.
+        create_update(
+            &EmpiricalFormula::default(),
+            Some(empirical_formula_id),
+            &db_connection,
+            "C2H5Br",
+        )
+        .unwrap();
+        assert!(search("O").is_empty());
+        assert_eq!(search("Br"), vec![1]);
+    }
+
+    #[test]
+    fn test_parse_case_sensitive_formulas() {
+        let db_connection = crate::test_utils::init_test();
+
+        let cobalt_id =
+            create_update(&EmpiricalFormula::default(), None, &db_connection, "Co").unwrap();
+
+        assert!(
+            parse(&EmpiricalFormula::default(), &db_connection, "CO")
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            parse(&EmpiricalFormula::default(), &db_connection, "Co")
+                .unwrap()
+                .and_then(|empirical_formula| empirical_formula.get_id()),
+            Some(cobalt_id)
+        );
+
+        // Labels are still case insensitive.
+        create_update(&Name::default(), None, &db_connection, "Cobalt").unwrap();
+        assert!(
+            parse(&Name::default(), &db_connection, "COBALT")
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_purge_unused() {
+        let mut db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ALCOHOL'), (3, 'METHANOL');
+                INSERT INTO cas_number (cas_number_id, cas_number_label, cas_number_cmr) VALUES
+                    (1, '64-17-5', NULL), (2, '67-56-1', NULL), (3, '71-43-2', 'C1A M1B');
+                INSERT INTO supplier (supplier_id, supplier_label) VALUES (1, 'sigma');
+                INSERT INTO supplier_ref (supplier_ref_id, supplier_ref_label, supplier) VALUES (1, 'ref1', 1), (2, 'ref2', 1);
+                INSERT INTO product (product_id, product_type, name, cas_number) VALUES (1, 'chem', 1, 1);
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2);
+                INSERT INTO productsupplierrefs (productsupplierrefs_product_id, productsupplierrefs_supplier_ref_id) VALUES (1, 1);",
+            )
+            .unwrap();
+
+        assert_eq!(
+            get_usage_count(&Name::default(), &db_connection, 1).unwrap(),
+            1
+        );
+        assert_eq!(
+            get_usage_count(&Name::default(), &db_connection, 3).unwrap(),
+            0
+        );
+
+        let (names, count) =
+            get_many_with_usage_count(&Name::default(), &db_connection, &RequestFilter::default())
+                .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            names
+                .iter()
+                .map(|(name, usage_count)| (name.get_text(), *usage_count))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ALCOHOL".to_string(), 1),
+                ("ETHANOL".to_string(), 1),
+                ("METHANOL".to_string(), 0)
+            ]
+        );
+
+        let expected = vec![
+            UnusedRow {
+                table: "name".to_string(),
+                id: 3,
+                label: "METHANOL".to_string(),
+            },
+            UnusedRow {
+                table: "cas_number".to_string(),
+                id: 2,
+                label: "67-56-1".to_string(),
+            },
+            UnusedRow {
+                table: "supplier_ref".to_string(),
+                id: 2,
+                label: "ref2".to_string(),
+            },
+        ];
+
+        // Dry run.
+        assert_eq!(purge_unused(&mut db_connection, true).unwrap(), expected);
+        assert_eq!(purge_unused(&mut db_connection, false).unwrap(), expected);
+        assert!(purge_unused(&mut db_connection, false).unwrap().is_empty());
+
+        let nb_cas_numbers: u64 = db_connection
+            .query_row("SELECT COUNT(*) FROM cas_number", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(nb_cas_numbers, 2);
+
+        // Rows referenced by a product revision only are kept.
+        db_connection
+            .execute_batch(
+                r#"INSERT INTO name (name_id, name_label) VALUES (4, 'ETHYL ALCOHOL'), (5, 'PROPANOL');
+                INSERT INTO cas_number (cas_number_id, cas_number_label) VALUES (4, '67-63-0');
+                INSERT INTO product_revision (product, product_revision_snapshot) VALUES
+                    (1, '{"fields": {"name": 1, "cas_number": 4}, "relations": {"productsynonyms": [4]}}');"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            purge_unused(&mut db_connection, false).unwrap(),
+            vec![UnusedRow {
+                table: "name".to_string(),
+                id: 5,
+                label: "PROPANOL".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_count_references_by_id_many_ids() {
+        let db_connection = crate::test_utils::init_test();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ALCOHOL');
+                INSERT INTO product (product_id, product_type, name) VALUES (1, 'chem', 1);
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 1), (1, 2);",
+            )
+            .unwrap();
+
+        // More ids than the SQLite bound parameters limit.
+        let ids: Vec<u64> = (1..=40_000).collect();
+        let counts = count_references_by_id(&db_connection, "name", &ids).unwrap();
+
+        assert_eq!(counts, HashMap::from([(1, 2), (2, 1)]));
+    }
+
     fn init_logger() {
         let _ = env_logger::builder().is_test(true).try_init();
     }
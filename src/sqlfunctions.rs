@@ -0,0 +1,132 @@
+use log::debug;
+use regex::Regex;
+use rusqlite::{
+    Connection,
+    functions::{Context, FunctionFlags},
+    types::ValueRef,
+};
+use std::sync::Arc;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+// Compile the regex pattern of the first argument.
+// The compiled regex is cached by SQLite as long as the pattern argument
+// is a constant of the statement.
+fn get_regex(ctx: &Context) -> Result<Arc<Regex>, rusqlite::Error> {
+    ctx.get_or_create_aux(0, |pattern| -> Result<Regex, BoxError> {
+        Ok(Regex::new(pattern.as_str()?)?)
+    })
+}
+
+// regexp(pattern, text)
+// Return 1 if text matches pattern, 0 otherwise, NULL if text is NULL.
+// Also used by SQLite for the "text REGEXP pattern" operator.
+fn regexp(ctx: &Context) -> Result<Option<bool>, rusqlite::Error> {
+    let re = get_regex(ctx)?;
+
+    let Some(text) = ctx.get::<Option<String>>(1)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(re.is_match(&text)))
+}
+
+// regex_capture(pattern, text, group)
+// Return the given group (name or index) of the first match of pattern in text,
+// NULL if there is no match or no such group.
+fn regex_capture(ctx: &Context) -> Result<Option<String>, rusqlite::Error> {
+    let re = get_regex(ctx)?;
+
+    let Some(text) = ctx.get::<Option<String>>(1)? else {
+        return Ok(None);
+    };
+
+    let Some(captures) = re.captures(&text) else {
+        return Ok(None);
+    };
+
+    let group = match ctx.get_raw(2) {
+        ValueRef::Integer(index) => usize::try_from(index)
+            .ok()
+            .and_then(|index| captures.get(index)),
+        ValueRef::Text(name) => {
+            let name = std::str::from_utf8(name)
+                .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))?;
+            captures.name(name)
+        }
+        _ => None,
+    };
+
+    Ok(group.map(|group| group.as_str().to_string()))
+}
+
+// Fold a character to its lowercase unaccented form.
+// Covers the Latin-1 and Latin Extended-A letters used in French and
+// most European languages.
+fn fold_char(c: char, folded: &mut String) {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => folded.push('a'),
+        'æ' => folded.push_str("ae"),
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => folded.push('c'),
+        'ď' | 'đ' => folded.push('d'),
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => folded.push('e'),
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => folded.push('g'),
+        'ĥ' | 'ħ' => folded.push('h'),
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => folded.push('i'),
+        'ĵ' => folded.push('j'),
+        'ķ' => folded.push('k'),
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => folded.push('l'),
+        'ñ' | 'ń' | 'ņ' | 'ň' => folded.push('n'),
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => folded.push('o'),
+        'œ' => folded.push_str("oe"),
+        'ŕ' | 'ŗ' | 'ř' => folded.push('r'),
+        'ś' | 'ŝ' | 'ş' | 'š' => folded.push('s'),
+        'ß' => folded.push_str("ss"),
+        'ţ' | 'ť' | 'ŧ' => folded.push('t'),
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => folded.push('u'),
+        'ŵ' => folded.push('w'),
+        'ý' | 'ÿ' | 'ŷ' => folded.push('y'),
+        'ź' | 'ż' | 'ž' => folded.push('z'),
+        _ => folded.push(c),
+    }
+}
+
+// Return the lowercase unaccented form of s, "Éthanol" -> "ethanol".
+#[must_use]
+pub fn normalize(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+
+    for c in s.chars().flat_map(char::to_lowercase) {
+        fold_char(c, &mut folded);
+    }
+
+    folded
+}
+
+// normalize(text)
+// Return the lowercase unaccented form of text, NULL if text is NULL.
+fn normalize_function(ctx: &Context) -> Result<Option<String>, rusqlite::Error> {
+    Ok(ctx.get::<Option<String>>(0)?.map(|text| normalize(&text)))
+}
+
+// Register the Rust SQL functions and collations on the connection.
+pub fn register_functions(db_connection: &Connection) -> Result<(), rusqlite::Error> {
+    debug!("registering SQL functions");
+
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+
+    db_connection.create_scalar_function("regexp", 2, flags, regexp)?;
+    db_connection.create_scalar_function("regex_capture", 3, flags, regex_capture)?;
+    db_connection.create_scalar_function("normalize", 1, flags, normalize_function)?;
+
+    // Case and accent insensitive collation: "éthanol", "Éthanol" and "ethanol" are equal.
+    // It is not used in the schema so that the database can still be
+    // opened by tools that do not know it.
+    db_connection.create_collation("UNICODE_NOCASE", |a, b| normalize(a).cmp(&normalize(b)))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "sqlfunctions_tests.rs"]
+mod sqlfunctions_tests;
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::sqlfunctions::*;
+    use rusqlite::Connection;
+
+    fn init_test_sqlfunctions() -> Connection {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let db_connection = Connection::open_in_memory().unwrap();
+        register_functions(&db_connection).unwrap();
+
+        db_connection
+    }
+
+    #[test]
+    fn test_regexp() {
+        let db_connection = init_test_sqlfunctions();
+
+        let is_match: Option<bool> = db_connection
+            .query_row(
+                r"SELECT regexp('^[_a-zA-Z]+[0-9]+\.[0-9]+$', 'AB12.3')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(is_match, Some(true));
+
+        let is_match: Option<bool> = db_connection
+            .query_row(
+                r"SELECT regexp('^[_a-zA-Z]+[0-9]+\.[0-9]+$', 'AB12')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(is_match, Some(false));
+
+        let is_match: Option<bool> = db_connection
+            .query_row("SELECT regexp('^a', NULL)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(is_match, None);
+
+        // REGEXP operator.
+        let is_match: Option<bool> = db_connection
+            .query_row("SELECT 'abc' REGEXP '^a.c$'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(is_match, Some(true));
+
+        // Invalid pattern.
+        assert!(
+            db_connection
+                .query_row("SELECT regexp('(', 'abc')", [], |row| row.get::<_, bool>(0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_regex_capture() {
+        let db_connection = init_test_sqlfunctions();
+
+        let capture: Option<String> = db_connection
+            .query_row(
+                r"SELECT regex_capture('^[_a-zA-Z]+(?P<barecode_major>[0-9]+)\.[0-9]+$', 'AB12.3', 'barecode_major')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(capture, Some("12".to_string()));
+
+        let capture: Option<String> = db_connection
+            .query_row(
+                r"SELECT regex_capture('^([_a-zA-Z]+)([0-9]+)\.[0-9]+$', 'AB12.3', 1)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(capture, Some("AB".to_string()));
+
+        // No match.
+        let capture: Option<String> = db_connection
+            .query_row(
+                r"SELECT regex_capture('^(?P<major>[0-9]+)$', 'AB12.3', 'major')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(capture, None);
+
+        // Unknown group.
+        let capture: Option<String> = db_connection
+            .query_row(
+                r"SELECT regex_capture('^(?P<major>[0-9]+)$', '12', 'minor')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(capture, None);
+
+        // Used on a column.
+        db_connection
+            .execute_batch(
+                "CREATE TABLE t (v TEXT);
+                INSERT INTO t VALUES ('A1.1'), ('A10.2'), ('B3.0'), (NULL);",
+            )
+            .unwrap();
+        let max: Option<i64> = db_connection
+            .query_row(
+                r"SELECT MAX(CAST(regex_capture('^[A-Z]+(?P<major>[0-9]+)\.[0-9]+$', v, 'major') AS INTEGER)) FROM t",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(max, Some(10));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let db_connection = init_test_sqlfunctions();
+
+        assert_eq!(normalize("Éthanol"), "ethanol");
+        assert_eq!(normalize("ŒSTROGÈNE"), "oestrogene");
+        assert_eq!(
+            normalize("Acide 2,4-dichlorophénoxyacétique"),
+            "acide 2,4-dichlorophenoxyacetique"
+        );
+
+        let normalized: Option<String> = db_connection
+            .query_row("SELECT normalize('Chlorure de Méthylène')", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(normalized, Some("chlorure de methylene".to_string()));
+
+        let normalized: Option<String> = db_connection
+            .query_row("SELECT normalize(NULL)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(normalized, None);
+    }
+
+    #[test]
+    fn test_unicode_nocase_collation() {
+        let db_connection = init_test_sqlfunctions();
+
+        db_connection
+            .execute_batch(
+                "CREATE TABLE t (v TEXT);
+                INSERT INTO t VALUES ('zinc'), ('Éthanol'), ('eau'), ('acétone'), ('Ether');",
+            )
+            .unwrap();
+
+        let values: Vec<String> = db_connection
+            .prepare("SELECT v FROM t ORDER BY v COLLATE UNICODE_NOCASE")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(values, vec!["acétone", "eau", "Éthanol", "Ether", "zinc"]);
+
+        let count: u64 = db_connection
+            .query_row(
+                "SELECT COUNT(*) FROM t WHERE v = 'ETHANOL' COLLATE UNICODE_NOCASE",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -155,7 +155,7 @@ pub fn compute_stock(
         .group_by_col((Alias::new("self_unit"), Unit::Unit))
         .order_by_expr(
             Expr::cust_with_expr(
-                "? COLLATE NOCASE",
+                "? COLLATE UNICODE_NOCASE",
                 Expr::col(StoreLocation::StoreLocationFullPath),
             ),
             Order::Asc,
@@ -16,7 +16,7 @@ use csv::WriterBuilder;
 use log::debug;
 use qrcode_png::{Color, QrCode, QrCodeEcc};
 use regex::Regex;
-use rusqlite::{Connection, Row, Transaction};
+use rusqlite::{Connection, Row, Transaction, params};
 use sea_query::{
     Alias, ColumnRef, Cond, Expr, ExprTrait, Iden, IntoColumnRef, JoinType, Order, Query,
     SimpleExpr, SqliteQueryBuilder, any,
@@ -1083,7 +1083,7 @@ pub fn get_storages(
         .expr(Expr::col((Borrowing::Table, Borrowing::BorrowingComment)))
         .group_by_col((Storage::Table, Storage::StorageId))
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(order_by)),
+            Expr::cust_with_expr("? COLLATE UNICODE_NOCASE", Expr::col(order_by)),
             order,
         )
         .conditions(
@@ -1142,7 +1142,7 @@ pub fn get_storages(
     Ok((storages, count))
 }
 
-fn create_storage_qrcode(
+pub(crate) fn create_storage_qrcode(
     db_transaction: &Transaction,
     storage_id: u64,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -1331,16 +1331,86 @@ fn compute_storage_barecode_parts(
     Ok((barecode_string, barecode_major, barecode_minor + 1))
 }
 
+// Move a storage to another product, keeping a history copy of the storage.
+// With recompute_barecode the storage gets the barecode a new storage
+// of the product would get in the same store location.
+// Return the new barecode if any.
+pub(crate) fn move_storage_to_product(
+    db_transaction: &Transaction,
+    storage_id: u64,
+    product_id: u64,
+    person_id: u64,
+    recompute_barecode: bool,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let store_location_id: u64 = db_transaction.query_row(
+        "SELECT store_location FROM storage WHERE storage_id = ?1",
+        [storage_id],
+        |row| row.get(0),
+    )?;
+
+    let storage = StorageStruct {
+        storage_id: Some(storage_id),
+        store_location: StoreLocationStruct {
+            store_location_id: Some(store_location_id),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    create_storage_history(db_transaction, &storage)?;
+
+    // Computed before the move so that the current barecode is not counted.
+    let maybe_barecode = if recompute_barecode {
+        let (barecode_string, barecode_major, barecode_minor) =
+            compute_storage_barecode_parts(db_transaction, &storage, product_id, person_id)?;
+
+        Some(format!(
+            "{barecode_string}{barecode_major}.{barecode_minor}"
+        ))
+    } else {
+        None
+    };
+
+    db_transaction.execute(
+        "UPDATE storage SET product = ?1,
+            storage_barecode = COALESCE(?2, storage_barecode),
+            storage_modification_date = strftime('%s', 'now')
+        WHERE storage_id = ?3",
+        params![product_id, maybe_barecode, storage_id],
+    )?;
+
+    Ok(maybe_barecode)
+}
+
 pub fn create_update_storage(
     db_connection: &mut Connection,
+    storage: StorageStruct,
+    nb_items: u64,
+    identical_barecode: bool,
+) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    let db_transaction = db_connection.transaction()?;
+
+    let storage_ids = create_update_storage_in_transaction(
+        &db_transaction,
+        storage,
+        nb_items,
+        identical_barecode,
+    )?;
+
+    db_transaction.commit()?;
+
+    Ok(storage_ids)
+}
+
+// Same as create_update_storage, in a transaction managed by the caller.
+pub(crate) fn create_update_storage_in_transaction(
+    db_transaction: &Transaction,
     mut storage: StorageStruct,
     nb_items: u64,
     identical_barecode: bool,
 ) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
     debug!("create_update_storage: {storage:#?}");
 
-    let db_transaction = db_connection.transaction()?;
-
     //
     // supplier
     //
@@ -1354,7 +1424,7 @@ pub fn create_update_storage(
                 ..Default::default()
             },
             None,
-            &db_transaction,
+            db_transaction,
             supplier.supplier_label.as_str(),
         )?;
         storage.supplier = Some(SupplierStruct {
@@ -1383,7 +1453,7 @@ pub fn create_update_storage(
     // Create history on update.
     //
     if storage.storage_id.is_some() {
-        create_storage_history(&db_transaction, &storage)?;
+        create_storage_history(db_transaction, &storage)?;
     }
 
     //
@@ -1394,7 +1464,7 @@ pub fn create_update_storage(
     let mut barecode_minor: u64 = 1;
     if storage.storage_barecode.is_none() {
         (barecode_string, barecode_major, barecode_minor) =
-            compute_storage_barecode_parts(&db_transaction, &storage, product_id, person_id)?;
+            compute_storage_barecode_parts(db_transaction, &storage, product_id, person_id)?;
 
         storage.storage_barecode = Some(format!(
             "{barecode_string}{barecode_major}.{barecode_minor}"
@@ -1708,14 +1778,14 @@ pub fn create_update_storage(
         } else {
             last_insert_update_id = db_transaction.last_insert_rowid().try_into()?;
 
-            create_storage_qrcode(&db_transaction, last_insert_update_id)?;
+            create_storage_qrcode(db_transaction, last_insert_update_id)?;
         }
 
         debug!("last_insert_update_id: {last_insert_update_id}");
 
         storage_ids.push(last_insert_update_id);
 
-        create_storage_qrcode(&db_transaction, last_insert_update_id)?;
+        create_storage_qrcode(db_transaction, last_insert_update_id)?;
 
         if !identical_barecode {
             barecode_minor += 1;
@@ -1728,8 +1798,6 @@ pub fn create_update_storage(
         nb_items_created += 1;
     }
 
-    db_transaction.commit()?;
-
     Ok(storage_ids)
 }
 
@@ -0,0 +1,510 @@
+use crate::{
+    productimport::{field, header_position, in_savepoint},
+    searchable::parse,
+    storage::create_update_storage_in_transaction,
+    unit,
+};
+use chimitheque_traits::searchable::Searchable;
+use chimitheque_types::{
+    person::Person as PersonStruct, product::Product as ProductStruct,
+    storage::Storage as StorageStruct, storelocation::StoreLocation as StoreLocationStruct,
+    supplier::Supplier as SupplierStruct, unit::Unit as UnitStruct, unittype::UnitType,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use csv::{ReaderBuilder, StringRecord};
+use log::{debug, info, warn};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::{
+    fmt::{Display, Formatter},
+    io::Read,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StorageImportError {
+    MissingColumn(String),
+    MissingProduct,
+    ProductNotFound(String),
+    SeveralProducts(String),
+    MissingStoreLocation,
+    StoreLocationNotFound(String),
+    SeveralStoreLocations(String),
+    StoreLocationCanNotStore(String),
+    InvalidQuantity(String),
+    UnknownUnit(String),
+    NotAQuantityUnit(String),
+    InvalidDate(String),
+}
+
+impl Display for StorageImportError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            StorageImportError::MissingColumn(s) => write!(f, "missing column {s}"),
+            StorageImportError::MissingProduct => {
+                write!(f, "missing product cas number, name or barecode")
+            }
+            StorageImportError::ProductNotFound(s) => write!(f, "product not found for {s}"),
+            StorageImportError::SeveralProducts(s) => write!(f, "several products match {s}"),
+            StorageImportError::MissingStoreLocation => write!(f, "missing store location"),
+            StorageImportError::StoreLocationNotFound(s) => {
+                write!(f, "store location not found for {s}")
+            }
+            StorageImportError::SeveralStoreLocations(s) => {
+                write!(f, "several store locations match {s}")
+            }
+            StorageImportError::StoreLocationCanNotStore(s) => {
+                write!(f, "store location {s} can not store")
+            }
+            StorageImportError::InvalidQuantity(s) => write!(f, "invalid quantity {s}"),
+            StorageImportError::UnknownUnit(s) => write!(f, "unknown unit {s}"),
+            StorageImportError::NotAQuantityUnit(s) => write!(f, "{s} is not a quantity unit"),
+            StorageImportError::InvalidDate(s) => {
+                write!(f, "invalid date {s}, expected YYYY-MM-DD")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageImportError {}
+
+// Header of the CSV column of each storage field, None to ignore the field.
+// The defaults match the headers written by storage::export_storages,
+// where STORE_LOCATION is the store location full path.
+#[derive(Debug, Clone)]
+pub struct StorageImportMapping {
+    // Product matching, at least one column must be filled on each line.
+    pub product_cas_number: Option<String>,
+    pub product_name: Option<String>,
+    pub product_specificity: Option<String>,
+    // Barecode kept by the storage. When existing, the storage product must match.
+    // Generated when empty.
+    pub barecode: Option<String>,
+    // Full path of the store location, such as "Building A/Room 12/Cabinet [FL]".
+    pub store_location: String,
+    // Entity name, to distinguish store locations with the same full path.
+    pub entity: Option<String>,
+    // Quantity, optionally followed by its unit: "500 mL".
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub supplier: Option<String>,
+    pub batch_number: Option<String>,
+    pub comment: Option<String>,
+    // Dates as YYYY-MM-DD.
+    pub entry_date: Option<String>,
+    pub expiration_date: Option<String>,
+    // CSV field delimiter.
+    pub delimiter: u8,
+}
+
+impl Default for StorageImportMapping {
+    fn default() -> Self {
+        Self {
+            product_cas_number: Some("CAS_NUMBER".to_string()),
+            product_name: Some("PRODUCT_NAME".to_string()),
+            product_specificity: Some("PRODUCT_SPECIFICITY".to_string()),
+            barecode: Some("BARECODE".to_string()),
+            store_location: "STORE_LOCATION".to_string(),
+            entity: Some("ENTITY".to_string()),
+            quantity: Some("QUANTITY".to_string()),
+            unit: Some("UNIT".to_string()),
+            supplier: Some("SUPPLIER".to_string()),
+            batch_number: Some("BATCH_NUMBER".to_string()),
+            comment: Some("COMMENT".to_string()),
+            entry_date: Some("ENTRY_DATE".to_string()),
+            expiration_date: Some("EXPIRATION_DATE".to_string()),
+            delimiter: b',',
+        }
+    }
+}
+
+// Positions of the mapped columns in the CSV header.
+#[derive(Debug, Default)]
+struct StorageImportColumns {
+    product_cas_number: Option<usize>,
+    product_name: Option<usize>,
+    product_specificity: Option<usize>,
+    barecode: Option<usize>,
+    store_location: usize,
+    entity: Option<usize>,
+    quantity: Option<usize>,
+    unit: Option<usize>,
+    supplier: Option<usize>,
+    batch_number: Option<usize>,
+    comment: Option<usize>,
+    entry_date: Option<usize>,
+    expiration_date: Option<usize>,
+}
+
+impl StorageImportColumns {
+    fn new(
+        mapping: &StorageImportMapping,
+        headers: &StringRecord,
+    ) -> Result<Self, StorageImportError> {
+        let optional_position = |maybe_header: &Option<String>| {
+            maybe_header
+                .as_deref()
+                .and_then(|header| header_position(headers, header))
+        };
+
+        let store_location = header_position(headers, &mapping.store_location)
+            .ok_or_else(|| StorageImportError::MissingColumn(mapping.store_location.clone()))?;
+
+        Ok(Self {
+            product_cas_number: optional_position(&mapping.product_cas_number),
+            product_name: optional_position(&mapping.product_name),
+            product_specificity: optional_position(&mapping.product_specificity),
+            barecode: optional_position(&mapping.barecode),
+            store_location,
+            entity: optional_position(&mapping.entity),
+            quantity: optional_position(&mapping.quantity),
+            unit: optional_position(&mapping.unit),
+            supplier: optional_position(&mapping.supplier),
+            batch_number: optional_position(&mapping.batch_number),
+            comment: optional_position(&mapping.comment),
+            entry_date: optional_position(&mapping.entry_date),
+            expiration_date: optional_position(&mapping.expiration_date),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageImportLineError {
+    // Line number in the CSV file, the header being line 1.
+    pub line: u64,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct StorageImportReport {
+    // Number of lines without errors.
+    pub valid: usize,
+    // Ids of the created storages, empty on dry run.
+    pub created: Vec<u64>,
+    // Unresolved lines with the reasons.
+    pub errors: Vec<StorageImportLineError>,
+    pub dry_run: bool,
+}
+
+// Return the id of the only product matching the non empty criteria.
+// A known barecode restricts the match to the products stored with it,
+// an unknown one is ignored when a CAS number or a name is given.
+fn find_product(
+    db_connection: &Connection,
+    cas_number: Option<&str>,
+    name: Option<&str>,
+    specificity: Option<&str>,
+    barecode: Option<&str>,
+) -> Result<Result<u64, StorageImportError>, Box<dyn std::error::Error + Send + Sync>> {
+    if cas_number.is_none() && name.is_none() && barecode.is_none() {
+        return Ok(Err(StorageImportError::MissingProduct));
+    }
+
+    let sql = "SELECT DISTINCT product.product_id FROM product
+        JOIN name ON product.name = name.name_id
+        LEFT JOIN cas_number ON product.cas_number = cas_number.cas_number_id
+        WHERE (?1 IS NULL OR cas_number.cas_number_label = ?1)
+        AND (?2 IS NULL OR name.name_label = ?2 COLLATE UNICODE_NOCASE
+            OR product.product_id IN (SELECT productsynonyms_product_id FROM productsynonyms
+                JOIN name AS synonym ON productsynonyms.productsynonyms_name_id = synonym.name_id
+                WHERE synonym.name_label = ?2 COLLATE UNICODE_NOCASE))
+        AND (?3 IS NULL OR product.product_specificity = ?3 COLLATE UNICODE_NOCASE)
+        AND (?4 IS NULL
+            OR ((?1 IS NOT NULL OR ?2 IS NOT NULL)
+                AND NOT EXISTS (SELECT 1 FROM storage WHERE storage.storage_barecode = ?4))
+            OR product.product_id IN (SELECT storage.product FROM storage WHERE storage.storage_barecode = ?4))
+        ORDER BY product.product_id
+        LIMIT 2";
+
+    debug!("sql: {sql}");
+
+    let product_ids = db_connection
+        .prepare(sql)?
+        .query_map((cas_number, name, specificity, barecode), |row| {
+            row.get::<_, u64>(0)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let criteria = [cas_number, name, specificity, barecode]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(match product_ids.as_slice() {
+        [product_id] => Ok(*product_id),
+        [] => Err(StorageImportError::ProductNotFound(criteria)),
+        _ => Err(StorageImportError::SeveralProducts(criteria)),
+    })
+}
+
+// Return the id of the only store location with the full path,
+// in the entity if given.
+fn find_store_location(
+    db_connection: &Connection,
+    full_path: &str,
+    entity_name: Option<&str>,
+) -> Result<Result<u64, StorageImportError>, Box<dyn std::error::Error + Send + Sync>> {
+    let sql = "SELECT store_location.store_location_id, store_location.store_location_can_store FROM store_location
+        JOIN entity ON store_location.entity = entity.entity_id
+        WHERE store_location.store_location_full_path = ?1 COLLATE UNICODE_NOCASE
+        AND (?2 IS NULL OR entity.entity_name = ?2 COLLATE UNICODE_NOCASE)
+        LIMIT 2";
+
+    debug!("sql: {sql}");
+
+    let store_locations = db_connection
+        .prepare(sql)?
+        .query_map((full_path, entity_name), |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, bool>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(match store_locations.as_slice() {
+        [(store_location_id, true)] => Ok(*store_location_id),
+        [(_, false)] => Err(StorageImportError::StoreLocationCanNotStore(
+            full_path.to_string(),
+        )),
+        [] => Err(StorageImportError::StoreLocationNotFound(
+            full_path.to_string(),
+        )),
+        _ => Err(StorageImportError::SeveralStoreLocations(
+            full_path.to_string(),
+        )),
+    })
+}
+
+// Split a quantity such as "500 mL" or "2,5kg" into its value and unit.
+fn parse_quantity(text: &str) -> Result<(f64, Option<&str>), StorageImportError> {
+    let (value, unit) = text.split_at(
+        text.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .unwrap_or(text.len()),
+    );
+
+    let quantity = value
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .map_err(|_| StorageImportError::InvalidQuantity(text.to_string()))?;
+
+    let unit = unit.trim();
+
+    Ok((quantity, (!unit.is_empty()).then_some(unit)))
+}
+
+fn parse_date(text: &str) -> Result<DateTime<Utc>, StorageImportError> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date_time| date_time.and_utc())
+        .ok_or_else(|| StorageImportError::InvalidDate(text.to_string()))
+}
+
+// Build the storage of a CSV line, resolving its product, store location
+// and unit. Return the storage with every error of the line.
+fn storage_from_record(
+    db_connection: &Connection,
+    record: &StringRecord,
+    columns: &StorageImportColumns,
+    person_id: u64,
+) -> Result<(StorageStruct, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut errors: Vec<String> = Vec::new();
+
+    let barecode = field(record, columns.barecode);
+
+    let mut storage = StorageStruct {
+        person: PersonStruct {
+            person_id: Some(person_id),
+            ..Default::default()
+        },
+        storage_barecode: barecode.map(ToString::to_string),
+        storage_batch_number: field(record, columns.batch_number).map(ToString::to_string),
+        storage_comment: field(record, columns.comment).map(ToString::to_string),
+        ..Default::default()
+    };
+
+    // Product.
+    match find_product(
+        db_connection,
+        field(record, columns.product_cas_number),
+        field(record, columns.product_name),
+        field(record, columns.product_specificity),
+        barecode,
+    )? {
+        Ok(product_id) => {
+            storage.product = ProductStruct {
+                product_id: Some(product_id),
+                ..Default::default()
+            };
+        }
+        Err(err) => errors.push(err.to_string()),
+    }
+
+    // Store location.
+    if let Some(full_path) = field(record, Some(columns.store_location)) {
+        match find_store_location(db_connection, full_path, field(record, columns.entity))? {
+            Ok(store_location_id) => {
+                storage.store_location = StoreLocationStruct {
+                    store_location_id: Some(store_location_id),
+                    ..Default::default()
+                };
+            }
+            Err(err) => errors.push(err.to_string()),
+        }
+    } else {
+        errors.push(StorageImportError::MissingStoreLocation.to_string());
+    }
+
+    // Quantity and unit.
+    if let Some(quantity_text) = field(record, columns.quantity) {
+        match parse_quantity(quantity_text) {
+            Ok((quantity, maybe_unit_label)) => {
+                storage.storage_quantity = Some(quantity);
+
+                if let Some(unit_label) = maybe_unit_label.or(field(record, columns.unit)) {
+                    match unit::parse(db_connection, unit_label)? {
+                        Some(unit) if unit.unit_type == UnitType::Quantity => {
+                            storage.unit_quantity = Some(UnitStruct {
+                                unit_id: unit.unit_id,
+                                unit_label: unit.unit_label,
+                                ..Default::default()
+                            });
+                        }
+                        Some(_) => errors.push(
+                            StorageImportError::NotAQuantityUnit(unit_label.to_string())
+                                .to_string(),
+                        ),
+                        None => errors.push(
+                            StorageImportError::UnknownUnit(unit_label.to_string()).to_string(),
+                        ),
+                    }
+                }
+            }
+            Err(err) => errors.push(err.to_string()),
+        }
+    }
+
+    // Supplier, created by create_update_storage if missing.
+    if let Some(supplier_label) = field(record, columns.supplier) {
+        storage.supplier = Some(SupplierStruct {
+            supplier_id: parse(&SupplierStruct::default(), db_connection, supplier_label)?
+                .and_then(|supplier| supplier.get_id()),
+            supplier_label: supplier_label.to_string(),
+            ..Default::default()
+        });
+    }
+
+    // Dates.
+    if let Some(entry_date_text) = field(record, columns.entry_date) {
+        match parse_date(entry_date_text) {
+            Ok(entry_date) => storage.storage_entry_date = Some(entry_date),
+            Err(err) => errors.push(err.to_string()),
+        }
+    }
+
+    if let Some(expiration_date_text) = field(record, columns.expiration_date) {
+        match parse_date(expiration_date_text) {
+            Ok(expiration_date) => storage.storage_expiration_date = Some(expiration_date),
+            Err(err) => errors.push(err.to_string()),
+        }
+    }
+
+    Ok((storage, errors))
+}
+
+// Import the storages of a CSV file with a header line.
+// Every line is checked and its errors reported, unresolved lines are skipped.
+// A storage is created for each valid line, with a generated barecode when
+// the line has none, on dry run the whole import is then rolled back.
+pub fn import_storages(
+    db_connection: &mut Connection,
+    reader: impl Read,
+    mapping: &StorageImportMapping,
+    person_id: u64,
+    dry_run: bool,
+) -> Result<StorageImportReport, Box<dyn std::error::Error + Send + Sync>> {
+    info!("importing storages, dry run: {dry_run}");
+
+    let mut report = StorageImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .delimiter(mapping.delimiter)
+        .from_reader(reader);
+
+    let columns = StorageImportColumns::new(mapping, csv_reader.headers()?)?;
+
+    debug!("columns: {columns:?}");
+
+    // The lines are written even on dry run, so that the errors raised when
+    // writing them are reported, and the transaction is rolled back.
+    let db_transaction = db_connection.transaction()?;
+
+    for mayerr_record in csv_reader.records() {
+        let record = match mayerr_record {
+            Ok(record) => record,
+            Err(err) => {
+                report.errors.push(StorageImportLineError {
+                    line: err.position().map_or(0, csv::Position::line),
+                    error: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, csv::Position::line);
+
+        let (storage, errors) = storage_from_record(&db_transaction, &record, &columns, person_id)?;
+
+        if !errors.is_empty() {
+            for error in errors {
+                warn!("line {line}: {error}");
+                report.errors.push(StorageImportLineError { line, error });
+            }
+            continue;
+        }
+
+        report.valid += 1;
+
+        match in_savepoint(&db_transaction, || {
+            create_update_storage_in_transaction(&db_transaction, storage, 1, false)
+        }) {
+            Ok(storage_ids) => {
+                if !dry_run {
+                    report.created.extend(storage_ids);
+                }
+            }
+            Err(err) => {
+                warn!("line {line}: {err}");
+
+                report.valid -= 1;
+                report.errors.push(StorageImportLineError {
+                    line,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    if dry_run {
+        db_transaction.rollback()?;
+    } else {
+        db_transaction.commit()?;
+    }
+
+    info!(
+        "valid: {} created: {} errors: {}",
+        report.valid,
+        report.created.len(),
+        report.errors.len()
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "storageimport_tests.rs"]
+mod storageimport_tests;
@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::{init::populate_db_with_base_data, storageimport::*, test_utils::count};
+
+    fn init_test_storageimport() -> Connection {
+        let mut db_connection = crate::test_utils::init_test();
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        db_connection
+            .execute_batch(
+                "INSERT INTO entity (entity_id, entity_name) VALUES (1, 'lab');
+                INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES
+                    (1, 'all', 'all', NULL);
+                INSERT INTO store_location (store_location_id, store_location_name, store_location_can_store, store_location_full_path, entity, store_location) VALUES
+                    (1, 'lab', 0, 'lab', 1, NULL),
+                    (2, 'cabinet [CAB]', 1, 'lab/cabinet [CAB]', 1, 1);
+                INSERT INTO name (name_id, name_label) VALUES (1, 'ETHANOL'), (2, 'ALCOOL ETHYLIQUE'), (3, 'BENZENE');
+                INSERT INTO cas_number (cas_number_id, cas_number_label) VALUES (1, '64-17-5'), (2, '71-43-2');
+                INSERT INTO product (product_id, product_type, name, cas_number, product_specificity) VALUES
+                    (1, 'chem', 1, 1, NULL), (2, 'chem', 3, 2, NULL), (3, 'chem', 1, 1, 'absolute');
+                INSERT INTO productsynonyms (productsynonyms_product_id, productsynonyms_name_id) VALUES (1, 2);
+                INSERT INTO storage (storage_id, storage_barecode, product, store_location, storage) VALUES
+                    (1, 'CAB1.1', 1, 2, NULL);",
+            )
+            .unwrap();
+
+        db_connection
+    }
+
+    const CSV: &str = "CAS_NUMBER,PRODUCT_NAME,PRODUCT_SPECIFICITY,BARECODE,QUANTITY,UNIT,SUPPLIER,STORE_LOCATION,ENTRY_DATE
+,benzene,,,500 mL,,Abcam,lab/cabinet [CAB],2024-01-15
+,,,CAB1.1,1,kg,,Lab/Cabinet [CAB],
+64-17-5,,,,2,L,,lab/cabinet [CAB],
+71-43-2,,,,abc,,,lab/shelf,2024-13-01
+,toluene,,,1,mM,,lab,
+,alcool ethylique,,,,,,lab/cabinet [CAB],
+";
+
+    #[test]
+    fn test_import_storages_dry_run() {
+        let mut db_connection = init_test_storageimport();
+
+        let report = import_storages(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &StorageImportMapping::default(),
+            1,
+            true,
+        )
+        .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.valid, 3);
+        assert!(report.created.is_empty());
+
+        let errors: Vec<(u64, &str)> = report
+            .errors
+            .iter()
+            .map(|error| (error.line, error.error.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (4, "several products match 64-17-5"),
+                (5, "invalid quantity abc"),
+                (5, "store location not found for lab/shelf"),
+                (5, "invalid date 2024-13-01, expected YYYY-MM-DD"),
+                (6, "product not found for toluene"),
+                (6, "store location lab can not store"),
+                (6, "mM is not a quantity unit"),
+            ]
+        );
+
+        // Nothing written.
+        assert_eq!(count(&db_connection, "SELECT COUNT(*) FROM storage"), 1);
+    }
+
+    #[test]
+    fn test_import_storages_write_errors() {
+        let mut db_connection = init_test_storageimport();
+
+        // The unknown person is only detected when writing the storages.
+        let report = import_storages(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &StorageImportMapping::default(),
+            42,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(report.valid, 0);
+        assert!(report.created.is_empty());
+        assert_eq!(report.errors.len(), 10);
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .filter(|error| [2, 3, 7].contains(&error.line))
+                .count(),
+            3
+        );
+
+        assert_eq!(count(&db_connection, "SELECT COUNT(*) FROM storage"), 1);
+    }
+
+    #[test]
+    fn test_import_storages() {
+        let mut db_connection = init_test_storageimport();
+
+        let report = import_storages(
+            &mut db_connection,
+            CSV.as_bytes(),
+            &StorageImportMapping::default(),
+            1,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(report.valid, 3);
+        assert_eq!(report.created.len(), 3);
+        assert_eq!(report.errors.len(), 7);
+
+        let storages: Vec<(u64, String, Option<f64>, Option<String>)> = db_connection
+            .prepare(
+                "SELECT product, storage_barecode, storage_quantity, unit.unit_label FROM storage
+                LEFT JOIN unit ON storage.unit_quantity = unit.unit_id
+                WHERE storage.storage IS NULL ORDER BY storage_id",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            storages,
+            vec![
+                (1, "CAB1.1".to_string(), None, None),
+                // Generated barecode.
+                (2, "CAB2.1".to_string(), Some(500.0), Some("mL".to_string())),
+                // Kept barecode, product matched by the barecode.
+                (1, "CAB1.1".to_string(), Some(1.0), Some("kg".to_string())),
+                // Product matched by a synonym.
+                (1, "CAB1.2".to_string(), None, None),
+            ]
+        );
+
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM storage JOIN supplier ON storage.supplier = supplier.supplier_id
+                WHERE supplier_label = 'Abcam'"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_find_product() {
+        let db_connection = init_test_storageimport();
+
+        assert_eq!(
+            find_product(&db_connection, None, None, None, Some("CAB1.1")).unwrap(),
+            Ok(1)
+        );
+        assert_eq!(
+            find_product(&db_connection, None, Some("benzene"), None, Some("CAB9.9")).unwrap(),
+            Ok(2)
+        );
+        // An unknown barecode alone does not match every product.
+        assert_eq!(
+            find_product(&db_connection, None, None, None, Some("CAB9.9")).unwrap(),
+            Err(StorageImportError::ProductNotFound("CAB9.9".to_string()))
+        );
+        assert_eq!(
+            find_product(&db_connection, None, None, None, None).unwrap(),
+            Err(StorageImportError::MissingProduct)
+        );
+    }
+
+    #[test]
+    fn test_import_storages_missing_column() {
+        let mut db_connection = init_test_storageimport();
+
+        assert_eq!(
+            import_storages(
+                &mut db_connection,
+                "PRODUCT_NAME,QUANTITY\nbenzene,1\n".as_bytes(),
+                &StorageImportMapping::default(),
+                1,
+                false,
+            )
+            .unwrap_err()
+            .downcast_ref::<StorageImportError>(),
+            Some(&StorageImportError::MissingColumn(
+                "STORE_LOCATION".to_string()
+            ))
+        );
+    }
+}
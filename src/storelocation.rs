@@ -12,6 +12,7 @@ use sea_query::{
 };
 use sea_query_rusqlite::{RusqliteBinder, RusqliteValues};
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Iden)]
@@ -258,7 +259,7 @@ pub fn get_store_locations(
         // Apply sorting.
         .order_by_expr(
             Expr::cust_with_expr(
-                "? COLLATE NOCASE",
+                "? COLLATE UNICODE_NOCASE",
                 Expr::col((StoreLocation::Table, StoreLocation::StoreLocationName)),
             ),
             order,
@@ -584,6 +585,128 @@ pub fn delete_store_location(
     Ok(())
 }
 
+// A store location whose stored full path differs from the one
+// computed from its ancestors.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct StaleFullPath {
+    pub store_location_id: u64,
+    pub current_full_path: Option<String>,
+    pub expected_full_path: String,
+}
+
+// Compute the expected full path of every store location from its ancestors.
+// Return the store locations with a stale full path and the ids of the store
+// locations whose ancestors form a cycle (their full path can not be computed).
+pub(crate) fn compute_store_location_full_paths(
+    db_connection: &Connection,
+) -> Result<(Vec<StaleFullPath>, Vec<u64>), Box<dyn std::error::Error + Send + Sync>> {
+    let (select_sql, select_values) = Query::select()
+        .columns([
+            StoreLocation::StoreLocationId,
+            StoreLocation::StoreLocationName,
+            StoreLocation::StoreLocationFullPath,
+            StoreLocation::StoreLocation,
+        ])
+        .from(StoreLocation::Table)
+        .order_by(StoreLocation::StoreLocationId, Order::Asc)
+        .build_rusqlite(SqliteQueryBuilder);
+
+    debug!("select_sql: {}", select_sql.clone().as_str());
+    debug!("select_values: {select_values:?}");
+
+    let mut stmt = db_connection.prepare(select_sql.as_str())?;
+    let rows = stmt.query_map(&*select_values.as_params(), |row| {
+        Ok((
+            row.get::<_, u64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<u64>>(3)?,
+        ))
+    })?;
+
+    // store_location_id -> (name, current full path, parent id)
+    let mut store_locations: HashMap<u64, (String, Option<String>, Option<u64>)> = HashMap::new();
+    let mut store_location_ids: Vec<u64> = Vec::new();
+    for row in rows {
+        let (store_location_id, name, full_path, parent_id) = row?;
+        store_location_ids.push(store_location_id);
+        store_locations.insert(store_location_id, (name, full_path, parent_id));
+    }
+
+    let mut stale_full_paths: Vec<StaleFullPath> = Vec::new();
+    let mut cycles: Vec<u64> = Vec::new();
+
+    for store_location_id in store_location_ids {
+        let mut names: Vec<&str> = Vec::new();
+        let mut visited: HashSet<u64> = HashSet::new();
+        let mut current_id = Some(store_location_id);
+        let mut has_cycle = false;
+
+        // Walk up to the root.
+        while let Some(id) = current_id {
+            if !visited.insert(id) {
+                has_cycle = true;
+                break;
+            }
+
+            // A missing parent ends the walk, as in populate_store_location_full_path.
+            let Some((name, _, parent_id)) = store_locations.get(&id) else {
+                break;
+            };
+
+            names.push(name);
+            current_id = *parent_id;
+        }
+
+        if has_cycle {
+            cycles.push(store_location_id);
+            continue;
+        }
+
+        names.reverse();
+        let expected_full_path = names.join("/");
+        let current_full_path = store_locations[&store_location_id].1.clone();
+
+        if current_full_path.as_deref() != Some(expected_full_path.as_str()) {
+            stale_full_paths.push(StaleFullPath {
+                store_location_id,
+                current_full_path,
+                expected_full_path,
+            });
+        }
+    }
+
+    Ok((stale_full_paths, cycles))
+}
+
+// Update the stale store location full paths.
+// Return the number of updated store locations.
+pub(crate) fn update_store_location_full_paths(
+    db_connection: &Connection,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let (stale_full_paths, _cycles) = compute_store_location_full_paths(db_connection)?;
+
+    for stale_full_path in &stale_full_paths {
+        let (sql_query, sql_values) = Query::update()
+            .table(StoreLocation::Table)
+            .values([(
+                StoreLocation::StoreLocationFullPath,
+                stale_full_path.expected_full_path.clone().into(),
+            )])
+            .and_where(
+                Expr::col(StoreLocation::StoreLocationId).eq(stale_full_path.store_location_id),
+            )
+            .build_rusqlite(SqliteQueryBuilder);
+
+        debug!("sql_query: {}", sql_query.clone().as_str());
+        debug!("sql_values: {sql_values:?}");
+
+        _ = db_connection.execute(sql_query.as_str(), &*sql_values.as_params())?;
+    }
+
+    Ok(stale_full_paths.len())
+}
+
 #[cfg(test)]
 #[path = "storelocation_tests.rs"]
 mod storelocation_tests;
@@ -497,4 +497,42 @@ mod tests {
             assert!(location.store_location_name.contains("Lab"));
         }
     }
+
+    #[test]
+    fn test_update_store_location_full_paths() {
+        let db = init_test_storelocation();
+
+        // Fixture store locations have no full path.
+        let (stale_full_paths, cycles) = compute_store_location_full_paths(&db).unwrap();
+        assert_eq!(stale_full_paths.len(), 10);
+        assert!(cycles.is_empty());
+
+        let updated = update_store_location_full_paths(&db).unwrap();
+        assert_eq!(updated, 10);
+
+        let full_path: String = db
+            .query_row(
+                "SELECT store_location_full_path FROM store_location WHERE store_location_id = 7",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(full_path, "Main Storage/Cold Storage/Refrigerated Storage");
+
+        // Nothing left to update.
+        assert_eq!(update_store_location_full_paths(&db).unwrap(), 0);
+
+        // Create a cycle: 1 -> 7 -> 6 -> 1.
+        db.execute(
+            "UPDATE store_location SET store_location = 7 WHERE store_location_id = 1",
+            [],
+        )
+        .unwrap();
+
+        let (_, cycles) = compute_store_location_full_paths(&db).unwrap();
+        assert!(cycles.contains(&1));
+        assert!(cycles.contains(&6));
+        assert!(cycles.contains(&7));
+        assert!(cycles.contains(&10));
+    }
 }
@@ -59,7 +59,10 @@ pub fn get_suppliers(
     let (select_sql, select_values) = expression
         .columns([Supplier::SupplierId, Supplier::SupplierLabel])
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(Supplier::SupplierLabel)),
+            Expr::cust_with_expr(
+                "? COLLATE UNICODE_NOCASE",
+                Expr::col(Supplier::SupplierLabel),
+            ),
             Order::Asc,
         )
         .conditions(
@@ -94,7 +94,10 @@ pub fn get_supplier_refs(
             Alias::new("supplier.supplier_label"),
         )
         .order_by_expr(
-            Expr::cust_with_expr("? COLLATE NOCASE", Expr::col(SupplierRef::SupplierRefLabel)),
+            Expr::cust_with_expr(
+                "? COLLATE UNICODE_NOCASE",
+                Expr::col(SupplierRef::SupplierRefLabel),
+            ),
             Order::Asc,
         )
         .conditions(
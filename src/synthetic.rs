@@ -0,0 +1,585 @@
+use log::{debug, info};
+use rusqlite::{Connection, Transaction};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Synthetic data generator for performance testing and training.
+// The database must have been populated with populate_db_with_base_data.
+// The same seed on the same database always generates the same data.
+
+// Dates are generated from this timestamp (2024-01-01) to keep the output deterministic.
+const BASE_TIMESTAMP: i64 = 1_704_067_200;
+const ONE_YEAR: u64 = 365 * 24 * 3600;
+
+const NAME_PREFIXES: [&str; 12] = [
+    "methyl", "ethyl", "propyl", "butyl", "chloro", "bromo", "nitro", "amino", "hydroxy", "phenyl",
+    "benzyl", "acetyl",
+];
+const NAME_SUFFIXES: [&str; 10] = [
+    "benzene", "acetate", "alcohol", "amine", "chloride", "sulfate", "oxide", "acid", "ether",
+    "ketone",
+];
+const BIO_NAMES: [&str; 8] = [
+    "antibody",
+    "growth factor",
+    "medium",
+    "serum",
+    "enzyme",
+    "primer",
+    "plasmid",
+    "kit",
+];
+const CONS_NAMES: [&str; 6] = ["tube", "flask", "plate", "pipette tip", "vial", "glove"];
+const STORE_LOCATION_NAMES: [&str; 6] = ["Cabinet", "Shelf", "Drawer", "Fridge", "Freezer", "Box"];
+
+// SplitMix64 pseudo random number generator.
+// https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Return a number in [0, n[, n must not be 0.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    // Return true with the given percentage.
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        let index = self.below(u64::try_from(items.len()).unwrap_or(u64::MAX));
+        &items[usize::try_from(index).unwrap_or_default()]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntheticOptions {
+    pub seed: u64,
+    pub nb_entities: usize,
+    pub nb_store_locations_per_entity: usize,
+    pub store_location_max_depth: usize,
+    pub nb_people_per_entity: usize,
+    pub nb_products: usize,
+    pub nb_storages: usize,
+    // Each storage gets between 0 and this number of history entries.
+    pub max_history_per_storage: usize,
+}
+
+impl Default for SyntheticOptions {
+    fn default() -> Self {
+        SyntheticOptions {
+            seed: 42,
+            nb_entities: 5,
+            nb_store_locations_per_entity: 10,
+            store_location_max_depth: 3,
+            nb_people_per_entity: 10,
+            nb_products: 1000,
+            nb_storages: 10000,
+            max_history_per_storage: 2,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyntheticReport {
+    pub entities: usize,
+    pub store_locations: usize,
+    pub people: usize,
+    pub products: usize,
+    pub storages: usize,
+    pub history_storages: usize,
+}
+
+struct SyntheticStoreLocation {
+    store_location_id: u64,
+    can_store: bool,
+    barecode_prefix: String,
+}
+
+struct SyntheticEntity {
+    entity_id: u64,
+    people: Vec<u64>,
+    store_locations: Vec<SyntheticStoreLocation>,
+}
+
+fn select_ids(
+    db_transaction: &Transaction,
+    sql: &str,
+) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(db_transaction
+        .prepare(sql)?
+        .query_map([], |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+// Two uppercase letters code from an index: AA, AB... ZZ, then AAA...
+fn store_location_code(mut index: usize) -> String {
+    let mut code = Vec::new();
+    loop {
+        code.push(b'A' + u8::try_from(index % 26).unwrap_or_default());
+        index /= 26;
+        if code.len() >= 2 && index == 0 {
+            break;
+        }
+    }
+    code.reverse();
+    String::from_utf8(code).unwrap_or_default()
+}
+
+// Generate a valid CAS number from a random number.
+fn cas_number(rng: &mut SplitMix64) -> String {
+    let digits = format!("{}", 10_000 + rng.below(9_990_000));
+    let (first, second) = digits.split_at(digits.len() - 2);
+
+    let checksum: u32 = digits
+        .chars()
+        .rev()
+        .enumerate()
+        .map(|(i, c)| {
+            (u32::try_from(i).unwrap_or_default() + 1) * c.to_digit(10).unwrap_or_default()
+        })
+        .sum();
+
+    format!("{first}-{second}-{}", checksum % 10)
+}
+
+fn timestamp(rng: &mut SplitMix64) -> i64 {
+    BASE_TIMESTAMP - i64::try_from(rng.below(ONE_YEAR)).unwrap_or_default()
+}
+
+fn generate_entities(
+    db_transaction: &Transaction,
+    rng: &mut SplitMix64,
+    options: &SyntheticOptions,
+    report: &mut SyntheticReport,
+) -> Result<Vec<SyntheticEntity>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut entities = Vec::with_capacity(options.nb_entities);
+    let mut nb_codes: usize = 0;
+
+    let mut insert_entity = db_transaction
+        .prepare_cached("INSERT INTO entity (entity_name, entity_description) VALUES (?1, ?2)")?;
+    let mut insert_store_location = db_transaction.prepare_cached(
+        "INSERT INTO store_location (store_location_name, store_location_can_store, store_location_full_path, entity, store_location)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut insert_person =
+        db_transaction.prepare_cached("INSERT INTO person (person_email) VALUES (?1)")?;
+    let mut insert_membership = db_transaction.prepare_cached(
+        "INSERT INTO personentities (personentities_person_id, personentities_entity_id) VALUES (?1, ?2)",
+    )?;
+    let mut insert_manager = db_transaction.prepare_cached(
+        "INSERT INTO entitypeople (entitypeople_entity_id, entitypeople_person_id) VALUES (?1, ?2)",
+    )?;
+    let mut insert_permission = db_transaction.prepare_cached(
+        "INSERT INTO permission (person, permission_name, permission_item, permission_entity) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+
+    for e in 0..options.nb_entities {
+        insert_entity.execute((
+            format!("synthetic {} entity {e}", options.seed),
+            format!("Synthetic entity {e}"),
+        ))?;
+        let entity_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+
+        // Store locations with their depth and full path.
+        let mut store_locations: Vec<(SyntheticStoreLocation, usize, String)> = Vec::new();
+        for s in 0..options.nb_store_locations_per_entity {
+            let parents: Vec<usize> = store_locations
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, depth, _))| *depth < options.store_location_max_depth)
+                .map(|(i, _)| i)
+                .collect();
+
+            // The first store location is always a root, then 1 out of 5.
+            let parent = if s == 0 || parents.is_empty() || rng.chance(20) {
+                None
+            } else {
+                Some(*rng.choose(&parents))
+            };
+
+            let (name, depth, full_path, barecode_prefix, parent_id, can_store) =
+                if let Some(parent) = parent {
+                    let (parent_store_location, parent_depth, parent_full_path) =
+                        &store_locations[parent];
+                    let name = format!("{} {s}", rng.choose(&STORE_LOCATION_NAMES));
+                    (
+                        name.clone(),
+                        parent_depth + 1,
+                        format!("{parent_full_path}/{name}"),
+                        parent_store_location.barecode_prefix.clone(),
+                        Some(parent_store_location.store_location_id),
+                        true,
+                    )
+                } else {
+                    let code = store_location_code(nb_codes);
+                    nb_codes += 1;
+                    let name = format!("[{code}]Room {s}");
+                    (name.clone(), 1, name, code, None, false)
+                };
+
+            insert_store_location.execute((&name, can_store, &full_path, entity_id, parent_id))?;
+            let store_location_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+
+            store_locations.push((
+                SyntheticStoreLocation {
+                    store_location_id,
+                    can_store,
+                    barecode_prefix,
+                },
+                depth,
+                full_path,
+            ));
+            report.store_locations += 1;
+        }
+
+        // Roots can not store: without children, the last store location is
+        // made able to store, so that the entity can get storages.
+        if !store_locations
+            .iter()
+            .any(|(store_location, _, _)| store_location.can_store)
+            && let Some((store_location, _, _)) = store_locations.last_mut()
+        {
+            db_transaction.execute(
+                "UPDATE store_location SET store_location_can_store = 1 WHERE store_location_id = ?1",
+                [store_location.store_location_id],
+            )?;
+            store_location.can_store = true;
+        }
+
+        // People, the first one is the entity manager.
+        let mut people = Vec::with_capacity(options.nb_people_per_entity);
+        for p in 0..options.nb_people_per_entity {
+            insert_person.execute([format!(
+                "synthetic_{}_person_{e}_{p}@example.com",
+                options.seed
+            )])?;
+            let person_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+
+            insert_membership.execute((person_id, entity_id))?;
+            insert_permission.execute((person_id, "r", "products", None::<u64>))?;
+
+            if p == 0 {
+                insert_manager.execute((entity_id, person_id))?;
+                insert_permission.execute((person_id, "all", "all", Some(entity_id)))?;
+            } else {
+                insert_permission.execute((person_id, "w", "storages", Some(entity_id)))?;
+            }
+
+            people.push(person_id);
+            report.people += 1;
+        }
+
+        entities.push(SyntheticEntity {
+            entity_id,
+            people,
+            store_locations: store_locations.into_iter().map(|(sl, _, _)| sl).collect(),
+        });
+        report.entities += 1;
+    }
+
+    Ok(entities)
+}
+
+// Products are created by one of the generated people, if any.
+fn generate_products(
+    db_transaction: &Transaction,
+    rng: &mut SplitMix64,
+    options: &SyntheticOptions,
+    people: &[u64],
+    report: &mut SyntheticReport,
+) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    let physical_states = select_ids(
+        db_transaction,
+        "SELECT physical_state_id FROM physical_state",
+    )?;
+    let signal_words = select_ids(db_transaction, "SELECT signal_word_id FROM signal_word")?;
+    let categories = select_ids(db_transaction, "SELECT category_id FROM category")?;
+    let producers = select_ids(db_transaction, "SELECT producer_id FROM producer")?;
+    let symbols = select_ids(db_transaction, "SELECT symbol_id FROM symbol")?;
+    let tags = select_ids(db_transaction, "SELECT tag_id FROM tag")?;
+
+    let mut insert_name =
+        db_transaction.prepare_cached("INSERT INTO name (name_label) VALUES (?1)")?;
+    let mut insert_cas_number = db_transaction.prepare_cached(
+        "INSERT INTO cas_number (cas_number_label) VALUES (?1)
+        ON CONFLICT(cas_number_label) DO UPDATE SET cas_number_label = excluded.cas_number_label
+        RETURNING cas_number_id",
+    )?;
+    let mut insert_producer_ref = db_transaction.prepare_cached(
+        "INSERT INTO producer_ref (producer_ref_label, producer) VALUES (?1, ?2)",
+    )?;
+    let mut insert_product = db_transaction.prepare_cached(
+        "INSERT INTO product (product_type, product_specificity, cas_number, physical_state, signal_word, name,
+        producer_ref, category, product_number_per_carton, product_number_per_bag, person)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    let mut insert_symbol = db_transaction.prepare_cached(
+        "INSERT OR IGNORE INTO productsymbols (productsymbols_product_id, productsymbols_symbol_id) VALUES (?1, ?2)",
+    )?;
+    let mut insert_tag = db_transaction.prepare_cached(
+        "INSERT OR IGNORE INTO producttags (producttags_product_id, producttags_tag_id) VALUES (?1, ?2)",
+    )?;
+
+    let mut products = Vec::with_capacity(options.nb_products);
+
+    for p in 0..options.nb_products {
+        // 60% chem, 25% bio, 15% cons.
+        let draw = rng.below(100);
+        let product_type = if draw < 60 {
+            "chem"
+        } else if draw < 85 {
+            "bio"
+        } else {
+            "cons"
+        };
+
+        let label = match product_type {
+            "chem" => format!(
+                "{}{} {p}",
+                rng.choose(&NAME_PREFIXES),
+                rng.choose(&NAME_SUFFIXES)
+            ),
+            "bio" => format!("{} {p}", rng.choose(&BIO_NAMES)),
+            _ => format!("{} {p}", rng.choose(&CONS_NAMES)),
+        };
+        insert_name.execute([format!("synthetic {} {label}", options.seed)])?;
+        let name_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+
+        let mut cas_number_id: Option<u64> = None;
+        let mut physical_state: Option<u64> = None;
+        let mut signal_word: Option<u64> = None;
+        let mut producer_ref: Option<u64> = None;
+        let mut number_per_carton: Option<u64> = None;
+        let mut number_per_bag: Option<u64> = None;
+
+        if product_type == "chem" {
+            cas_number_id =
+                Some(insert_cas_number.query_row([cas_number(rng)], |row| row.get::<_, u64>(0))?);
+            if !physical_states.is_empty() {
+                physical_state = Some(*rng.choose(&physical_states));
+            }
+            if !signal_words.is_empty() && rng.chance(50) {
+                signal_word = Some(*rng.choose(&signal_words));
+            }
+        } else if !producers.is_empty() {
+            insert_producer_ref
+                .execute((format!("REF-{}-{p}", options.seed), *rng.choose(&producers)))?;
+            producer_ref = Some(db_transaction.last_insert_rowid().try_into()?);
+        }
+
+        if product_type == "cons" {
+            number_per_carton = Some(1 + rng.below(100));
+            number_per_bag = Some(1 + rng.below(10));
+        }
+
+        let category = if categories.is_empty() || product_type == "chem" {
+            None
+        } else {
+            Some(*rng.choose(&categories))
+        };
+
+        // Without generated people, person 1 as the column default.
+        let person = if people.is_empty() {
+            1
+        } else {
+            *rng.choose(people)
+        };
+
+        insert_product.execute((
+            product_type,
+            format!("grade {}", rng.below(5)),
+            cas_number_id,
+            physical_state,
+            signal_word,
+            name_id,
+            producer_ref,
+            category,
+            number_per_carton,
+            number_per_bag,
+            person,
+        ))?;
+        let product_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+
+        if product_type == "chem" && !symbols.is_empty() {
+            for _ in 0..rng.below(3) {
+                insert_symbol.execute((product_id, *rng.choose(&symbols)))?;
+            }
+        }
+        if !tags.is_empty() && rng.chance(30) {
+            insert_tag.execute((product_id, *rng.choose(&tags)))?;
+        }
+
+        products.push(product_id);
+        report.products += 1;
+    }
+
+    Ok(products)
+}
+
+fn generate_storages(
+    db_transaction: &Transaction,
+    rng: &mut SplitMix64,
+    options: &SyntheticOptions,
+    entities: &[SyntheticEntity],
+    products: &[u64],
+    report: &mut SyntheticReport,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let quantity_units = select_ids(
+        db_transaction,
+        "SELECT unit_id FROM unit WHERE unit_type = 'quantity'",
+    )?;
+    let suppliers = select_ids(db_transaction, "SELECT supplier_id FROM supplier")?;
+
+    let mut insert_storage = db_transaction.prepare_cached(
+        "INSERT INTO storage (storage_creation_date, storage_modification_date, storage_entry_date,
+        storage_quantity, storage_barecode, storage_batch_number, storage_archive, person, product,
+        store_location, unit_quantity, supplier, storage)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )?;
+
+    // Storage minor barecode number by (product, entity).
+    let mut barecode_minors: HashMap<(u64, u64), u64> = HashMap::new();
+
+    let entities: Vec<(&SyntheticEntity, Vec<&SyntheticStoreLocation>)> = entities
+        .iter()
+        .map(|entity| {
+            (
+                entity,
+                entity
+                    .store_locations
+                    .iter()
+                    .filter(|store_location| store_location.can_store)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(entity, store_locations)| {
+            !store_locations.is_empty() && !entity.people.is_empty()
+        })
+        .collect();
+
+    if entities.is_empty() || products.is_empty() {
+        return Ok(());
+    }
+
+    for _ in 0..options.nb_storages {
+        let (entity, store_locations) = rng.choose(&entities);
+        let store_location = rng.choose(store_locations);
+        let person = *rng.choose(&entity.people);
+        let product = *rng.choose(products);
+
+        let minor = barecode_minors
+            .entry((product, entity.entity_id))
+            .and_modify(|minor| *minor += 1)
+            .or_insert(1);
+        let barecode = format!("{}{product}.{minor}", store_location.barecode_prefix);
+
+        let creation_date = timestamp(rng);
+        let modification_date = creation_date + i64::try_from(rng.below(ONE_YEAR / 4))?;
+        let quantity = f64::from(u32::try_from(1 + rng.below(1000))?);
+        let unit_quantity = if quantity_units.is_empty() {
+            None
+        } else {
+            Some(*rng.choose(&quantity_units))
+        };
+        let supplier = if suppliers.is_empty() {
+            None
+        } else {
+            Some(*rng.choose(&suppliers))
+        };
+        let batch_number = format!("LOT{}", rng.below(1_000_000));
+        let archive = rng.chance(5);
+
+        insert_storage.execute((
+            creation_date,
+            modification_date,
+            creation_date,
+            quantity,
+            &barecode,
+            &batch_number,
+            archive,
+            person,
+            product,
+            store_location.store_location_id,
+            unit_quantity,
+            supplier,
+            None::<u64>,
+        ))?;
+        let storage_id: u64 = db_transaction.last_insert_rowid().try_into()?;
+        report.storages += 1;
+
+        // History entries are former versions of the storage.
+        let nb_history = rng.below(u64::try_from(options.max_history_per_storage)? + 1);
+        for h in 0..nb_history {
+            let history_date = creation_date
+                + i64::try_from(h + 1)? * (modification_date - creation_date)
+                    / i64::try_from(nb_history + 1)?;
+
+            insert_storage.execute((
+                creation_date,
+                history_date,
+                creation_date,
+                quantity + f64::from(u32::try_from(rng.below(100))?),
+                &barecode,
+                &batch_number,
+                false,
+                person,
+                product,
+                store_location.store_location_id,
+                unit_quantity,
+                supplier,
+                Some(storage_id),
+            ))?;
+            report.history_storages += 1;
+        }
+    }
+
+    Ok(())
+}
+
+// Generate synthetic entities, store locations, people, products and
+// storages with history in a single transaction.
+// Storage QR codes are not generated.
+pub fn generate_synthetic_data(
+    db_connection: &mut Connection,
+    options: &SyntheticOptions,
+) -> Result<SyntheticReport, Box<dyn std::error::Error + Send + Sync>> {
+    debug!("options: {options:?}");
+
+    let mut rng = SplitMix64(options.seed);
+    let mut report = SyntheticReport::default();
+
+    let tx = db_connection.transaction()?;
+
+    info!("- generating entities, store locations and people");
+    let entities = generate_entities(&tx, &mut rng, options, &mut report)?;
+
+    info!("- generating products");
+    let people: Vec<u64> = entities
+        .iter()
+        .flat_map(|entity| entity.people.iter().copied())
+        .collect();
+    let products = generate_products(&tx, &mut rng, options, &people, &mut report)?;
+
+    info!("- generating storages");
+    generate_storages(&tx, &mut rng, options, &entities, &products, &mut report)?;
+
+    tx.commit()?;
+
+    debug!("report: {report:?}");
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "synthetic_tests.rs"]
+mod synthetic_tests;
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::init::populate_db_with_base_data;
+    use crate::synthetic::*;
+    use crate::test_utils::count;
+    use rusqlite::Connection;
+
+    fn init_test_synthetic() -> Connection {
+        let mut db_connection = crate::test_utils::init_test();
+
+        populate_db_with_base_data(&mut db_connection).unwrap();
+
+        db_connection
+    }
+
+    fn small_options() -> SyntheticOptions {
+        SyntheticOptions {
+            seed: 7,
+            nb_entities: 2,
+            nb_store_locations_per_entity: 5,
+            store_location_max_depth: 3,
+            nb_people_per_entity: 3,
+            nb_products: 50,
+            nb_storages: 200,
+            max_history_per_storage: 2,
+        }
+    }
+
+    #[test]
+    fn test_generate_synthetic_data() {
+        let mut db_connection = init_test_synthetic();
+
+        let report = generate_synthetic_data(&mut db_connection, &small_options()).unwrap();
+
+        assert_eq!(report.entities, 2);
+        assert_eq!(report.store_locations, 10);
+        assert_eq!(report.people, 6);
+        assert_eq!(report.products, 50);
+        assert_eq!(report.storages, 200);
+
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM storage WHERE storage IS NULL"
+            ),
+            200
+        );
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM storage WHERE storage IS NOT NULL"
+            ),
+            report.history_storages
+        );
+
+        // Storages are only in store locations that can store.
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM storage JOIN store_location ON storage.store_location = store_location.store_location_id
+                WHERE store_location.store_location_can_store = 0"
+            ),
+            0
+        );
+
+        // Barecodes match the storage barecode format.
+        assert_eq!(
+            count(
+                &db_connection,
+                r"SELECT COUNT(*) FROM storage WHERE NOT regexp('^[_a-zA-Z]+[0-9]+\.[0-9]+$', storage_barecode)"
+            ),
+            0
+        );
+
+        // Products are created by the generated people.
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM product JOIN person ON product.person = person.person_id
+                WHERE person_email LIKE 'synthetic_7_person_%'"
+            ),
+            50
+        );
+
+        // Data is consistent.
+        let consistency = crate::init::check_consistency(&mut db_connection, false).unwrap();
+        assert!(consistency.stale_full_paths.is_empty());
+        assert!(consistency.orphan_permissions.is_empty());
+        assert!(consistency.duplicate_barecodes.is_empty());
+    }
+
+    #[test]
+    fn test_generate_synthetic_data_single_store_location() {
+        let mut db_connection = init_test_synthetic();
+
+        let report = generate_synthetic_data(
+            &mut db_connection,
+            &SyntheticOptions {
+                nb_store_locations_per_entity: 1,
+                ..small_options()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.store_locations, 2);
+        assert_eq!(report.storages, 200);
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM store_location WHERE store_location_can_store = 1"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_generate_synthetic_data_no_people() {
+        let mut db_connection = init_test_synthetic();
+
+        let report = generate_synthetic_data(
+            &mut db_connection,
+            &SyntheticOptions {
+                nb_people_per_entity: 0,
+                ..small_options()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.people, 0);
+        assert_eq!(report.products, 50);
+        // Storages need people.
+        assert_eq!(report.storages, 0);
+        assert_eq!(
+            count(
+                &db_connection,
+                "SELECT COUNT(*) FROM product WHERE person = 1"
+            ),
+            50
+        );
+    }
+
+    #[test]
+    fn test_generate_synthetic_data_deterministic() {
+        let dump = |db_connection: &Connection| -> Vec<(String, f64)> {
+            db_connection
+                .prepare(
+                    "SELECT storage_barecode, storage_quantity FROM storage ORDER BY storage_id",
+                )
+                .unwrap()
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        let mut db_connection_1 = init_test_synthetic();
+        generate_synthetic_data(&mut db_connection_1, &small_options()).unwrap();
+
+        let mut db_connection_2 = init_test_synthetic();
+        generate_synthetic_data(&mut db_connection_2, &small_options()).unwrap();
+
+        assert_eq!(dump(&db_connection_1), dump(&db_connection_2));
+    }
+}
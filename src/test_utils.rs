@@ -5,8 +5,10 @@
     clippy::too_many_lines
 )]
 
-use crate::init::{connect_test, create_tables};
+use crate::{connection::ConnectOptions, init::create_tables};
 use rusqlite::Connection;
+#[cfg(test)]
+use std::io::{Cursor, Read};
 use std::sync::Once;
 
 static INIT: Once = Once::new();
@@ -17,13 +19,29 @@ pub fn init_test() -> Connection {
         let _ = env_logger::builder().is_test(true).try_init();
     });
 
-    unsafe {
-        std::env::set_var("SQLITE_EXTENSION_DIR", "src/extensions");
-    };
-
-    let mut db_connection = connect_test();
+    let mut db_connection = ConnectOptions::new().open_in_memory().unwrap();
 
     create_tables(&mut db_connection).unwrap();
 
     db_connection
 }
+
+// Return the integer result of a COUNT query.
+pub fn count(db_connection: &Connection, sql: &str) -> usize {
+    db_connection.query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+// Return the content of a part of an XLSX file, such as xl/workbook.xml.
+#[cfg(test)]
+pub fn xlsx_part(xlsx: &[u8], name: &str) -> String {
+    let mut archive = zip::ZipArchive::new(Cursor::new(xlsx)).unwrap();
+
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+
+    content
+}
@@ -0,0 +1,163 @@
+use rust_xlsxwriter::{ColNum, RowNum, Workbook, Worksheet};
+use std::{
+    fmt::{Display, Formatter},
+    io::Write,
+};
+
+// XLSX writer with a single worksheet, built on rust_xlsxwriter.
+// The rows are kept in memory and the workbook is written on finish.
+
+// Excel limits.
+const SHEET_NAME_MAX_LENGTH: usize = 31;
+const SHEET_NAME_FORBIDDEN_CHARACTERS: [char; 7] = ['[', ']', ':', '*', '?', '/', '\\'];
+const MAX_ROWS: usize = 1_048_576;
+const MAX_COLUMNS: usize = 16_384;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum XlsxError {
+    EmptySheetName,
+    SheetNameTooLong(String),
+    InvalidSheetNameCharacter(String, char),
+    // Excel does not allow a leading or trailing apostrophe.
+    SheetNameApostrophe(String),
+    TooManyRows,
+    TooManyColumns,
+}
+
+impl Display for XlsxError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            XlsxError::EmptySheetName => write!(f, "empty sheet name"),
+            XlsxError::SheetNameTooLong(s) => write!(
+                f,
+                "sheet name {s} longer than {SHEET_NAME_MAX_LENGTH} characters"
+            ),
+            XlsxError::InvalidSheetNameCharacter(s, c) => {
+                write!(f, "invalid character {c} in sheet name {s}")
+            }
+            XlsxError::SheetNameApostrophe(s) => {
+                write!(f, "sheet name {s} starts or ends with an apostrophe")
+            }
+            XlsxError::TooManyRows => write!(f, "more than {MAX_ROWS} rows"),
+            XlsxError::TooManyColumns => write!(f, "more than {MAX_COLUMNS} columns"),
+        }
+    }
+}
+
+impl std::error::Error for XlsxError {}
+
+// Check a sheet name against the Excel rules.
+pub fn validate_sheet_name(sheet_name: &str) -> Result<(), XlsxError> {
+    if sheet_name.is_empty() {
+        return Err(XlsxError::EmptySheetName);
+    }
+
+    if sheet_name.chars().count() > SHEET_NAME_MAX_LENGTH {
+        return Err(XlsxError::SheetNameTooLong(sheet_name.to_string()));
+    }
+
+    if let Some(c) = sheet_name
+        .chars()
+        .find(|c| SHEET_NAME_FORBIDDEN_CHARACTERS.contains(c))
+    {
+        return Err(XlsxError::InvalidSheetNameCharacter(
+            sheet_name.to_string(),
+            c,
+        ));
+    }
+
+    if sheet_name.starts_with('\'') || sheet_name.ends_with('\'') {
+        return Err(XlsxError::SheetNameApostrophe(sheet_name.to_string()));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XlsxCell<'a> {
+    Empty,
+    Text(&'a str),
+    Number(f64),
+    Bool(bool),
+}
+
+pub struct XlsxWriter<W: Write> {
+    writer: W,
+    worksheet: Worksheet,
+    // Number of rows written.
+    nb_rows: usize,
+}
+
+impl<W: Write> XlsxWriter<W> {
+    // Start a workbook with a single sheet, the rows are then written one by one.
+    pub fn new(
+        writer: W,
+        sheet_name: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        validate_sheet_name(sheet_name)?;
+
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(sheet_name)?;
+
+        Ok(Self {
+            writer,
+            worksheet,
+            nb_rows: 0,
+        })
+    }
+
+    pub fn write_row(
+        &mut self,
+        cells: &[XlsxCell],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.nb_rows >= MAX_ROWS {
+            return Err(Box::new(XlsxError::TooManyRows));
+        }
+        if cells.len() > MAX_COLUMNS {
+            return Err(Box::new(XlsxError::TooManyColumns));
+        }
+
+        let row = RowNum::try_from(self.nb_rows)?;
+
+        for (index, cell) in cells.iter().enumerate() {
+            let column = ColNum::try_from(index)?;
+
+            match cell {
+                XlsxCell::Empty => {}
+                XlsxCell::Number(number) if number.is_finite() => {
+                    self.worksheet.write_number(row, column, *number)?;
+                }
+                // NaN and infinities are not valid cell values.
+                XlsxCell::Number(number) => {
+                    self.worksheet
+                        .write_string(row, column, number.to_string())?;
+                }
+                XlsxCell::Bool(boolean) => {
+                    self.worksheet.write_boolean(row, column, *boolean)?;
+                }
+                XlsxCell::Text(text) => {
+                    self.worksheet.write_string(row, column, *text)?;
+                }
+            }
+        }
+
+        self.nb_rows += 1;
+
+        Ok(())
+    }
+
+    // Write the workbook, return the inner writer.
+    pub fn finish(mut self) -> Result<W, Box<dyn std::error::Error + Send + Sync>> {
+        let mut workbook = Workbook::new();
+        workbook.push_worksheet(self.worksheet);
+
+        self.writer.write_all(&workbook.save_to_buffer()?)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+#[path = "xlsx_tests.rs"]
+mod xlsx_tests;
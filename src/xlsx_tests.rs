@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::too_many_lines
+    )]
+
+    use crate::{test_utils::xlsx_part, xlsx::*};
+
+    #[test]
+    fn test_validate_sheet_name() {
+        assert_eq!(validate_sheet_name("products"), Ok(()));
+        assert_eq!(validate_sheet_name(&"a".repeat(31)), Ok(()));
+        assert_eq!(validate_sheet_name("l'acide"), Ok(()));
+
+        assert_eq!(validate_sheet_name(""), Err(XlsxError::EmptySheetName));
+        assert_eq!(
+            validate_sheet_name(&"a".repeat(32)),
+            Err(XlsxError::SheetNameTooLong("a".repeat(32)))
+        );
+        for c in ['[', ']', ':', '*', '?', '/', '\\'] {
+            assert_eq!(
+                validate_sheet_name(&format!("products{c}")),
+                Err(XlsxError::InvalidSheetNameCharacter(
+                    format!("products{c}"),
+                    c
+                ))
+            );
+        }
+        assert_eq!(
+            validate_sheet_name("'products"),
+            Err(XlsxError::SheetNameApostrophe("'products".to_string()))
+        );
+
+        assert_eq!(
+            XlsxWriter::new(Vec::new(), "products/storages")
+                .err()
+                .unwrap()
+                .downcast_ref::<XlsxError>(),
+            Some(&XlsxError::InvalidSheetNameCharacter(
+                "products/storages".to_string(),
+                '/'
+            ))
+        );
+    }
+
+    #[test]
+    fn test_xlsx_writer() {
+        let mut xlsx_writer = XlsxWriter::new(Vec::new(), "products").unwrap();
+
+        xlsx_writer
+            .write_row(&[XlsxCell::Text("NAME"), XlsxCell::Text("QUANTITY")])
+            .unwrap();
+        xlsx_writer
+            .write_row(&[
+                XlsxCell::Text("acide <chlorhydrique> & eau"),
+                XlsxCell::Number(1.5),
+                XlsxCell::Empty,
+                XlsxCell::Bool(true),
+            ])
+            .unwrap();
+
+        let bytes = xlsx_writer.finish().unwrap();
+        assert_eq!(&bytes[0..4], b"PK\x03\x04");
+
+        assert!(xlsx_part(&bytes, "xl/workbook.xml").contains(r#"<sheet name="products""#));
+
+        let sheet = xlsx_part(&bytes, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains(r#"<c r="B2"><v>1.5</v></c>"#));
+        assert!(sheet.contains(r#"<c r="D2" t="b"><v>1</v></c>"#));
+        assert!(!sheet.contains(r#"r="C2""#));
+
+        assert!(
+            xlsx_part(&bytes, "xl/sharedStrings.xml")
+                .contains(">acide &lt;chlorhydrique&gt; &amp; eau</t>")
+        );
+    }
+}